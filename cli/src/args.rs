@@ -18,7 +18,7 @@
 //!
 //! - `Cli`: The main structure representing the command-line interface.
 //! - `Commands`: An enumeration of different subcommands that the user can invoke.
-//! - `ChatArgs`, `CommitMessageArgs`, `BackgroundArgs`, `CreateIconArgs`, and `WishArgs`: Additional argument structures for each command.
+//! - `ChatArgs`, `CommitMessageArgs`, `BackgroundArgs`, `CreateIconArgs`, `WishArgs`, and `UndoArgs`: Additional argument structures for each command.
 //!
 //! Each subcommand can also be detailed and equipped with functionality specific to the command's purpose.
 
@@ -28,6 +28,7 @@ use crate::cli::createbackground::BackgroundArgs;
 use crate::cli::createicon::CreateIconArgs;
 use crate::cli::init::InitArgs;
 use crate::cli::knowledge::KnowledgeArgs;
+use crate::cli::undo::UndoArgs;
 use crate::cli::wish::WishArgs;
 use clap::{Parser, Subcommand};
 use clap_complete::aot::Shell;
@@ -72,6 +73,9 @@ pub enum Commands {
     /// Collect files from a specified directory and create a context for chat.
     Wish(WishArgs),
 
+    /// Restore the files changed by tools in the last run.
+    Undo(UndoArgs),
+
     /// Manage knowledge entries.
     #[clap(subcommand)]
     Knowledge(KnowledgeArgs),
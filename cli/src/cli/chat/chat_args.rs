@@ -20,6 +20,13 @@
 //! - `one_shot`: Sends a single message, exiting the session immediately.
 //! - `model`: Defines the AI model to be used during the chat session.
 //! - `silence`: Suppresses outputs of prior messages when loading sessions.
+//! - `explore`: Lets the AI read, list and search project files on demand.
+//! - `knowledge_mode`: Selects vector, keyword or hybrid search for `--knowledge`.
+//! - `collections`: Restricts `--knowledge` to some knowledge collections.
+//! - `knowledge_kinds`, `knowledge_path`, `knowledge_language`, `knowledge_since`: Restrict
+//!   `--knowledge` to some sources, paths, languages or recently added entries.
+//! - `knowledge_min_similarity`, `knowledge_max_tokens`: Limit the knowledge added per message.
+//! - `cite`: Lets answers cite the knowledge and lists the cited sources below them.
 //!
 //! ## Usage Example
 //!
@@ -45,7 +52,9 @@
 //! This structure is integral in setting up chat-related functionalities,
 //! thereby aiding developers in enhancing the interactive elements of Rusty Buddy.
 
+use chrono::{DateTime, Utc};
 use clap::Args;
+use rbchat::knowledge::{parse_date, PathPattern, SearchMode, SourceKind};
 use std::path::PathBuf;
 
 /// Structure representing command-line arguments for managing a chat session.
@@ -65,6 +74,7 @@ use std::path::PathBuf;
 /// - `one_shot`: Dispatch one message and exit.
 /// - `model`: Define the AI model for the chat session.
 /// - `silence`: Suppress the output of old messages.
+/// - `explore`: Let the AI read, list and search project files on demand.
 #[derive(Args)]
 pub struct ChatArgs {
     /// Start a new chat session
@@ -109,7 +119,54 @@ pub struct ChatArgs {
     #[arg(short = 'k', long = "knowledge")]
     pub knowledge: Option<Option<usize>>,
 
+    /// How to search the knowledge database: vector, keyword or hybrid
+    #[arg(long = "knowledge-mode", default_value = "hybrid")]
+    pub knowledge_mode: SearchMode,
+
+    /// Only search these knowledge collections, separated by commas
+    #[arg(long = "collection", value_delimiter = ',')]
+    pub collections: Vec<String>,
+
+    /// Only search these kinds of knowledge sources, separated by commas: context, internet or
+    /// local-files
+    #[arg(long = "knowledge-kind", value_delimiter = ',')]
+    pub knowledge_kinds: Vec<SourceKind>,
+
+    /// Only search knowledge whose path or URL matches this glob, e.g. `src/**`
+    #[arg(long = "knowledge-path")]
+    pub knowledge_path: Option<PathPattern>,
+
+    /// Only search knowledge of this language or file extension, e.g. `rust` or `md`
+    #[arg(long = "knowledge-language")]
+    pub knowledge_language: Option<String>,
+
+    /// Only search knowledge added since this date, e.g. `2025-03-01`
+    #[arg(long = "knowledge-since", value_parser = parse_date)]
+    pub knowledge_since: Option<DateTime<Utc>>,
+
+    /// Only add knowledge with at least this cosine similarity to the message, e.g. `0.3`.
+    /// Overrides `knowledge_min_similarity` of the configuration
+    #[arg(long = "knowledge-min-similarity")]
+    pub knowledge_min_similarity: Option<f32>,
+
+    /// Add at most this many estimated tokens of knowledge per message. Overrides
+    /// `knowledge_max_tokens` of the configuration
+    #[arg(long = "knowledge-max-tokens")]
+    pub knowledge_max_tokens: Option<usize>,
+
+    /// Number the knowledge added with `--knowledge`, ask the AI to cite it and list the cited
+    /// sources below each answer
+    #[arg(long = "cite", requires = "knowledge")]
+    pub cite: bool,
+
     /// Image file to add to the chat. Only works with vision capable models!                            
     #[arg(short = 'i', long = "image", value_hint = clap::ValueHint::FilePath)]
     pub image: Option<PathBuf>,
+
+    /// Let the AI read, list and search the files of the project on demand
+    ///
+    /// The AI gets the read-only tools `read_file`, `list_directory` and `grep`,
+    /// so large projects do not have to be loaded into the context up front.
+    #[arg(short = 'e', long = "explore")]
+    pub explore: bool,
 }
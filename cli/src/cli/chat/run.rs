@@ -50,6 +50,7 @@ use crate::cli::style::configure_mad_skin;
 use atty::Stream;
 use chrono::{DateTime, Local, Utc};
 use log::error;
+use rbchat::chat::citations::cited_sources;
 use rbchat::chat::command_registry::CommandRegistry;
 use rbchat::chat::commands::initialize_commands;
 use rbchat::chat::file_storage::DirectoryChatStorage;
@@ -57,17 +58,27 @@ use rbchat::chat::interface::{ChatStorage, MessageInfo, MessageRole};
 use rbchat::chat::service::ChatService;
 use rbchat::config;
 use rbchat::config::{get_chat_sessions_dir, Config};
-use rbchat::knowledge::{KnowledgeStore, StoreBuilder};
+use rbchat::knowledge::{KnowledgeFilter, KnowledgeStore, SearchMode, StoreBuilder};
 use rbchat::persona::{resolve_persona, Persona};
+use rbchat::tools::{initialize_read_only_tools, ToolRegistry};
 use std::borrow::Cow;
 use std::error::Error;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::path::PathBuf;
 use std::sync::Arc;
+use termimad::crossterm::cursor::{MoveToColumn, MoveUp};
+use termimad::crossterm::queue;
+use termimad::crossterm::terminal::{Clear, ClearType};
+use termimad::terminal_size;
+use unicode_width::UnicodeWidthStr;
 
 struct Services {
     chat_service: ChatService,
     knowledge_store: Arc<dyn KnowledgeStore>,
+    knowledge_mode: SearchMode,
+    knowledge_filter: KnowledgeFilter,
+    /// Lets the model explore the project with the read-only tools
+    explore: bool,
 }
 
 /// Runs the chat application, initializing the necessary components,
@@ -85,16 +96,44 @@ pub async fn run_chat(args: ChatArgs) -> Result<(), Box<dyn Error>> {
         .as_deref()
         .unwrap_or(config.ai.chat_model.as_str());
 
+    // Only read-only tools are offered in chat, so nothing has to be approved
+    let mut tools = ToolRegistry::new();
+    initialize_read_only_tools(&mut tools);
+
     let mut services = Services {
         chat_service: ChatService::builder()
             .model_name(model_name)
             .storage(Box::new(storage))
             .persona(persona.clone())
             .directory(args.directory)
+            .tool_registry(tools)
             .build()?,
         knowledge_store: StoreBuilder::new().build().await?,
+        knowledge_mode: args.knowledge_mode,
+        knowledge_filter: KnowledgeFilter {
+            collections: args.collections,
+            kinds: args.knowledge_kinds,
+            path: args.knowledge_path,
+            language: args.knowledge_language,
+            added_after: args.knowledge_since,
+        },
+        explore: args.explore,
     };
 
+    // Flags override the configured limits. Hits of a keyword search have no similarity.
+    let mut limits = services.chat_service.knowledge_limits();
+    if let Some(min_similarity) = args.knowledge_min_similarity {
+        limits.min_similarity = Some(min_similarity);
+    }
+    if args.knowledge_mode == SearchMode::Keyword {
+        limits.min_similarity = None;
+    }
+    if let Some(max_tokens) = args.knowledge_max_tokens {
+        limits.max_tokens = Some(max_tokens);
+    }
+    services.chat_service.set_knowledge_limits(limits);
+    services.chat_service.set_cite_knowledge(args.cite);
+
     handle_session(
         &mut services.chat_service,
         args.new,
@@ -143,7 +182,7 @@ fn handle_session(
     load_name: &Option<String>,
 ) -> Result<(), Box<dyn Error>> {
     if start_new {
-        chat_service.setup_context();
+        setup_context(chat_service);
     } else {
         handle_session_loading(chat_service, continue_last, load_name)?;
     }
@@ -163,24 +202,36 @@ fn handle_session_loading(
             }
             _ => {
                 eprintln!("No previous session found. Starting a new chat.");
-                chat_service.setup_context();
+                setup_context(chat_service);
             }
         }
     } else if let Some(session_name) = load_name {
         eprintln!("Loading session: {}", session_name);
         chat_service.load_history(session_name)?;
     } else {
-        chat_service.setup_context();
+        setup_context(chat_service);
     }
     Ok(())
 }
 
+// Loads the context and tells the user if files did not fit into the model's window
+fn setup_context(chat_service: &mut ChatService) {
+    chat_service.setup_context();
+    let report = chat_service.context_report();
+    if !report.is_complete() {
+        eprint!("{}", report);
+    }
+}
+
 // Function to print loaded messages
 fn print_loaded_messages(chat_service: &ChatService) {
     let is_terminal = is_output_to_terminal();
+    // Knowledge seen so far, to list the sources cited by the answers
+    let mut knowledge = Vec::new();
 
     chat_service.process_messages(|msg| {
         match msg.role {
+            MessageRole::Knowledge => knowledge.push(msg.clone()),
             MessageRole::User => {
                 let timestamp = msg
                     .info
@@ -230,6 +281,25 @@ fn print_loaded_messages(chat_service: &ChatService) {
                     msg.content.as_str(),
                     is_terminal,
                 );
+                let sources = cited_sources(&knowledge, &msg.content);
+                if !sources.is_empty() {
+                    print!("{}", sources);
+                }
+            }
+            MessageRole::System => {
+                // Summaries of compacted turns stand in for the turns they replaced
+                if let Some(MessageInfo::SummaryInfo {
+                    model, timestamp, ..
+                }) = &msg.info
+                {
+                    print_with_optional_formatting(
+                        "Summary",
+                        model,
+                        timestamp,
+                        msg.content.as_str(),
+                        is_terminal,
+                    );
+                }
             }
             _ => {}
         }
@@ -390,7 +460,7 @@ async fn send_and_display_response(
     image_path: &Option<PathBuf>,
 ) -> Result<(), Box<dyn Error>> {
     let is_terminal = is_output_to_terminal();
-    let spinner = if is_terminal {
+    let mut spinner = if is_terminal {
         Some(start_spinner())
     } else {
         None
@@ -398,35 +468,67 @@ async fn send_and_display_response(
     if let Some(knowledge) = knowledge {
         let knowledge = services
             .knowledge_store
-            .query_knowledge(user_input.clone(), knowledge.unwrap_or(10))
+            .query_knowledge(
+                user_input.clone(),
+                knowledge.unwrap_or(10),
+                services.knowledge_mode,
+                &services.knowledge_filter,
+            )
             .await?;
-        services.chat_service.add_knowledge(knowledge).await?;
+        let report = services.chat_service.add_knowledge(knowledge).await?;
+        // Show which knowledge was added, without the spinner running over the report
+        if let Some(spin) = spinner.take() {
+            stop_spinner(spin);
+        }
+        eprint!("{}", report);
+        if is_terminal {
+            spinner = Some(start_spinner());
+        }
     }
+    // Print the AI's response as it arrives; the spinner runs until the first delta
+    let mut streamed = String::new();
+    let mut header_printed = false;
     let result = services
         .chat_service
-        .send_message(user_input, image_path, false)
-        .await;
-    let response = match result {
-        Ok(response) => response,
-        Err(err) => {
-            if let Some(spin) = spinner {
+        .send_message_stream(user_input, image_path, services.explore, |delta| {
+            if let Some(spin) = spinner.take() {
                 stop_spinner(spin);
             }
-            return Err(err);
-        }
-    };
+            if !header_printed {
+                print_response_header(persona.name.as_str(), model, &Utc::now(), is_terminal);
+                header_printed = true;
+            }
+            print!("{}", delta);
+            io::stdout().flush().ok();
+            streamed.push_str(delta);
+        })
+        .await;
     if let Some(spin) = spinner {
         stop_spinner(spin);
     }
-
-    // Always print the AI's response
-    print_with_optional_formatting(
-        persona.name.as_str(),
-        model,
-        &Utc::now(),
-        response.as_str(),
-        is_terminal,
-    );
+    let answer = match result {
+        Ok(answer) => answer,
+        Err(e) => {
+            if header_printed {
+                println!();
+            }
+            return Err(e);
+        }
+    };
+    if !header_printed {
+        print_response_header(persona.name.as_str(), model, &Utc::now(), is_terminal);
+    }
+    // The raw text is replaced by the answer rendered as markdown, unless part of it
+    // scrolled out of view and can no longer be erased
+    if is_terminal && erase_streamed_text(&streamed)? {
+        configure_mad_skin().print_text(&answer);
+    } else {
+        println!();
+    }
+    let sources = services.chat_service.cited_sources(&answer);
+    if !sources.is_empty() {
+        print!("{}", sources);
+    }
 
     // Print statistics only if output is to terminal
     if is_terminal {
@@ -436,6 +538,41 @@ async fn send_and_display_response(
     Ok(())
 }
 
+fn print_response_header(persona: &str, model: &str, timestamp: &DateTime<Utc>, formatted: bool) {
+    let local_time: DateTime<Local> = timestamp.with_timezone(&Local);
+    let time = local_time.format("%Y-%m-%d %H:%M:%S").to_string();
+    let header = format!("---\n# AI Persona: {} Model: {} @{}", persona, model, time);
+    if formatted {
+        configure_mad_skin().print_text(&header);
+    } else {
+        println!("{}", header);
+    }
+}
+
+// Moves the cursor back to the start of the text streamed to the terminal and clears it.
+// Lines longer than the terminal is wide take several rows. Returns false, leaving the text,
+// if it is taller than the terminal, as the rows scrolled out of view cannot be reached.
+fn erase_streamed_text(text: &str) -> io::Result<bool> {
+    let (width, height) = terminal_size();
+    let columns = usize::from(width.max(1));
+    let rows: usize = text
+        .split('\n')
+        .map(|line| line.width().div_ceil(columns).max(1))
+        .sum();
+    if rows >= usize::from(height) {
+        return Ok(false);
+    }
+    let mut stdout = io::stdout();
+    queue!(stdout, MoveToColumn(0))?;
+    if rows > 1 {
+        let up = u16::try_from(rows - 1).unwrap_or(u16::MAX);
+        queue!(stdout, MoveUp(up))?;
+    }
+    queue!(stdout, Clear(ClearType::FromCursorDown))?;
+    stdout.flush()?;
+    Ok(true)
+}
+
 fn get_user_input_from_option_or_stdin(
     input_message: Option<String>,
 ) -> Result<String, Box<dyn Error>> {
@@ -0,0 +1,40 @@
+//! This module lets the user edit a text in their own editor. The text is written to a
+//! temporary file, which is opened in the editor named by `$VISUAL` or `$EDITOR`, falling
+//! back to `vi`. Once the editor exits, the content of the file is returned.
+//!
+//! ## Example Usage
+//!
+//! ```rust
+//! use crate::cli::editor::edit_in_external_editor;
+//!
+//! let edited = edit_in_external_editor("fn main() {}\n")?;
+//! println!("{}", edited);
+//! ```
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+use tempfile::NamedTempFile;
+
+/// Opens `content` in the user's editor and returns the edited text.
+pub fn edit_in_external_editor(content: &str) -> Result<String, Box<dyn Error>> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let temp_file = NamedTempFile::new()?;
+    fs::write(temp_file.path(), content)?;
+
+    // The editor setting may carry arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(parts)
+        .arg(temp_file.path())
+        .status()
+        .map_err(|e| format!("Failed to launch editor '{}': {}", program, e))?;
+    if !status.success() {
+        return Err(format!("Editor '{}' exited with {}", program, status).into());
+    }
+
+    Ok(fs::read_to_string(temp_file.path())?)
+}
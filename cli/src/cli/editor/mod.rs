@@ -16,6 +16,7 @@
 //! - **Multiline Input:** Allow users to provide multiple lines of input.
 //! - **Password Input:** Securely gather sensitive data while masking input.
 //! - **General User Input:** Capture simple strings from users.
+//! - **External Editor:** Edit a text in the editor configured in `$VISUAL` or `$EDITOR`.
 //!
 //! ## Example Usages
 //!
@@ -43,11 +44,13 @@
 //! In these examples, you can see how each function is tailored to
 //! retrieve user input efficiently, enhancing the overall usability of
 //! Rusty Buddy.
+mod external_editor;
 mod filename_input_editor;
 mod multiline_editor;
 mod password_input_editor;
 mod user_input_editor;
 
+pub use external_editor::edit_in_external_editor;
 pub use filename_input_editor::get_filename_input;
 pub use multiline_editor::get_multiline_input;
 pub use password_input_editor::get_password_input;
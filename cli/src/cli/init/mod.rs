@@ -114,6 +114,7 @@ pub async fn run_init_command(choose_persona: bool) -> Result<(), Box<dyn Error>
                 selected_persona
             );
         }
+        AIBackend::Anthropic => unreachable!("init does not offer the Anthropic backend"),
     }
 
     Ok(())
@@ -264,11 +265,13 @@ embedding_model = "{}"
 name = "openai_fast"
 api_name = "gpt-4o-mini"
 backend = "OpenAI"
+context_window = 128000
 
 [[models]]
 name = "openai_complex"
 api_name = "gpt-4o"
 backend = "OpenAI"
+context_window = 128000
 
 [[models]]
 name = "openai_embedding"
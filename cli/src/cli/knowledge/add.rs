@@ -1,11 +1,16 @@
 use crate::cli::knowledge::knowledge_args::AddArgs;
 use log::{info, warn};
-use rbchat::knowledge::{ConnectionMode, DataSource, EmbeddingData, KnowledgeStore, StoreBuilder};
-use std::borrow::Cow;
+use rbchat::config;
+use rbchat::extract::{extract_file, ExtractorRegistry};
+use rbchat::knowledge::{
+    crawl_knowledge, file_modified, index_document, Chunker, ConnectionMode, CrawlOptions,
+    DataSource, KnowledgeStore, StoreBuilder,
+};
+use reqwest::header::CONTENT_TYPE;
 use std::error::Error;
-use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
+use tokio::sync::Semaphore;
 use tokio::task::JoinHandle;
 use walkdir::WalkDir;
 
@@ -15,14 +20,29 @@ pub async fn add(add: AddArgs) -> Result<(), Box<dyn Error>> {
         .build()
         .await?;
 
+    let collection: Arc<str> = add.collection.into();
     if let Some(dir) = add.dir {
-        add_directory_to_knowledge(&dir, &store).await?;
+        // As many files are embedded at once as `concurrency` in `[embedding]` allows
+        let concurrency = config::CONFIG.lock().unwrap().embedding.concurrency.max(1);
+        add_directory_to_knowledge(&dir, &store, &collection, concurrency).await?;
     }
     if let Some(file) = add.file {
-        add_file_to_knowledge(&file, &store).await?;
+        add_file_to_knowledge(&file, &store, &collection).await?;
     }
     if let Some(url) = add.url {
-        add_url_to_knowledge(&url, &store).await?;
+        if add.crawl {
+            let options = CrawlOptions {
+                max_depth: add.depth,
+                max_pages: add.max_pages,
+                include: add.include,
+                exclude: add.exclude,
+                respect_robots: !add.ignore_robots,
+                use_sitemap: !add.no_sitemap,
+            };
+            crawl_site(&url, &store, &collection, options).await?;
+        } else {
+            add_url_to_knowledge(&url, &store, &collection).await?;
+        }
     }
     Ok(())
 }
@@ -30,17 +50,26 @@ pub async fn add(add: AddArgs) -> Result<(), Box<dyn Error>> {
 async fn add_directory_to_knowledge(
     directory: &PathBuf,
     store: &Arc<dyn KnowledgeStore>,
+    collection: &Arc<str>,
+    concurrency: usize,
 ) -> Result<(), Box<dyn Error>> {
-    let tasks: Vec<JoinHandle<()>> = WalkDir::new(directory)
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let mut tasks: Vec<JoinHandle<()>> = Vec::new();
+    let files = WalkDir::new(directory)
         .into_iter()
         .filter_map(Result::ok)
-        .filter(|entry| entry.path().is_file())
-        .map(|entry| {
-            let store = store.clone();
-            let path = entry.path().to_owned();
-            tokio::spawn(async move { process_file(&path, &store).await })
-        })
-        .collect();
+        .filter(|entry| entry.path().is_file());
+    for entry in files {
+        // Wait for a running file to finish before the next one is started
+        let permit = permits.clone().acquire_owned().await?;
+        let store = store.clone();
+        let collection = collection.clone();
+        let path = entry.path().to_owned();
+        tasks.push(tokio::spawn(async move {
+            process_file(&path, &store, &collection).await;
+            drop(permit);
+        }));
+    }
 
     for task in tasks {
         task.await?;
@@ -52,72 +81,93 @@ async fn add_directory_to_knowledge(
 async fn add_file_to_knowledge(
     file: &PathBuf,
     store: &Arc<dyn KnowledgeStore>,
+    collection: &str,
 ) -> Result<(), Box<dyn Error>> {
-    process_file(file, store).await;
+    process_file(file, store, collection).await;
     Ok(())
 }
 
-async fn process_file(file_path: &PathBuf, store: &Arc<dyn KnowledgeStore>) {
+async fn process_file(file_path: &PathBuf, store: &Arc<dyn KnowledgeStore>, collection: &str) {
     info!("Processing file: {}", file_path.display());
 
-    // Attempt to read file content
-    let content = match fs::read_to_string(file_path) {
+    // Attempt to read the text of the file, PDFs and other documents are extracted
+    let content = match extract_file(file_path) {
         Ok(content) => content,
         Err(e) => {
-            warn!("Failed to read file '{}': {}", file_path.display(), e);
-            return;
-        }
-    };
-
-    // Generate an embedding using Cow to avoid unnecessary cloning
-    let embedding = match store.get_embedding(Cow::Borrowed(&content)).await {
-        Ok(embedding) => embedding,
-        Err(e) => {
-            warn!(
-                "Failed to generate embedding for file '{}': {}",
-                file_path.display(),
-                e
-            );
+            warn!("{}", e);
             return;
         }
     };
 
-    // Store the knowledge without cloning the path or content unnecessarily
-    if let Err(e) = store
-        .store_knowledge(EmbeddingData {
-            data_source: DataSource::LocalFiles(file_path.to_string_lossy().into_owned()),
-            embedding: *embedding,
-            content: Some(content), // Transfer ownership here
-            metadata: None,
-        })
-        .await
+    // Every chunk of the file is embedded and stored as its own record
+    let data_source = DataSource::LocalFiles(file_path.to_string_lossy().into_owned());
+    let modified = file_modified(file_path);
+    match index_document(
+        store.as_ref(),
+        &Chunker::default(),
+        collection,
+        data_source,
+        &content,
+        modified,
+    )
+    .await
     {
-        warn!(
+        Ok(chunks) => info!("Stored {} chunks of '{}'", chunks, file_path.display()),
+        Err(e) => warn!(
             "Failed to store knowledge for file '{}': {}",
             file_path.display(),
             e
-        );
+        ),
     }
 }
 
 async fn add_url_to_knowledge(
     url: &str,
     store: &Arc<dyn KnowledgeStore>,
+    collection: &str,
 ) -> Result<(), Box<dyn Error>> {
     println!("Processing URL: {}", url);
 
-    // Fetch and process the URL content here using Cow to avoid cloning until necessary
-    let content = reqwest::get(url).await?.text().await?;
-    let embedding = store.get_embedding(Cow::Borrowed(&content)).await?;
+    // The page is read as the type the server declares, e.g. HTML or PDF
+    let response = reqwest::get(url).await?.error_for_status()?;
+    let mime_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let bytes = response.bytes().await?;
+    let content = ExtractorRegistry::default().extract(&bytes, mime_type.as_deref(), url)?;
+    let chunks = index_document(
+        store.as_ref(),
+        &Chunker::default(),
+        collection,
+        DataSource::Internet(url.to_string()),
+        &content,
+        None,
+    )
+    .await?;
+    println!("Stored {} chunks of {}", chunks, url);
 
-    store
-        .store_knowledge(EmbeddingData {
-            data_source: DataSource::Internet(url.to_string()),
-            embedding: *embedding,
-            content: Some(content), // Transfer ownership here
-            metadata: None,
-        })
-        .await?;
+    Ok(())
+}
+
+async fn crawl_site(
+    url: &str,
+    store: &Arc<dyn KnowledgeStore>,
+    collection: &str,
+    options: CrawlOptions,
+) -> Result<(), Box<dyn Error>> {
+    println!("Crawling {}", url);
+
+    let report = crawl_knowledge(
+        store.as_ref(),
+        &Chunker::default(),
+        collection,
+        url,
+        options,
+    )
+    .await?;
+    println!("{}", report);
 
     Ok(())
 }
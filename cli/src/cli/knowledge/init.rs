@@ -4,21 +4,25 @@ use rbchat::config;
 use rbchat::config::Config;
 use rbchat::context::{load_files_into_context, ContextConsumer};
 use rbchat::knowledge::DataSource::Context;
-use rbchat::knowledge::{ConnectionMode, EmbeddingData, KnowledgeStore, StoreBuilder};
+use rbchat::knowledge::{
+    file_modified, sync_document, Chunker, ConnectionMode, DataSource, IndexedSource,
+    KnowledgeStore, StoreBuilder, SyncOutcome,
+};
 use rbchat::persona::{resolve_persona, Persona};
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::env;
 use std::error::Error;
+use std::path::Path;
 use std::sync::Arc;
 use tokio::task::JoinHandle;
 
-/// Bounded channel size as a constant for readability.
-const CHANNEL_SIZE: usize = 10;
-const MAX_THREADS: usize = 10; // Maximum number of threads
-
 /// Job type alias for clarity
 type Job = (String, String);
 
+/// The sources already in the collection, files that did not change since are not embedded again.
+type Indexed = Arc<HashMap<DataSource, IndexedSource>>;
+
 /// Fetch configuration settings as a clone.
 fn get_config() -> Config {
     let config = config::CONFIG.lock().unwrap();
@@ -32,11 +36,21 @@ pub async fn init(args: InitArgs) -> Result<(), Box<dyn Error>> {
         .connection_mode(ConnectionMode::Persistent)
         .build()
         .await?;
-    let (sender, receiver) = async_channel::bounded(CHANNEL_SIZE);
+    // As many files are queued as are embedded at once, set by `concurrency` in `[embedding]`
+    let workers = config.embedding.concurrency.max(1);
+    let (sender, receiver) = async_channel::bounded(workers);
     let persona = resolve_persona(&args.persona, config.default_persona.as_str())?;
+    let indexed: Indexed = Arc::new(
+        db.list_sources()
+            .await?
+            .into_iter()
+            .filter(|source| source.collection == args.collection)
+            .map(|source| (source.data_source.clone(), source))
+            .collect(),
+    );
 
     // Spawn workers for processing jobs.
-    let handles = spawn_workers(receiver, db);
+    let handles = spawn_workers(workers, receiver, db, indexed, args.collection.into());
 
     // Load and process files
     process_files(sender, persona)?;
@@ -48,47 +62,55 @@ pub async fn init(args: InitArgs) -> Result<(), Box<dyn Error>> {
 }
 
 /// Spawn workers that will handle processing jobs concurrently.
-fn spawn_workers(receiver: Receiver<Job>, db: Arc<dyn KnowledgeStore>) -> Vec<JoinHandle<()>> {
-    (0..MAX_THREADS)
+fn spawn_workers(
+    workers: usize,
+    receiver: Receiver<Job>,
+    db: Arc<dyn KnowledgeStore>,
+    indexed: Indexed,
+    collection: Arc<str>,
+) -> Vec<JoinHandle<()>> {
+    (0..workers)
         .map(|_| {
             let receiver_clone = receiver.clone();
             let db_clone = db.clone();
+            let indexed_clone = indexed.clone();
+            let collection_clone = collection.clone();
 
             tokio::spawn(async move {
-                process_jobs(receiver_clone, db_clone).await;
+                process_jobs(receiver_clone, db_clone, indexed_clone, collection_clone).await;
             })
         })
         .collect()
 }
 
-/// Process jobs received from the channel by retrieving embeddings and saving them to the database.
-async fn process_jobs(receiver: Receiver<Job>, db: Arc<dyn KnowledgeStore>) {
+/// Process jobs received from the channel by embedding the chunks of each new or changed file
+/// and saving them to the database.
+async fn process_jobs(
+    receiver: Receiver<Job>,
+    db: Arc<dyn KnowledgeStore>,
+    indexed: Indexed,
+    collection: Arc<str>,
+) {
+    let chunker = Chunker::default();
     while let Ok((filename, content)) = receiver.recv().await {
-        eprintln!("Processing File: {}", filename);
-
-        // Now handle success or failure from embedding processing
-        let embedding = match db.get_embedding(Cow::Owned(content)).await {
-            Ok(embedding) => {
-                // Assemble the embedding data (once get_embedding has been successful)
-                Some(EmbeddingData {
-                    data_source: Context(filename.clone()),
-                    content: None,
-                    embedding: embedding.to_vec(),
-                    metadata: None,
-                })
-            }
-            Err(err) => {
-                // Handle the error from the embedding service
-                eprintln!("Failed to get embedding for {}: {}", filename, err);
-                None
-            }
-        };
-        if let Some(embedding) = embedding {
-            // At this point, the embedding has been processed; now we can make the db call.
-
-            if let Err(e) = db.store_knowledge(embedding).await {
-                eprintln!("Failed to store embedding for {} {}", filename, e);
-            }
+        let data_source = Context(filename.clone());
+        let previous = indexed.get(&data_source);
+        let modified = file_modified(Path::new(&filename));
+
+        match sync_document(
+            db.as_ref(),
+            &chunker,
+            &collection,
+            previous,
+            data_source,
+            &content,
+            modified,
+        )
+        .await
+        {
+            Ok(SyncOutcome::Unchanged) => {}
+            Ok(_) => eprintln!("Processed File: {}", filename),
+            Err(err) => eprintln!("Failed to store embeddings for {}: {}", filename, err),
         }
     }
 }
@@ -121,4 +143,8 @@ impl ContextConsumer for Consumer {
         tokio::task::block_in_place(|| self.sender.send_blocking(job))?;
         Ok(())
     }
+
+    fn skip(&mut self, filename: Cow<str>, error: Box<dyn Error>) {
+        eprintln!("Skipping {}: {}", filename, error);
+    }
 }
@@ -4,7 +4,9 @@
 //! It utilizes the `clap` library for parsing, providing a clear interface for users specifying
 //! how to manage knowledge entries.
 
+use chrono::{DateTime, Utc};
 use clap::{Args, Subcommand};
+use rbchat::knowledge::{parse_date, PathPattern, SearchMode, SourceKind, DEFAULT_COLLECTION};
 use std::path::PathBuf;
 
 #[derive(Subcommand)]
@@ -12,6 +14,66 @@ pub enum KnowledgeArgs {
     Init(InitArgs),
     Search(SearchArgs),
     Add(AddArgs), // New subcommand for adding knowledge
+    /// Re-index changed files and remove the ones that no longer exist
+    Sync(SyncArgs),
+    /// List the sources stored in the knowledge database
+    List(ListArgs),
+    /// Show the number of records, the embedding model and the size of the knowledge database
+    Stats,
+    /// Remove a source from the knowledge database
+    Remove(RemoveArgs),
+    /// Remove everything from the knowledge database
+    Purge(PurgeArgs),
+    /// Embed the knowledge database again with the configured embedding model
+    Reindex,
+    /// Write the knowledge database to a portable JSON Lines file
+    Export(ExportArgs),
+    /// Add the knowledge of a file written by `knowledge export`
+    Import(ImportArgs),
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Only list sources of this kind: context, internet or local-files
+    #[arg(short, long)]
+    pub kind: Option<SourceKind>,
+
+    /// Only list sources whose path or URL starts with this prefix
+    #[arg(short, long)]
+    pub prefix: Option<String>,
+
+    /// Only list sources of this collection
+    #[arg(short, long)]
+    pub collection: Option<String>,
+}
+
+#[derive(Args)]
+pub struct RemoveArgs {
+    /// The source as printed by `knowledge list`, or its path or URL
+    pub source: String,
+
+    /// Only remove the source from this collection
+    #[arg(short, long)]
+    pub collection: Option<String>,
+}
+
+#[derive(Args)]
+pub struct PurgeArgs {
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// The file to write, e.g. `knowledge.jsonl`
+    pub file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// A file written by `knowledge export`
+    pub file: PathBuf,
 }
 
 #[derive(Args)]
@@ -19,6 +81,24 @@ pub struct SearchArgs {
     pub search: String,
     #[arg(short = 'n', long, default_value = "10")]
     pub limit: Option<usize>,
+    /// How to search: vector, keyword or hybrid
+    #[arg(long, default_value = "hybrid")]
+    pub mode: SearchMode,
+    /// Only search these collections, separated by commas
+    #[arg(short, long, value_delimiter = ',')]
+    pub collection: Vec<String>,
+    /// Only search these kinds of sources, separated by commas: context, internet or local-files
+    #[arg(short, long, value_delimiter = ',')]
+    pub kind: Vec<SourceKind>,
+    /// Only search files whose path or URL matches this glob, e.g. `src/**`
+    #[arg(short, long)]
+    pub path: Option<PathPattern>,
+    /// Only search files of this language or file extension, e.g. `rust` or `md`
+    #[arg(short, long)]
+    pub language: Option<String>,
+    /// Only search entries added since this date, e.g. `2025-03-01`
+    #[arg(short, long, value_parser = parse_date)]
+    pub since: Option<DateTime<Utc>>,
 }
 
 #[derive(Args)]
@@ -26,6 +106,21 @@ pub struct InitArgs {
     /// Specify a persona for the knowledge initialization
     #[arg(short, long)]
     pub persona: Option<String>,
+
+    /// The collection to store the project files in
+    #[arg(short, long, default_value = DEFAULT_COLLECTION)]
+    pub collection: String,
+}
+
+#[derive(Args)]
+pub struct SyncArgs {
+    /// Specify the persona whose file types are synced, like for `init`
+    #[arg(short, long)]
+    pub persona: Option<String>,
+
+    /// The collection the project files were stored in by `init`
+    #[arg(short, long, default_value = DEFAULT_COLLECTION)]
+    pub collection: String,
 }
 
 #[derive(Args)] // New AddArgs struct for the add subcommand
@@ -41,4 +136,36 @@ pub struct AddArgs {
     /// Add a webpage to the knowledge database
     #[arg(short, long)]
     pub url: Option<String>,
+
+    /// Follow the links of the webpage and add the pages of its site
+    #[arg(long, requires = "url")]
+    pub crawl: bool,
+
+    /// Number of links followed from the webpage when crawling
+    #[arg(long, default_value_t = 2)]
+    pub depth: usize,
+
+    /// Maximum number of pages added when crawling
+    #[arg(long, default_value_t = 50)]
+    pub max_pages: usize,
+
+    /// Only crawl URLs matching these globs, e.g. `*/guide/*`
+    #[arg(long, value_delimiter = ',')]
+    pub include: Vec<PathPattern>,
+
+    /// Do not crawl URLs matching these globs
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<PathPattern>,
+
+    /// Crawl pages the site's robots.txt excludes
+    #[arg(long)]
+    pub ignore_robots: bool,
+
+    /// Do not crawl the pages listed in the site's sitemaps
+    #[arg(long)]
+    pub no_sitemap: bool,
+
+    /// The collection to add the knowledge to, e.g. `api-docs`
+    #[arg(short, long, default_value = DEFAULT_COLLECTION)]
+    pub collection: String,
 }
@@ -0,0 +1,189 @@
+//! This module implements the subcommands that inspect and maintain the knowledge database:
+//! `list`, `stats`, `remove`, `purge`, `reindex`, `export` and `import`.
+
+use crate::cli::editor::get_user_input;
+use crate::cli::knowledge::knowledge_args::{
+    ExportArgs, ImportArgs, ListArgs, PurgeArgs, RemoveArgs,
+};
+use rbchat::knowledge::{
+    export_knowledge, import_knowledge, reindex_knowledge, Chunker, ConnectionMode, StoreBuilder,
+};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+/// Prints the stored sources, optionally filtered by kind, path or URL prefix and collection.
+pub async fn list(args: ListArgs) -> Result<(), Box<dyn Error>> {
+    let store = StoreBuilder::new().build().await?;
+    let sources = store.list_sources().await?;
+    let matching = sources.iter().filter(|source| {
+        args.kind
+            .is_none_or(|kind| source.data_source.kind() == kind)
+            && args
+                .prefix
+                .as_ref()
+                .is_none_or(|prefix| source.data_source.name().starts_with(prefix.as_str()))
+            && args
+                .collection
+                .as_ref()
+                .is_none_or(|collection| &source.collection == collection)
+    });
+
+    let mut count = 0;
+    for source in matching {
+        match source.modified {
+            Some(modified) => println!(
+                "[{}] {} ({} chunks, modified {})",
+                source.collection,
+                source.data_source,
+                source.chunks,
+                modified.format("%Y-%m-%d %H:%M:%S")
+            ),
+            None => println!(
+                "[{}] {} ({} chunks)",
+                source.collection, source.data_source, source.chunks
+            ),
+        }
+        count += 1;
+    }
+    if count == 0 {
+        println!("No sources found.");
+    }
+    Ok(())
+}
+
+/// Prints the size of the knowledge database and the embedding settings it was built with.
+pub async fn stats() -> Result<(), Box<dyn Error>> {
+    let store = StoreBuilder::new().build().await?;
+    let stats = store.stats().await?;
+    println!("Sources:         {}", stats.sources);
+    println!("Records:         {}", stats.records);
+    println!("Collections:     {}", stats.collections.join(", "));
+    match &stats.embedding {
+        Some(embedding) => println!(
+            "Embedding model: {} ({} dimensions)",
+            embedding.model, embedding.dimension
+        ),
+        None => println!("Embedding model: not recorded yet"),
+    }
+    println!("Size on disk:    {}", format_size(stats.size_on_disk));
+    if let Some(embedding) = stats
+        .embedding
+        .filter(|embedding| embedding.model != stats.configured_model)
+    {
+        println!(
+            "\nThe configured embedding model is '{}', but the knowledge was embedded with '{}'. \
+             Run `rusty-buddy knowledge reindex` to re-embed it.",
+            stats.configured_model, embedding.model
+        );
+    }
+    Ok(())
+}
+
+/// Removes the records of the source given as listed, e.g. `Internet.https://example.com`,
+/// or by its path or URL alone. Without a collection, the source is removed from all of them.
+pub async fn remove(args: RemoveArgs) -> Result<(), Box<dyn Error>> {
+    let store = StoreBuilder::new().build().await?;
+    let sources = store.list_sources().await?;
+    let matching: Vec<_> = sources
+        .iter()
+        .filter(|source| {
+            (source.data_source.to_string() == args.source
+                || source.data_source.name() == args.source)
+                && args
+                    .collection
+                    .as_ref()
+                    .is_none_or(|collection| &source.collection == collection)
+        })
+        .collect();
+    if matching.is_empty() {
+        return Err(format!("No source '{}' in the knowledge database.", args.source).into());
+    }
+
+    for source in matching {
+        store
+            .remove_knowledge(&source.collection, &source.data_source)
+            .await?;
+        println!(
+            "Removed {} from {} ({} chunks)",
+            source.data_source, source.collection, source.chunks
+        );
+    }
+    Ok(())
+}
+
+/// Removes every record from the knowledge database after asking for confirmation.
+pub async fn purge(args: PurgeArgs) -> Result<(), Box<dyn Error>> {
+    let store = StoreBuilder::new().build().await?;
+    if !args.yes {
+        let stats = store.stats().await?;
+        let answer = get_user_input(&format!(
+            "Remove all {} records of {} sources from the knowledge database? [y/N] ",
+            stats.records, stats.sources
+        ))?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            println!("Nothing was removed.");
+            return Ok(());
+        }
+    }
+
+    store.purge_knowledge().await?;
+    println!("The knowledge database is empty.");
+    Ok(())
+}
+
+/// Embeds every record again with the configured embedding model, e.g. after switching it.
+pub async fn reindex() -> Result<(), Box<dyn Error>> {
+    let store = StoreBuilder::new()
+        .connection_mode(ConnectionMode::Persistent)
+        .build()
+        .await?;
+    println!(
+        "Re-embedding the knowledge database with '{}'...",
+        store.embedding_model()
+    );
+    let report = reindex_knowledge(store.as_ref(), &Chunker::default()).await?;
+    println!("{}", report);
+    for data_source in &report.skipped {
+        println!("  - {}", data_source);
+    }
+    Ok(())
+}
+
+/// Writes every record with its embedding to a file that `import` reads on another machine.
+pub async fn export(args: ExportArgs) -> Result<(), Box<dyn Error>> {
+    let store = StoreBuilder::new().build().await?;
+    let file = File::create(&args.file)
+        .map_err(|e| format!("Failed to create '{}': {}", args.file.display(), e))?;
+    let records = export_knowledge(store.as_ref(), &mut BufWriter::new(file)).await?;
+    println!("Exported {} records to {}", records, args.file.display());
+    Ok(())
+}
+
+/// Adds the records of an exported file. They keep their embeddings, so nothing is embedded.
+pub async fn import(args: ImportArgs) -> Result<(), Box<dyn Error>> {
+    let file = File::open(&args.file)
+        .map_err(|e| format!("Failed to open '{}': {}", args.file.display(), e))?;
+    let store = StoreBuilder::new()
+        .connection_mode(ConnectionMode::Persistent)
+        .build()
+        .await?;
+    let report = import_knowledge(store.as_ref(), BufReader::new(file)).await?;
+    println!("{}", report);
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
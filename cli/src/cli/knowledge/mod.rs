@@ -5,8 +5,10 @@
 mod add;
 mod init;
 mod knowledge_args;
+mod manage;
 mod run;
 mod search;
+mod sync;
 
 pub use knowledge_args::KnowledgeArgs;
 pub use run::run_knowledge;
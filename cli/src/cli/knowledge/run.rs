@@ -1,7 +1,7 @@
 //! This module provides functionality for managing knowledge entries based
 //! on user-defined commands within the Rusty Buddy application.
 
-use crate::cli::knowledge::{add, init, search, KnowledgeArgs};
+use crate::cli::knowledge::{add, init, manage, search, sync, KnowledgeArgs};
 use std::error::Error;
 
 pub async fn run_knowledge(args: KnowledgeArgs) -> Result<(), Box<dyn Error>> {
@@ -15,6 +15,30 @@ pub async fn run_knowledge(args: KnowledgeArgs) -> Result<(), Box<dyn Error>> {
         KnowledgeArgs::Add(add) => {
             add::add(add).await?;
         }
+        KnowledgeArgs::Sync(sync) => {
+            sync::sync(sync).await?;
+        }
+        KnowledgeArgs::List(list) => {
+            manage::list(list).await?;
+        }
+        KnowledgeArgs::Stats => {
+            manage::stats().await?;
+        }
+        KnowledgeArgs::Remove(remove) => {
+            manage::remove(remove).await?;
+        }
+        KnowledgeArgs::Purge(purge) => {
+            manage::purge(purge).await?;
+        }
+        KnowledgeArgs::Reindex => {
+            manage::reindex().await?;
+        }
+        KnowledgeArgs::Export(export) => {
+            manage::export(export).await?;
+        }
+        KnowledgeArgs::Import(import) => {
+            manage::import(import).await?;
+        }
     }
 
     Ok(())
@@ -1,14 +1,26 @@
 use crate::cli::knowledge::knowledge_args::SearchArgs;
-use rbchat::knowledge::{KnowledgeResult, StoreBuilder};
+use rbchat::knowledge::{KnowledgeFilter, KnowledgeResult, StoreBuilder};
 use std::error::Error;
 
 pub async fn search(args: SearchArgs) -> Result<(), Box<dyn Error>> {
     let db = StoreBuilder::new().build().await?;
+    let filter = KnowledgeFilter {
+        collections: args.collection,
+        kinds: args.kind,
+        path: args.path,
+        language: args.language,
+        added_after: args.since,
+    };
     let knowledge: Vec<KnowledgeResult> = db
-        .query_knowledge(args.search.into(), args.limit.unwrap_or(10))
+        .query_knowledge(
+            args.search.into(),
+            args.limit.unwrap_or(10),
+            args.mode,
+            &filter,
+        )
         .await?;
     for piece in knowledge {
-        println!("{} {}", piece.data_source, piece.distance);
+        println!("{} {}", piece.location(), piece.score);
     }
     Ok(())
 }
@@ -0,0 +1,28 @@
+use crate::cli::knowledge::knowledge_args::SyncArgs;
+use rbchat::config;
+use rbchat::knowledge::{sync_knowledge, Chunker, ConnectionMode, StoreBuilder};
+use rbchat::persona::resolve_persona;
+use std::env;
+use std::error::Error;
+
+/// Re-indexes the project files that changed since they were added and removes the records of
+/// deleted files.
+pub async fn sync(args: SyncArgs) -> Result<(), Box<dyn Error>> {
+    let default_persona = config::CONFIG.lock().unwrap().default_persona.clone();
+    let persona = resolve_persona(&args.persona, default_persona.as_str())?;
+    let store = StoreBuilder::new()
+        .connection_mode(ConnectionMode::Persistent)
+        .build()
+        .await?;
+
+    let report = sync_knowledge(
+        store.as_ref(),
+        &Chunker::default(),
+        &args.collection,
+        &env::current_dir()?,
+        &persona.file_types,
+    )
+    .await?;
+    println!("{}", report);
+    Ok(())
+}
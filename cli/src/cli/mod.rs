@@ -13,6 +13,7 @@
 //! - **Slash Completer Module**: Implements auto-completion for slash commands in the chat interface, enhancing usability.
 //! - **Spinner Module**: Displays a visual spinner in the terminal during potentially long-running tasks, improving user experience by indicating processing.
 //! - **Style Module**: Configures terminal output styles for a better visual experience while using Rusty Buddy.
+//! - **Undo Module**: Restores the files changed by the last tool run from the change journal.
 //! - **Wish Module**: Implements functionality for users to express wishes for file and directory manipulations, utilizing AI to accomplish user requests.
 //!
 //! By organizing functionalities into distinct modules, Rusty Buddy makes it easy for users to extend and maintain the application while leveraging the power of AI to improve their development workflows.
@@ -26,4 +27,5 @@ pub mod knowledge;
 mod slash_completer;
 mod spinner;
 mod style;
+pub mod undo;
 pub mod wish;
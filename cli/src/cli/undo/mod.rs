@@ -0,0 +1,18 @@
+//! This module provides the `undo` command, which restores the files changed by tools.
+//!
+//! Every `wish --tools` invocation records the prior state of the files it changes in the change
+//! journal under `.rusty/journal`. Running `rusty-buddy undo` restores the files of the last run;
+//! running it again undoes the run before. With `--list` the recorded runs are printed instead.
+
+mod run;
+mod undo_args;
+
+pub use undo_args::UndoArgs;
+
+pub async fn run(args: UndoArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.list {
+        run::list_journal()
+    } else {
+        run::run_undo()
+    }
+}
@@ -0,0 +1,31 @@
+//! This module implements the `undo` command on top of the change journal of `rbchat`.
+
+use rbchat::config;
+use rbchat::tools::{list_runs, undo_last_run_summary};
+use std::error::Error;
+
+/// Restores the files changed by the most recent tool run.
+pub fn run_undo() -> Result<(), Box<dyn Error>> {
+    let journal_dir = config::get_journal_dir()?;
+    for line in undo_last_run_summary(&journal_dir, &config::get_project_root()?)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Prints the recorded runs, the one `undo` restores last.
+pub fn list_journal() -> Result<(), Box<dyn Error>> {
+    let runs = list_runs(&config::get_journal_dir()?)?;
+    if runs.is_empty() {
+        println!("No changes recorded.");
+    }
+    for run in runs.iter().rev() {
+        println!(
+            "{} {} ({} files)",
+            run.started.format("%Y-%m-%d %H:%M:%S"),
+            run.label,
+            run.entries.len()
+        );
+    }
+    Ok(())
+}
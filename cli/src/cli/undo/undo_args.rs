@@ -0,0 +1,14 @@
+//! This module defines the command-line arguments of the `undo` command using the Clap library.
+//!
+//! Without arguments the command restores the files changed by the last tool run. The `list`
+//! flag only shows the runs recorded in the change journal.
+
+use clap::Args;
+
+/// Command line arguments for undoing the file changes made by tools.
+#[derive(Args)]
+pub struct UndoArgs {
+    /// List the recorded runs instead of undoing the last one
+    #[arg(short, long)]
+    pub list: bool,
+}
@@ -0,0 +1,87 @@
+//! This module asks the user to review every file change the AI wants to make while
+//! fulfilling a wish. The planned change is shown as a colored unified diff, or in the
+//! external diff tool configured as `diff_tool`, before the user accepts, rejects or
+//! edits it. In a dry run the change is only printed as a unified diff.
+
+use crate::cli::editor::{edit_in_external_editor, get_user_input};
+use atty::Stream;
+use rbchat::config;
+use rbchat::tools::file_diff::show_diff_in_external_tool;
+use rbchat::tools::{render_diff, ChangeApprover, FileChange, Review};
+use std::error::Error;
+
+/// Prompts on the console before each file operation of a tool.
+pub struct ConsoleChangeApprover {
+    diff_tool: Option<String>,
+}
+
+impl ConsoleChangeApprover {
+    pub fn new(diff_tool: Option<String>) -> Self {
+        ConsoleChangeApprover { diff_tool }
+    }
+
+    /// Shows the change to the user. Returns the new content if it was modified in the diff tool.
+    fn show(&self, change: &FileChange) -> Result<Option<String>, Box<dyn Error>> {
+        if let (
+            Some(diff_tool),
+            FileChange::WriteFile {
+                path,
+                original: Some(_),
+                content,
+            },
+        ) = (&self.diff_tool, change)
+        {
+            let file = config::get_project_root()?.join(path);
+            let reviewed = show_diff_in_external_tool(diff_tool, &file.to_string_lossy(), content)?;
+            return Ok((reviewed != *content).then_some(reviewed));
+        }
+        print!("{}", render_diff(change, atty::is(Stream::Stdout)));
+        Ok(None)
+    }
+}
+
+impl ChangeApprover for ConsoleChangeApprover {
+    fn approve(&self, change: &FileChange) -> Result<bool, Box<dyn Error>> {
+        Ok(self.review(change)? != Review::Reject)
+    }
+
+    fn review(&self, change: &FileChange) -> Result<Review, Box<dyn Error>> {
+        let mut change = change.clone();
+        let mut edited = false;
+        loop {
+            if let (Some(reviewed), FileChange::WriteFile { content, .. }) =
+                (self.show(&change)?, &mut change)
+            {
+                *content = reviewed;
+                edited = true;
+            }
+
+            let answer = get_user_input(&format!(
+                "Apply this change to {}? (a)ccept / (r)eject / (e)dit: ",
+                change.path().display()
+            ))?;
+            match answer.to_lowercase().as_str() {
+                "a" | "accept" | "y" | "yes" => {
+                    return Ok(match change {
+                        FileChange::WriteFile { content, .. } if edited => Review::Edit(content),
+                        _ => Review::Accept,
+                    });
+                }
+                "e" | "edit" => match &mut change {
+                    FileChange::WriteFile { content, .. } => {
+                        *content = edit_in_external_editor(content)?;
+                        edited = true;
+                    }
+                    FileChange::CreateDirectory { .. } => {
+                        println!("Only the content of files can be edited.");
+                    }
+                },
+                _ => return Ok(Review::Reject),
+            }
+        }
+    }
+
+    fn preview(&self, change: &FileChange) {
+        print!("{}", change.unified_diff());
+    }
+}
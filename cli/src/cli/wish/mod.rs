@@ -22,6 +22,7 @@
 //!     let args = WishArgs {
 //!         directory: "./src".to_string(),
 //!         tools: true,
+//!         dry_run: false,
 //!     };
 //!     
 //!     if let Err(e) = run(args).await {
@@ -39,11 +40,12 @@
 //! or an error wrapped in a `Box` that indicates what went wrong during the request processing. Users
 //! should be prepared to handle errors gracefully to ensure a smooth interaction experience.
 
+mod change_approver;
 mod run;
 mod wish_args;
 
 pub use wish_args::WishArgs;
 
 pub async fn run(args: WishArgs) -> Result<(), Box<dyn std::error::Error>> {
-    run::run_wish(args.directory, args.tools).await
+    run::run_wish(args.directory, args.tools, args.dry_run).await
 }
@@ -41,10 +41,12 @@
 //! the request processing. Users should be prepared to handle errors gracefully to ensure
 //! a smooth interaction experience.
 use crate::cli::editor::get_multiline_input;
+use crate::cli::wish::change_approver::ConsoleChangeApprover;
 use rbchat::chat::file_storage::NilChatStorage;
 use rbchat::chat::service::ChatService;
 use rbchat::config;
 use rbchat::persona::get_persona;
+use rbchat::tools::{initialize_tools, ChangeJournal, FileSandbox, ToolRegistry};
 use std::error::Error;
 use std::path::PathBuf;
 
@@ -59,6 +61,7 @@ use std::path::PathBuf;
 ///
 /// * `directory` - A string slice that holds the path to the directory where the service is to be initialized.
 /// * `use_tools` - A boolean indicating whether tools should be used in the service response.
+/// * `dry_run` - If true, the tools only print their changes as a unified diff.
 ///
 /// # Errors
 ///
@@ -67,19 +70,33 @@ use std::path::PathBuf;
 pub async fn run_wish(
     directory: Option<Vec<PathBuf>>,
     use_tools: bool,
+    dry_run: bool,
 ) -> Result<(), Box<dyn Error>> {
     // Initialize the chat service with an OpenAI backend and NilStorage
-    let (model, default_persona) = get_config();
+    let (model, default_persona, diff_tool) = get_config();
     let storage = NilChatStorage {};
     let persona = get_persona(default_persona.as_str()).unwrap();
 
+    // Tools may only touch files below the project root, each change needs the user's approval
+    // and is recorded in the journal, so `rusty-buddy undo` can restore the files
+    let sandbox = FileSandbox::new(config::get_project_root()?)
+        .approver(Box::new(ConsoleChangeApprover::new(diff_tool)))
+        .dry_run(dry_run)
+        .journal(ChangeJournal::start(config::get_journal_dir()?, "wish"));
+    let mut tools = ToolRegistry::with_sandbox(sandbox);
+    initialize_tools(&mut tools);
+
     let mut chat_service = ChatService::builder()
         .model_name(model.as_str())
         .storage(Box::new(storage))
         .persona(persona.clone())
         .directory(directory)
+        .tool_registry(tools)
         .build()?;
     chat_service.setup_context();
+    if !chat_service.context_report().is_complete() {
+        eprint!("{}", chat_service.context_report());
+    }
     // Get user input for their wish
     let user_input = get_multiline_input("What do you wish? ", vec![])
         .map_err(|e| format!("Failed to read user input: {}", e))?;
@@ -97,9 +114,9 @@ pub async fn run_wish(
     Ok(())
 }
 
-fn get_config() -> (String, String) {
+fn get_config() -> (String, String, Option<String>) {
     let config = config::CONFIG.lock().unwrap();
     let model = config.ai.wish_model.clone();
     let default_persona = config.default_persona.clone();
-    (model, default_persona)
+    (model, default_persona, config.diff_tool.clone())
 }
@@ -28,6 +28,8 @@
 //! - `directory`: A vector of `PathBuf` representing the source directory from which files will be collected.
 //! - `tools`: A boolean flag that indicates whether the application should use tools to fulfill user wishes,
 //!   which may involve creating or modifying files and directories based on user-specified actions.
+//!   Every change is confined to the project root and has to be approved by the user.
+//! - `dry_run`: Prints the planned changes as a unified diff instead of applying them.
 
 use clap::Args;
 use std::path::PathBuf;
@@ -44,4 +46,8 @@ pub struct WishArgs {
     /// Activate the usage of tools
     #[arg(short, long)]
     pub tools: bool,
+
+    /// Only print the changes the tools would make as a unified diff
+    #[arg(long, requires = "tools")]
+    pub dry_run: bool,
 }
@@ -84,6 +84,9 @@ async fn main() {
             args::Commands::Wish(args) => {
                 cli::wish::run(args).await.unwrap();
             }
+            args::Commands::Undo(args) => {
+                cli::undo::run(args).await.unwrap();
+            }
             args::Commands::Init(_init_args) => {}
             args::Commands::Knowledge(args) => {
                 cli::knowledge::run_knowledge(args).await.unwrap();
@@ -0,0 +1,144 @@
+//! This module lets answers cite the knowledge they are based on.
+//!
+//! When citing is enabled, every knowledge message of a session gets a number, which is
+//! stored in its `KnowledgeInfo` and therefore saved with the session. The content sent to
+//! the model starts with the number and origin, and `CITATION_PROMPT` asks the model to cite
+//! the knowledge as `[1]` or `[1, 2]`.
+//!
+//! `cited_sources` finds the numbers an answer cites and maps them to the origins of the
+//! knowledge, so a "Sources" footer can be shown below the answer.
+
+use crate::chat::interface::{Message, MessageInfo};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Instruction added to the session before the first numbered knowledge message.
+pub const CITATION_PROMPT: &str = "The knowledge messages are numbered like [1]. When a \
+statement of your answer is based on a knowledge message, cite it with its number in square \
+brackets, e.g. [1] or [1, 3]. Do not cite anything else and do not list the sources yourself.";
+
+/// A knowledge message cited by an answer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Citation {
+    pub number: usize,
+    /// File path and line range, or URL of the knowledge.
+    pub origin: String,
+}
+
+/// The knowledge cited by an answer, shown as a footer below it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sources(pub Vec<Citation>);
+
+impl Sources {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for Sources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Sources:")?;
+        for citation in &self.0 {
+            writeln!(f, "  [{}] {}", citation.number, citation.origin)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the content sent to the model for knowledge number `number` from `origin`.
+pub fn numbered_knowledge(number: usize, origin: &str, content: &str) -> String {
+    format!("[{}] Source: {}\n{}", number, origin, content)
+}
+
+/// Returns the highest citation number of the knowledge in `messages`, 0 if there is none.
+pub fn last_citation(messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .filter_map(|msg| match &msg.info {
+            Some(MessageInfo::KnowledgeInfo { citation, .. }) => *citation,
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Maps the numbers cited in `answer` to the knowledge in `messages`. Numbers without
+/// knowledge are ignored.
+pub fn cited_sources(messages: &[Message], answer: &str) -> Sources {
+    let numbers = citation_numbers(answer);
+    let citations = messages
+        .iter()
+        .filter_map(|msg| match &msg.info {
+            Some(MessageInfo::KnowledgeInfo {
+                citation: Some(number),
+                origin,
+                ..
+            }) if numbers.contains(number) => Some(Citation {
+                number: *number,
+                origin: origin.clone(),
+            }),
+            _ => None,
+        })
+        .collect();
+    Sources(citations)
+}
+
+// Finds the numbers in brackets like `[2]` or `[1, 3]`
+fn citation_numbers(answer: &str) -> BTreeSet<usize> {
+    let mut numbers = BTreeSet::new();
+    for (start, _) in answer.match_indices('[') {
+        let Some(end) = answer[start..].find(']') else {
+            break;
+        };
+        let parsed: Result<Vec<usize>, _> = answer[start + 1..start + end]
+            .split(',')
+            .map(|number| number.trim().parse())
+            .collect();
+        if let Ok(parsed) = parsed {
+            numbers.extend(parsed);
+        }
+    }
+    numbers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::interface::MessageRole;
+
+    fn knowledge(number: usize, origin: &str) -> Message {
+        Message {
+            role: MessageRole::Knowledge,
+            content: numbered_knowledge(number, origin, "content"),
+            info: Some(MessageInfo::KnowledgeInfo {
+                origin: origin.to_string(),
+                distance: 0.5,
+                citation: Some(number),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_cited_sources() {
+        let messages = vec![
+            knowledge(1, "src/main.rs:1-40"),
+            knowledge(2, "https://example.com"),
+            knowledge(3, "docs/guide.md:10-20"),
+        ];
+
+        let sources = cited_sources(
+            &messages,
+            "Use `args[0]` [3], as the guide says [1, 3]. See [7] and [link](x).",
+        );
+
+        assert_eq!(
+            sources.0.iter().map(|c| c.number).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(
+            sources.to_string(),
+            "Sources:\n  [1] src/main.rs:1-40\n  [3] docs/guide.md:10-20\n"
+        );
+        assert_eq!(last_citation(&messages), 3);
+    }
+}
@@ -0,0 +1,53 @@
+//! This command compacts the chat session.
+//!
+//! The `CompactCommand` replaces the older turns of the session with a summary written by the
+//! compaction model, as `ChatService` does on its own once the session crosses the compaction
+//! threshold. The replaced messages are archived when the session is saved. It is registered as
+//! `"/compact"` with the alias `"compact"`.
+
+use crate::chat::command::{ChatCommand, RegisterableCommand};
+use crate::chat::command_registry::CommandRegistry;
+use crate::chat::service::ChatService;
+use std::error::Error;
+use tokio::runtime::Handle;
+use tokio::task;
+
+/// A command that summarises the older turns of the chat session.
+pub struct CompactCommand {}
+
+impl CompactCommand {
+    pub fn new() -> Self {
+        CompactCommand {}
+    }
+}
+
+impl ChatCommand for CompactCommand {
+    fn execute(
+        &self,
+        _args: &[&str],
+        chat_service: &mut ChatService,
+    ) -> Result<(), Box<dyn Error>> {
+        let before = chat_service.estimated_prompt_tokens();
+        // Commands run synchronously inside the chat loop, so the summary request blocks it
+        let compacted =
+            task::block_in_place(|| Handle::current().block_on(chat_service.compact()))?;
+        if compacted == 0 {
+            println!("Nothing to compact.");
+        } else {
+            println!(
+                "Compacted {} messages, the session shrank from ~{} to ~{} tokens.",
+                compacted,
+                before,
+                chat_service.estimated_prompt_tokens()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl RegisterableCommand for CompactCommand {
+    fn register_with_registry(registry: &mut CommandRegistry) {
+        let command = CompactCommand::new();
+        registry.register_command("/compact", Box::new(command), vec!["compact".to_string()]);
+    }
+}
@@ -6,6 +6,8 @@
 //!
 //! The registered commands include:
 //! - `RenewCommand`: Responsible for refreshing the chat state.
+//! - `UndoChangesCommand`: Restores the files changed by the last tool run.
+//! - `CompactCommand`: Summarises the older turns of the chat session.
 //! - `SaveFilesCommand`: Handles saving files related to the chat.
 //! - `SaveLastAnswerCommand`: Manages saving the last response from the chat.
 //!
@@ -24,11 +26,17 @@
 
 use crate::chat::command::RegisterableCommand;
 use crate::chat::command_registry::CommandRegistry;
+use crate::chat::commands::compact::CompactCommand;
 use crate::chat::commands::refresh::RenewCommand;
+use crate::chat::commands::undo_changes::UndoChangesCommand;
 
+mod compact;
 mod refresh;
+mod undo_changes;
 
 pub fn initialize_commands(registry: &mut CommandRegistry) {
     // Each command registers itself
     RenewCommand::register_with_registry(registry);
+    UndoChangesCommand::register_with_registry(registry);
+    CompactCommand::register_with_registry(registry);
 }
@@ -0,0 +1,46 @@
+//! This command undoes the file changes of the last tool run.
+//!
+//! The `UndoChangesCommand` restores the files recorded in the most recent run of the change
+//! journal in `.rusty/journal`, for example after a `wish --tools` invocation went wrong. It is
+//! registered as `"/undo-changes"` with the alias `"undo-changes"`.
+
+use crate::chat::command::{ChatCommand, RegisterableCommand};
+use crate::chat::command_registry::CommandRegistry;
+use crate::chat::service::ChatService;
+use crate::config;
+use crate::tools::undo_last_run_summary;
+use std::error::Error;
+
+/// A command that restores the files changed by the last tool run.
+pub struct UndoChangesCommand {}
+
+impl UndoChangesCommand {
+    pub fn new() -> Self {
+        UndoChangesCommand {}
+    }
+}
+
+impl ChatCommand for UndoChangesCommand {
+    fn execute(
+        &self,
+        _args: &[&str],
+        _chat_service: &mut ChatService,
+    ) -> Result<(), Box<dyn Error>> {
+        let journal_dir = config::get_journal_dir()?;
+        for line in undo_last_run_summary(&journal_dir, &config::get_project_root()?)? {
+            println!("{}", line);
+        }
+        Ok(())
+    }
+}
+
+impl RegisterableCommand for UndoChangesCommand {
+    fn register_with_registry(registry: &mut CommandRegistry) {
+        let command = UndoChangesCommand::new();
+        registry.register_command(
+            "/undo-changes",
+            Box::new(command),
+            vec!["undo-changes".to_string()],
+        );
+    }
+}
@@ -9,7 +9,8 @@
 //!
 //! - `DirectoryChatStorage`: A structured chat storage that saves chat sessions to a specified
 //!   directory as JSON files. It provides methods to save a session, load a session, and list
-//!   all saved sessions within the storage directory.
+//!   all saved sessions within the storage directory. Messages compacted out of a session are
+//!   archived in the `archive` subdirectory under the session's name.
 //!
 //! ## Usage Example
 //!
@@ -75,6 +76,12 @@ impl DirectoryChatStorage {
         self.storage_dir.join(format!("{}.json", session_name))
     }
 
+    fn get_archive_path(&self, session_name: &str) -> PathBuf {
+        self.storage_dir
+            .join("archive")
+            .join(format!("{}.json", session_name))
+    }
+
     fn ensure_storage_dir_exists(&self) -> io::Result<()> {
         fs::create_dir_all(&self.storage_dir)?;
         Ok(())
@@ -132,6 +139,28 @@ impl ChatStorage for DirectoryChatStorage {
             })
             .collect())
     }
+
+    fn load_archive(&mut self, session_name: &str) -> io::Result<Vec<Message>> {
+        let file_path = self.get_archive_path(session_name);
+        if !file_path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(file_path)?;
+        serde_json::from_str(&content)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    }
+
+    fn save_archive(&self, session_name: &str, messages: &[Message]) -> io::Result<()> {
+        let file_path = self.get_archive_path(session_name);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json_content = serde_json::to_string(messages)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+        fs::write(&file_path, json_content.as_bytes())?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -210,4 +239,30 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_archive_is_not_listed_as_session() {
+        let storage_dir = TempDir::new().expect("Failed to create temp dir").keep();
+        let mut storage = DirectoryChatStorage::new(storage_dir.clone());
+
+        let messages = vec![Message {
+            role: MessageRole::User,
+            content: "Compacted question".to_string(),
+            ..Default::default()
+        }];
+
+        assert!(storage.load_archive("session").unwrap().is_empty());
+        storage
+            .save_session("session", &messages)
+            .expect("Failed to save session.");
+        storage
+            .save_archive("session", &messages)
+            .expect("Failed to save archive.");
+
+        assert_eq!(storage.load_archive("session").unwrap(), messages);
+        assert_eq!(
+            storage.list_sessions().unwrap(),
+            vec!["session".to_string()]
+        );
+    }
 }
@@ -15,6 +15,8 @@
 //! - **MessageRole**:
 //!   An enum that defines different roles a message may assume (e.g., System, User, Assistant),
 //!   allowing for clear distinction and handling of messages based on who authored them.
+//!   `ToolCall` marks an assistant turn that requests tools, `ToolResult` carries the
+//!   output of one of those tools back to the model.
 //!
 //! - **MessageInfo**:
 //!   A structure containing additional contextual information about the message, including
@@ -24,11 +26,14 @@
 //! - **ChatBackend**:
 //!   A trait that provides an interface for sending messages to a chat model asynchronously.
 //!   It facilitates the implementation of various backends, enabling flexible integrations
-//!   with models (e.g., OpenAI, Ollama).
+//!   with models (e.g., OpenAI, Ollama). Answers can be received at once or streamed
+//!   delta by delta through `send_request_stream`.
 //!
 //! - **ChatStorage**:
 //!   A trait that facilitates management of chat sessions. It allows for loading, saving,
 //!   and listing chat sessions, ensuring state persistence across application runs.
+//!   Messages replaced by a summary when a session is compacted are kept in an archive
+//!   next to the session.
 //!
 //! ## Examples
 //!
@@ -46,6 +51,7 @@
 //! seamless interactions with AI models while ensuring flexibility and extensibility in
 //! message management and session handling.
 
+use crate::tools::ToolDefinition;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -71,9 +77,33 @@ pub enum MessageInfo {
     KnowledgeInfo {
         origin: String,
         distance: f32,
+        /// Number the answers cite the knowledge by, if citing was enabled.
+        #[serde(default)]
+        citation: Option<usize>,
+    },
+    ToolCallInfo {
+        tool_calls: Vec<ToolCall>,
+    },
+    ToolResultInfo {
+        tool_call_id: String,
+        name: String,
+    },
+    SummaryInfo {
+        model: String,
+        compacted_messages: usize,
+        timestamp: DateTime<Utc>,
     }, // Add additional variants as needed
 }
 
+/// A single tool invocation requested by the model. `arguments` holds the JSON
+/// encoded arguments exactly as the model produced them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Message {
     pub role: MessageRole,
@@ -99,16 +129,37 @@ pub enum MessageRole {
     Assistant,
     Context,
     Knowledge,
+    ToolCall,
+    ToolResult,
 }
 
+/// A backend offers the given `tools` to the model in its own wire format. It answers
+/// either with an `Assistant` message or, when the model wants to run tools, with a
+/// `ToolCall` message carrying `MessageInfo::ToolCallInfo`.
 #[async_trait]
-pub trait ChatBackend {
+pub trait ChatBackend: Send {
     async fn send_request(
         &mut self,
         messages: &[Message],
-        use_tools: bool,
+        tools: &[ToolDefinition],
     ) -> Result<Message, Box<dyn Error>>;
 
+    /// Sends the messages to the backend and hands every content delta to `on_delta`
+    /// as soon as it arrives. The returned `Message` holds the complete answer.
+    ///
+    /// Backends without streaming support fall back to `send_request` and emit the
+    /// whole answer as a single delta.
+    async fn send_request_stream(
+        &mut self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        on_delta: &mut (dyn for<'a> FnMut(&'a str) + Send),
+    ) -> Result<Message, Box<dyn Error>> {
+        let message = self.send_request(messages, tools).await?;
+        on_delta(message.content.as_str());
+        Ok(message)
+    }
+
     fn print_statistics(&self);
 }
 
@@ -116,4 +167,15 @@ pub trait ChatStorage {
     fn load_session(&mut self, session_name: &str) -> io::Result<Vec<Message>>;
     fn save_session(&self, session_name: &str, messages: &[Message]) -> io::Result<()>;
     fn list_sessions(&self) -> io::Result<Vec<String>>;
+
+    /// Loads the messages that were compacted out of the session. Storages without an
+    /// archive return no messages.
+    fn load_archive(&mut self, _session_name: &str) -> io::Result<Vec<Message>> {
+        Ok(Vec::new())
+    }
+
+    /// Saves the messages that were compacted out of the session, so they can be audited.
+    fn save_archive(&self, _session_name: &str, _messages: &[Message]) -> io::Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,109 @@
+//! This module decides which knowledge is added to a chat session before a message is sent.
+//!
+//! Knowledge is queried again on every turn, so without limits the same records are added
+//! over and over and weak matches crowd the prompt. `KnowledgeLimits` therefore
+//!
+//! - drops results whose cosine similarity to the question is below `min_similarity`,
+//! - skips results whose origin is already present as knowledge in the session,
+//! - stops adding results once `max_tokens` are used up for the turn.
+//!
+//! `KnowledgeReport` tells which results were added, with their score, and how many were
+//! left out.
+
+use std::fmt;
+
+/// Size of the knowledge added per turn when the model's context window is unknown.
+pub const DEFAULT_KNOWLEDGE_TOKENS: usize = 4_000;
+
+/// Share of the model's context window, in percent, the knowledge of a turn may take up.
+const KNOWLEDGE_WINDOW_SHARE: usize = 20;
+
+/// Limits for the knowledge added to a session per turn. The default adds every result.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KnowledgeLimits {
+    /// Minimum cosine similarity of a result to the question.
+    pub min_similarity: Option<f32>,
+    /// Maximum estimated tokens of the knowledge added per turn.
+    pub max_tokens: Option<usize>,
+}
+
+impl KnowledgeLimits {
+    /// Limits with a token budget of `max_tokens`, or a share of the model's context window if
+    /// not set.
+    pub fn new(
+        min_similarity: Option<f32>,
+        max_tokens: Option<usize>,
+        context_window: Option<usize>,
+    ) -> Self {
+        let max_tokens = max_tokens.unwrap_or_else(|| match context_window {
+            Some(window) => window * KNOWLEDGE_WINDOW_SHARE / 100,
+            None => DEFAULT_KNOWLEDGE_TOKENS,
+        });
+        KnowledgeLimits {
+            min_similarity,
+            max_tokens: Some(max_tokens),
+        }
+    }
+}
+
+/// A result added to the session as knowledge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InjectedKnowledge {
+    pub origin: String,
+    /// Cosine similarity to the question, 0 for hits of a keyword search.
+    pub similarity: f32,
+    /// The score the result was ranked by.
+    pub score: f32,
+    pub tokens: usize,
+}
+
+/// Describes which knowledge was added to the session in a turn and what was left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KnowledgeReport {
+    pub injected: Vec<InjectedKnowledge>,
+    /// Results less similar than `min_similarity`.
+    pub below_threshold: usize,
+    /// Results already added to the session in an earlier turn.
+    pub already_present: usize,
+    /// Results that did not fit into `max_tokens`.
+    pub over_budget: usize,
+    /// Results whose file could not be read.
+    pub unreadable: usize,
+}
+
+impl KnowledgeReport {
+    pub fn injected_tokens(&self) -> usize {
+        self.injected.iter().map(|knowledge| knowledge.tokens).sum()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.below_threshold + self.already_present + self.over_budget + self.unreadable
+    }
+}
+
+impl fmt::Display for KnowledgeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Knowledge: {} results with ~{} tokens added, {} skipped",
+            self.injected.len(),
+            self.injected_tokens(),
+            self.skipped()
+        )?;
+        for knowledge in &self.injected {
+            writeln!(
+                f,
+                "  + {} (score {:.3}, similarity {:.3}, ~{} tokens)",
+                knowledge.origin, knowledge.score, knowledge.similarity, knowledge.tokens
+            )?;
+        }
+        if self.skipped() > 0 {
+            writeln!(
+                f,
+                "  - {} already in the session, {} below the similarity threshold, {} over the token budget, {} unreadable",
+                self.already_present, self.below_threshold, self.over_budget, self.unreadable
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -45,6 +45,7 @@ mod tests {
     use super::*;
     use crate::chat::interface::{ChatBackend, ChatStorage, Message, MessageRole};
     use crate::persona::Persona;
+    use crate::tools::ToolDefinition;
     use async_trait::async_trait;
     use std::error::Error;
     use std::io;
@@ -83,7 +84,7 @@ mod tests {
         async fn send_request(
             &mut self,
             _messages: &[Message],
-            _use_tools: bool,
+            _tools: &[ToolDefinition],
         ) -> Result<Message, Box<dyn Error>> {
             Ok(Message::default())
         }
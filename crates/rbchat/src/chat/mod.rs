@@ -10,14 +10,18 @@
 //!   `chat`, `commit-message`, `create-icon`, etc.
 //! - **File Storage:** Manages sessions and persistent storage of chat logs and user inputs.
 //! - **Interface Layer:** Facilitates communication between the chat service and backend AI models.
+//! - **Citations:** Numbers the knowledge of a session and maps the citations of answers to it.
+//! - **Knowledge Limits:** Decides which knowledge results are added to a session per turn.
 //! - **Message Helpers:** Provides utilities for handling messages exchanged during chats.
 //! - **Service Management:** Core functionalities that support chat interactions, context management,
 //!   and message processing.
+pub mod citations;
 pub mod command;
 pub mod command_registry;
 pub mod commands;
 pub mod file_storage;
 pub mod interface;
+pub mod knowledge_limits;
 pub mod message_helpers;
 pub mod service;
 mod service_builder;
@@ -25,19 +25,43 @@
 //! ### `setup_context`
 //!
 //! Sets up the initial context for the chat session, including loading files.
+//! If the context window of the model is known, the files are kept within a token
+//! budget and `context_report` tells which files were included and dropped.
+//!
+//! ### `add_knowledge`
+//!
+//! Adds the results of a knowledge query to the session. Results below the similarity
+//! threshold, results already in the session and results beyond the token budget of the
+//! turn are skipped; the returned `KnowledgeReport` tells which were added. When citing is
+//! enabled, the knowledge is numbered and `cited_sources` maps the citations of an answer to
+//! its origins.
 //!
 //! ### `send_message`
 //!
 //! Sends a user message to the backend, retrieves the assistant's response,
-//! and records it in the session.
+//! and records it in the session. When tools are enabled, requested tool calls
+//! are executed and their results handed back to the model until it answers,
+//! up to a configurable number of rounds.
+//!
+//! ### `send_message_stream`
+//!
+//! Like `send_message`, but hands the answer to a callback piece by piece
+//! while the backend is still generating it.
+//!
+//! ### `compact`
+//!
+//! Replaces the older user and assistant turns with a single summary written by the
+//! compaction model, keeping the most recent turns verbatim. Sending a message compacts
+//! the session automatically once its estimated size crosses the compaction threshold.
+//! The replaced messages are kept and archived by the storage.
 //!
 //! ### `load_history`
 //!
-//! Loads previous chat messages from storage by session name.
+//! Loads previous chat messages, and their archive, from storage by session name.
 //!
 //! ### `save_history`
 //!
-//! Saves current chat messages to storage under a given session name.
+//! Saves current chat messages, and their archive, to storage under a given session name.
 //!
 //! ### `print_statistics`
 //!
@@ -45,16 +69,25 @@
 
 use std::borrow::Cow;
 // The `ChatService` struct encapsulates the entirety of chat session management.
+use crate::chat::citations::{
+    cited_sources, last_citation, numbered_knowledge, Sources, CITATION_PROMPT,
+};
 use crate::chat::interface::MessageInfo::KnowledgeInfo;
 use crate::chat::interface::{ChatBackend, Message, MessageRole};
 use crate::chat::interface::{ChatStorage, MessageInfo};
+use crate::chat::knowledge_limits::{InjectedKnowledge, KnowledgeLimits, KnowledgeReport};
 use crate::chat::service_builder::ChatServiceBuilder;
-use crate::context::{load_files_into_context, ContextConsumer};
+use crate::context::estimate_tokens;
+use crate::context::{
+    load_files_into_context_with_budget, read_project_file, ContextBudget, ContextConsumer,
+    ContextReport,
+};
 use crate::knowledge::{DataSource, KnowledgeResult};
+use crate::tools::{initialize_tools, ToolDefinition, ToolRegistry};
 use chrono::Utc;
 use log::{info, warn};
+use std::collections::HashSet;
 use std::error::Error;
-use std::fs;
 use std::path::{Path, PathBuf};
 
 /// The `ChatService` struct acts as a mediator between user interactions and backend processing.
@@ -65,10 +98,28 @@ pub struct ChatService {
     messages: Vec<Message>,        // Stores messages exchanged during the current chat session
     persona: Persona,              // Represents the context and behavior in the chat session
     directory: Option<Vec<PathBuf>>, // Directory path for file context loading
+    tools: ToolRegistry,           // Tools the model may call when tools are enabled
+    max_tool_iterations: usize,    // Rounds of tool calls allowed per user message
+    context_window: Option<usize>, // Context window of the model in tokens, if known
+    context_report: ContextReport, // Files included in and dropped from the last context setup
+    compaction_backend: Option<Box<dyn ChatBackend>>, // Summarises older turns, the chat backend if not set
+    compaction_threshold: Option<usize>, // Estimated prompt tokens above which the session is compacted
+    archived_messages: Vec<Message>,     // Messages replaced by summaries, kept for auditing
+    knowledge_limits: KnowledgeLimits,   // Threshold and token budget of the knowledge per turn
+    cite_knowledge: bool,                // Numbers the knowledge so answers can cite it
 }
 
 use crate::persona::Persona;
 
+const DEFAULT_MAX_TOOL_ITERATIONS: usize = 10;
+
+/// Number of the latest user turns, with their answers, that compaction keeps verbatim.
+const KEEP_RECENT_TURNS: usize = 2;
+
+const COMPACTION_PROMPT: &str = "Summarise the following conversation between a user and an \
+assistant. Keep the facts, decisions, open questions and code details the assistant needs to \
+continue the conversation. Answer with the summary only.";
+
 // Implementation of ChatService struct
 impl ChatService {
     // Constructor to initialize a new ChatService with a backend and storage system.
@@ -78,12 +129,23 @@ impl ChatService {
         persona: Persona,
         directory: Option<Vec<PathBuf>>,
     ) -> Self {
+        let mut tools = ToolRegistry::new();
+        initialize_tools(&mut tools);
         let mut cs = ChatService {
             backend,
             storage,
             directory,
             persona,
             messages: vec![],
+            tools,
+            max_tool_iterations: DEFAULT_MAX_TOOL_ITERATIONS,
+            context_window: None,
+            context_report: ContextReport::default(),
+            compaction_backend: None,
+            compaction_threshold: None,
+            archived_messages: vec![],
+            knowledge_limits: KnowledgeLimits::default(),
+            cite_knowledge: false,
         };
         // Add persona's chat prompt to the context
         let prompt = cs.persona.chat_prompt.clone();
@@ -107,17 +169,27 @@ impl ChatService {
                 .into(),
         );
         if let Some(directories) = self.directory.clone() {
-            for directory in directories {
-                load_files_into_context(
-                    self,
-                    Path::new(&directory),
-                    self.persona.file_types.clone().as_slice(),
-                )
-                .unwrap();
-            }
+            let budget = self.context_window.map(ContextBudget::for_context_window);
+            self.context_report = load_files_into_context_with_budget(
+                self,
+                &directories,
+                self.persona.file_types.clone().as_slice(),
+                budget.as_ref(),
+            )
+            .unwrap();
         }
     }
 
+    // Sets the context window of the model, which limits the files loaded into the context
+    pub fn set_context_window(&mut self, context_window: Option<usize>) {
+        self.context_window = context_window;
+    }
+
+    // Returns which files the last `setup_context` included and dropped
+    pub fn context_report(&self) -> &ContextReport {
+        &self.context_report
+    }
+
     // Inserts a new context message into the session
     pub fn add_context_message(&mut self, filename: Cow<str>, system_message: Cow<str>) {
         let mut pos = 1; // 0 is the persona prompt
@@ -157,45 +229,120 @@ impl ChatService {
         }
     }
 
+    // Adds the results of a knowledge query to the session, best first, within the knowledge
+    // limits. Results already added in an earlier turn are skipped.
     pub async fn add_knowledge(
         &mut self,
         knowledge: Vec<KnowledgeResult>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<KnowledgeReport, Box<dyn Error>> {
+        let mut present: HashSet<String> = self
+            .messages
+            .iter()
+            .filter_map(|msg| match &msg.info {
+                Some(KnowledgeInfo { origin, .. }) => Some(origin.clone()),
+                _ => None,
+            })
+            .collect();
+        let mut remaining = self.knowledge_limits.max_tokens;
+        let mut report = KnowledgeReport::default();
         for k in knowledge {
-            let message = match k.data_source {
-                DataSource::Context(ref filename) => {
+            let origin = k.location();
+            if self
+                .knowledge_limits
+                .min_similarity
+                .is_some_and(|min| k.distance < min)
+            {
+                report.below_threshold += 1;
+                continue;
+            }
+            if present.contains(&origin) {
+                report.already_present += 1;
+                continue;
+            }
+            let content = match (&k.data_source, k.content) {
+                // Chunks carry their content, records of whole files are read again
+                (DataSource::Context(filename), None) => {
                     info!("Adding knowledge to session from file: {}", filename); // Log the added file
-                    let file_path = Path::new(filename);
-                    let text = fs::read_to_string(file_path).map_err(|e| {
-                        warn!("Failed to read file '{}': {}", file_path.display(), e); // Log failures
-                        ""
-                    })?;
-                    Message {
-                        role: MessageRole::Knowledge,
-                        content: text,
-                        info: Some(KnowledgeInfo {
-                            distance: k.distance,
-                            origin: k.data_source.to_string(),
-                        }),
+                    match read_project_file(Path::new(filename)) {
+                        Ok(content) => content,
+                        Err(e) => {
+                            warn!("{}", e); // Log failures
+                            report.unreadable += 1;
+                            continue;
+                        }
                     }
                 }
-                DataSource::Internet(ref content) | DataSource::LocalFiles(ref content) => {
-                    info!("Adding knowledge from external source: {}", content);
-                    Message {
-                        role: MessageRole::Knowledge,
-                        content: k.content.unwrap(),
-                        info: Some(KnowledgeInfo {
-                            distance: k.distance,
-                            origin: content.to_string(),
-                        }),
-                    }
+                (_, content) => {
+                    info!("Adding knowledge from: {}", origin);
+                    content.unwrap_or_default()
                 }
             };
-            self.messages.push(message);
+            let tokens = estimate_tokens(&content);
+            if remaining.is_some_and(|remaining| tokens > remaining) {
+                report.over_budget += 1;
+                continue;
+            }
+            remaining = remaining.map(|remaining| remaining - tokens);
+
+            let citation = if self.cite_knowledge {
+                let last = last_citation(&self.messages);
+                // The prompt outlives the knowledge cited before a compaction
+                if !self
+                    .messages
+                    .iter()
+                    .any(|msg| msg.content == CITATION_PROMPT)
+                {
+                    self.add_system_message(CITATION_PROMPT);
+                }
+                Some(last + 1)
+            } else {
+                None
+            };
+            let content = match citation {
+                Some(number) => numbered_knowledge(number, &origin, &content),
+                None => content,
+            };
+
+            present.insert(origin.clone());
+            report.injected.push(InjectedKnowledge {
+                origin: origin.clone(),
+                similarity: k.distance,
+                score: k.score,
+                tokens,
+            });
+            self.messages.push(Message {
+                role: MessageRole::Knowledge,
+                content,
+                info: Some(KnowledgeInfo {
+                    distance: k.distance,
+                    origin,
+                    citation,
+                }),
+            });
         }
 
-        Ok(())
+        Ok(report)
+    }
+
+    // Sets the similarity threshold and token budget of the knowledge added per turn
+    pub fn set_knowledge_limits(&mut self, knowledge_limits: KnowledgeLimits) {
+        self.knowledge_limits = knowledge_limits;
+    }
+
+    pub fn knowledge_limits(&self) -> KnowledgeLimits {
+        self.knowledge_limits
+    }
+
+    // Numbers the knowledge added from now on and asks the model to cite it
+    pub fn set_cite_knowledge(&mut self, cite_knowledge: bool) {
+        self.cite_knowledge = cite_knowledge;
+    }
+
+    // Returns the knowledge cited by `answer`, to be shown below it
+    pub fn cited_sources(&self, answer: &str) -> Sources {
+        cited_sources(&self.messages, answer)
     }
+
     // Sends a user message to the backend, potentially using tools, and captures the response
     pub async fn send_message(
         &mut self,
@@ -203,7 +350,240 @@ impl ChatService {
         image: &Option<PathBuf>,
         use_tools: bool,
     ) -> Result<String, Box<dyn Error>> {
-        // Add the user message to the session messages
+        self.add_user_message(user_message, image);
+        self.compact_if_needed().await;
+
+        let tools = self.tool_definitions(use_tools);
+        let mut iteration = 0;
+        loop {
+            // Send the request to the backend service and capture the response
+            let response = self.backend.send_request(&self.messages, &tools).await?;
+            if response.role != MessageRole::ToolCall {
+                return Ok(self.add_assistant_message(response));
+            }
+            self.run_tool_calls(response, &mut iteration).await?;
+        }
+    }
+
+    // Sends a user message to the backend and streams the answer through `on_delta` while it
+    // is generated. The complete response is recorded in the session like in `send_message`.
+    pub async fn send_message_stream<F>(
+        &mut self,
+        user_message: Cow<'_, str>,
+        image: &Option<PathBuf>,
+        use_tools: bool,
+        mut on_delta: F,
+    ) -> Result<String, Box<dyn Error>>
+    where
+        F: FnMut(&str) + Send,
+    {
+        self.add_user_message(user_message, image);
+        self.compact_if_needed().await;
+
+        let tools = self.tool_definitions(use_tools);
+        let mut iteration = 0;
+        loop {
+            let response = self
+                .backend
+                .send_request_stream(&self.messages, &tools, &mut on_delta)
+                .await?;
+            if response.role != MessageRole::ToolCall {
+                return Ok(self.add_assistant_message(response));
+            }
+            self.run_tool_calls(response, &mut iteration).await?;
+        }
+    }
+
+    // Sets the backend summarising older turns and the estimated prompt size that triggers it.
+    // Without a backend the chat backend writes the summary, without a threshold the session
+    // is only compacted on request.
+    pub fn set_compaction(
+        &mut self,
+        backend: Option<Box<dyn ChatBackend>>,
+        threshold: Option<usize>,
+    ) {
+        self.compaction_backend = backend;
+        self.compaction_threshold = threshold;
+    }
+
+    // Estimates the size of the messages sent with the next request in tokens
+    pub fn estimated_prompt_tokens(&self) -> usize {
+        self.messages
+            .iter()
+            .map(|msg| estimate_tokens(&msg.content))
+            .sum()
+    }
+
+    // Compacts the session when it has grown beyond the threshold. A failed compaction
+    // only costs space, so the message is sent anyway.
+    async fn compact_if_needed(&mut self) {
+        let Some(threshold) = self.compaction_threshold else {
+            return;
+        };
+        let tokens = self.estimated_prompt_tokens();
+        if tokens <= threshold {
+            return;
+        }
+        info!(
+            "Compacting the session, ~{} tokens exceed the threshold of {}",
+            tokens, threshold
+        );
+        if let Err(e) = self.compact().await {
+            warn!("Failed to compact the session: {}", e);
+        }
+    }
+
+    // Replaces the turns before the latest ones with a summary and returns the number of
+    // compacted messages. The compacted messages are archived with the session.
+    pub async fn compact(&mut self) -> Result<usize, Box<dyn Error>> {
+        // The latest turns start at this user message and stay as they are
+        let Some(end) = self
+            .messages
+            .iter()
+            .enumerate()
+            .filter(|(_, msg)| msg.role == MessageRole::User)
+            .map(|(i, _)| i)
+            .rev()
+            .nth(KEEP_RECENT_TURNS - 1)
+        else {
+            return Ok(0);
+        };
+        // The context files and the instructions of system messages, like the persona prompt
+        // or the citation prompt, are never compacted. Summaries of an earlier compaction are.
+        let is_compacted = |i: usize, msg: &Message| {
+            i < end
+                && match msg.role {
+                    MessageRole::Context => false,
+                    MessageRole::System => {
+                        matches!(msg.info, Some(MessageInfo::SummaryInfo { .. }))
+                    }
+                    _ => true,
+                }
+        };
+        let Some(start) = (0..end).find(|&i| is_compacted(i, &self.messages[i])) else {
+            return Ok(0);
+        };
+
+        let transcript = self
+            .messages
+            .iter()
+            .enumerate()
+            .filter(|(i, msg)| is_compacted(*i, msg))
+            .map(|(_, msg)| transcript_entry(msg))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let request = vec![
+            Message {
+                role: MessageRole::System,
+                content: COMPACTION_PROMPT.to_string(),
+                info: None,
+            },
+            Message {
+                role: MessageRole::User,
+                content: transcript,
+                info: None,
+            },
+        ];
+        let backend = self
+            .compaction_backend
+            .as_mut()
+            .unwrap_or(&mut self.backend);
+        let response = backend.send_request(&request, &[]).await?;
+        let model = match &response.info {
+            Some(MessageInfo::AssistantInfo { model, .. }) => model.clone(),
+            _ => String::new(),
+        };
+
+        let mut kept = Vec::with_capacity(self.messages.len());
+        let mut compacted = Vec::new();
+        for (i, msg) in self.messages.drain(..).enumerate() {
+            if is_compacted(i, &msg) {
+                compacted.push(msg);
+            } else {
+                kept.push(msg);
+            }
+        }
+        let compacted_messages = compacted.len();
+        kept.insert(
+            start,
+            Message {
+                role: MessageRole::System,
+                content: format!("Summary of the earlier conversation:\n{}", response.content),
+                info: Some(MessageInfo::SummaryInfo {
+                    model,
+                    compacted_messages,
+                    timestamp: Utc::now(),
+                }),
+            },
+        );
+        self.messages = kept;
+        self.archived_messages.extend(compacted);
+        Ok(compacted_messages)
+    }
+
+    // Replaces the tools offered to the model, e.g. to add tools of a library user
+    pub fn set_tool_registry(&mut self, tools: ToolRegistry) {
+        self.tools = tools;
+    }
+
+    fn tool_definitions(&self, use_tools: bool) -> Vec<ToolDefinition> {
+        if use_tools {
+            self.tools.definitions()
+        } else {
+            vec![]
+        }
+    }
+
+    // Limits the rounds of tool calls the model may run before it has to answer
+    pub fn set_max_tool_iterations(&mut self, max_tool_iterations: usize) {
+        self.max_tool_iterations = max_tool_iterations;
+    }
+
+    // Records the tool call message, runs the requested tools and appends their results,
+    // so the next request lets the model continue with them
+    async fn run_tool_calls(
+        &mut self,
+        response: Message,
+        iteration: &mut usize,
+    ) -> Result<(), Box<dyn Error>> {
+        *iteration += 1;
+        if *iteration > self.max_tool_iterations {
+            return Err(format!(
+                "Stopped after {} rounds of tool calls without a final answer.",
+                self.max_tool_iterations
+            )
+            .into());
+        }
+
+        let tool_calls = match &response.info {
+            Some(MessageInfo::ToolCallInfo { tool_calls }) => tool_calls.clone(),
+            _ => vec![],
+        };
+        self.messages.push(response);
+        for tool_call in tool_calls {
+            info!("Running tool '{}'", tool_call.name);
+            // Failures are reported to the model, which may correct its call
+            let content = match self.tools.execute_tool(&tool_call).await {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("Tool '{}' failed: {}", tool_call.name, e);
+                    format!("Error: {}", e)
+                }
+            };
+            self.messages.push(Message {
+                role: MessageRole::ToolResult,
+                content,
+                info: Some(MessageInfo::ToolResultInfo {
+                    tool_call_id: tool_call.id,
+                    name: tool_call.name,
+                }),
+            });
+        }
+        Ok(())
+    }
+
+    // Adds the user message to the session messages
+    fn add_user_message(&mut self, user_message: Cow<'_, str>, image: &Option<PathBuf>) {
         self.messages.push(Message {
             role: MessageRole::User,
             content: user_message.into_owned(),
@@ -212,10 +592,10 @@ impl ChatService {
                 image_path: image.clone().map(|p| p.to_string_lossy().to_string()),
             }),
         });
+    }
 
-        // Send the request to the backend service and capture the response
-        let mut response = self.backend.send_request(&self.messages, use_tools).await?;
-
+    // Stores the assistant's response message and returns its content
+    fn add_assistant_message(&mut self, mut response: Message) -> String {
         let answer = response.content.clone();
 
         // Update the response info if it's of a specific type
@@ -236,20 +616,25 @@ impl ChatService {
             });
         }
 
-        // Store the assistant's response message
         self.messages.push(response);
 
-        Ok(answer)
+        answer
     }
+
     // Loads chat history from storage by session name
     pub fn load_history(&mut self, session_name: &str) -> Result<(), Box<dyn Error>> {
         self.messages = self.storage.load_session(session_name)?;
+        self.archived_messages = self.storage.load_archive(session_name)?;
         Ok(())
     }
 
     // Saves current chat messages to storage with a specified session name
     pub fn save_history(&self, session_name: &str) -> Result<(), Box<dyn Error>> {
         self.storage.save_session(session_name, &self.messages)?;
+        if !self.archived_messages.is_empty() {
+            self.storage
+                .save_archive(session_name, &self.archived_messages)?;
+        }
         Ok(())
     }
 
@@ -259,6 +644,27 @@ impl ChatService {
     }
 }
 
+// Renders a message for the transcript handed to the compaction model
+fn transcript_entry(msg: &Message) -> String {
+    match (&msg.role, &msg.info) {
+        (MessageRole::ToolCall, Some(MessageInfo::ToolCallInfo { tool_calls })) => {
+            let calls = tool_calls
+                .iter()
+                .map(|call| format!("{}({})", call.name, call.arguments))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("Assistant called tools: {}", calls)
+        }
+        (MessageRole::ToolResult, Some(MessageInfo::ToolResultInfo { name, .. })) => {
+            format!("Result of tool {}:\n{}", name, msg.content)
+        }
+        (MessageRole::Knowledge, Some(MessageInfo::KnowledgeInfo { origin, .. })) => {
+            format!("Knowledge from {}:\n{}", origin, msg.content)
+        }
+        (role, _) => format!("{:?}: {}", role, msg.content),
+    }
+}
+
 impl ContextConsumer for ChatService {
     fn consume(&mut self, filename: Cow<str>, content: Cow<str>) -> Result<(), Box<dyn Error>> {
         let f_content = format!("Filename: {}\nContent:\n{}\n", &*filename, &*content);
@@ -270,11 +676,18 @@ impl ContextConsumer for ChatService {
 // Unit tests for ChatService
 #[cfg(test)]
 mod tests {
-    use crate::chat::file_storage::NilChatStorage;
-    use crate::chat::interface::{ChatBackend, Message};
+    use crate::chat::citations::CITATION_PROMPT;
+    use crate::chat::file_storage::{DirectoryChatStorage, NilChatStorage};
+    use crate::chat::interface::{
+        ChatBackend, ChatStorage, Message, MessageInfo, MessageRole, ToolCall,
+    };
+    use crate::chat::knowledge_limits::KnowledgeLimits;
     use crate::chat::service::ChatService;
+    use crate::knowledge::{DataSource, KnowledgeResult, DEFAULT_COLLECTION};
     use crate::persona::Persona;
+    use crate::tools::{initialize_tools, AutoApprover, FileSandbox, ToolDefinition, ToolRegistry};
     use async_trait::async_trait;
+    use chrono::Utc;
     use std::env;
     use std::error::Error;
 
@@ -414,6 +827,448 @@ mod tests {
         assert_eq!(chat_service.messages.len(), 4);
     }
 
+    // Test that streamed deltas are forwarded and the final answer is recorded
+    #[tokio::test]
+    async fn test_send_message_stream() {
+        let persona = Persona {
+            name: "test".to_string(),
+            chat_prompt: "Test persona prompt".to_string(),
+            file_types: vec![],
+        };
+        let mut chat_service = ChatService::new(
+            Box::new(MockChatBackend::new()),
+            Box::new(NilChatStorage {}),
+            persona,
+            None,
+        );
+
+        let mut streamed = String::new();
+        let answer = chat_service
+            .send_message_stream("Hello".into(), &None, false, |delta| {
+                streamed.push_str(delta)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(answer, "Hello World");
+        assert_eq!(streamed, "Hello World");
+        let last = chat_service.messages.last().unwrap();
+        assert_eq!(last.role, MessageRole::Assistant);
+        assert!(matches!(
+            &last.info,
+            Some(MessageInfo::AssistantInfo { persona_name, .. }) if persona_name == "test"
+        ));
+    }
+
+    // Test that tool results are handed back to the model until it answers
+    #[tokio::test]
+    async fn test_send_message_runs_tool_loop() {
+        let root = tempfile::tempdir().unwrap();
+        let mut chat_service = ChatService::new(
+            Box::new(ToolCallingBackend {
+                arguments: serde_json::json!({ "directory_path": "created" }).to_string(),
+                remaining_calls: 1,
+            }),
+            Box::new(NilChatStorage {}),
+            tool_persona(),
+            None,
+        );
+        let mut tools = ToolRegistry::with_sandbox(
+            FileSandbox::new(root.path().to_path_buf()).approver(Box::new(AutoApprover)),
+        );
+        initialize_tools(&mut tools);
+        chat_service.set_tool_registry(tools);
+
+        let answer = chat_service
+            .send_message("Create a directory".into(), &None, true)
+            .await
+            .unwrap();
+
+        assert_eq!(answer, "Done");
+        assert!(root.path().join("created").is_dir());
+        let roles: Vec<MessageRole> = chat_service
+            .messages
+            .iter()
+            .map(|m| m.role.clone())
+            .collect();
+        assert_eq!(
+            roles,
+            vec![
+                MessageRole::System,
+                MessageRole::User,
+                MessageRole::ToolCall,
+                MessageRole::ToolResult,
+                MessageRole::Assistant
+            ]
+        );
+        assert!(matches!(
+            &chat_service.messages[3].info,
+            Some(MessageInfo::ToolResultInfo { tool_call_id, .. }) if tool_call_id == "call_0"
+        ));
+    }
+
+    // Test that a model which never stops calling tools is cut off
+    #[tokio::test]
+    async fn test_send_message_stops_after_max_tool_iterations() {
+        let mut chat_service = ChatService::new(
+            Box::new(ToolCallingBackend {
+                arguments: "{}".to_string(),
+                remaining_calls: usize::MAX,
+            }),
+            Box::new(NilChatStorage {}),
+            tool_persona(),
+            None,
+        );
+        chat_service.set_max_tool_iterations(3);
+
+        let result = chat_service
+            .send_message("Loop forever".into(), &None, true)
+            .await;
+
+        assert!(result.is_err());
+        let tool_results = chat_service
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::ToolResult)
+            .collect::<Vec<_>>();
+        assert_eq!(tool_results.len(), 3);
+        assert!(tool_results[0].content.starts_with("Error:"));
+    }
+
+    // Test that older turns are replaced by a summary and archived with the session
+    #[tokio::test]
+    async fn test_compact_replaces_older_turns_with_summary() {
+        let storage_dir = tempfile::tempdir().unwrap();
+        let mut chat_service = ChatService::new(
+            Box::new(MockChatBackend::new()),
+            Box::new(DirectoryChatStorage::new(storage_dir.path().to_path_buf())),
+            tool_persona(),
+            None,
+        );
+        chat_service.set_compaction(Some(Box::new(SummaryBackend)), None);
+        for turn in 1..=3 {
+            add_turn(&mut chat_service, turn);
+        }
+
+        let compacted = chat_service.compact().await.unwrap();
+
+        assert_eq!(compacted, 4);
+        let roles: Vec<MessageRole> = chat_service
+            .messages
+            .iter()
+            .map(|m| m.role.clone())
+            .collect();
+        assert_eq!(
+            roles,
+            vec![
+                MessageRole::System,
+                MessageRole::System,
+                MessageRole::User,
+                MessageRole::Assistant,
+                MessageRole::User,
+                MessageRole::Assistant
+            ]
+        );
+        assert!(chat_service.messages[1].content.ends_with("Short summary"));
+        assert!(matches!(
+            &chat_service.messages[1].info,
+            Some(MessageInfo::SummaryInfo { compacted_messages: 4, model, .. }) if model == "cheap"
+        ));
+        assert_eq!(chat_service.messages[2].content, "Question 2");
+
+        chat_service.save_history("session").unwrap();
+        let mut storage = DirectoryChatStorage::new(storage_dir.path().to_path_buf());
+        let archive = storage.load_archive("session").unwrap();
+        assert_eq!(archive.len(), 4);
+        assert_eq!(archive[0].content, "Question 1");
+    }
+
+    // Test that system messages added during the session survive a compaction
+    #[tokio::test]
+    async fn test_compact_keeps_system_messages() {
+        let mut chat_service = ChatService::new(
+            Box::new(MockChatBackend::new()),
+            Box::new(NilChatStorage {}),
+            tool_persona(),
+            None,
+        );
+        chat_service.set_compaction(Some(Box::new(SummaryBackend)), None);
+        chat_service.set_cite_knowledge(true);
+        add_turn(&mut chat_service, 1);
+        let knowledge = KnowledgeResult {
+            distance: 0.9,
+            score: 0.9,
+            data_source: DataSource::Internet("https://a.example".to_string()),
+            collection: DEFAULT_COLLECTION.to_string(),
+            content: Some("Content".to_string()),
+            metadata: None,
+        };
+        chat_service
+            .add_knowledge(vec![knowledge.clone()])
+            .await
+            .unwrap();
+        for turn in 2..=4 {
+            add_turn(&mut chat_service, turn);
+        }
+
+        let compacted = chat_service.compact().await.unwrap();
+        chat_service.add_knowledge(vec![knowledge]).await.unwrap();
+
+        // The knowledge of the first turn was compacted, the prompt is neither lost nor repeated
+        assert_eq!(compacted, 5);
+        let system: Vec<&str> = chat_service
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::System)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(system.len(), 3);
+        assert_eq!(system[0], "Test persona prompt");
+        assert!(system[1].ends_with("Short summary"));
+        assert_eq!(system[2], CITATION_PROMPT);
+    }
+
+    // Test that sending a message compacts the session once it crosses the threshold
+    #[tokio::test]
+    async fn test_send_message_compacts_above_threshold() {
+        let mut chat_service = ChatService::new(
+            Box::new(MockChatBackend::new()),
+            Box::new(NilChatStorage {}),
+            tool_persona(),
+            None,
+        );
+        chat_service.set_compaction(Some(Box::new(SummaryBackend)), Some(10));
+        add_turn(&mut chat_service, 1);
+        chat_service
+            .send_message_stream("Hello".into(), &None, false, |_| {})
+            .await
+            .unwrap();
+        assert!(!chat_service
+            .messages
+            .iter()
+            .any(|m| matches!(m.info, Some(MessageInfo::SummaryInfo { .. }))));
+
+        for turn in 2..=20 {
+            add_turn(&mut chat_service, turn);
+        }
+        chat_service
+            .send_message_stream("Hello again".into(), &None, false, |_| {})
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            chat_service.messages[1].info,
+            Some(MessageInfo::SummaryInfo { .. })
+        ));
+        assert_eq!(chat_service.messages.len(), 6);
+        assert_eq!(chat_service.archived_messages.len(), 40);
+    }
+
+    // Test that knowledge below the threshold, beyond the budget or already added is skipped
+    #[tokio::test]
+    async fn test_add_knowledge_within_limits() {
+        let mut chat_service = ChatService::new(
+            Box::new(MockChatBackend::new()),
+            Box::new(NilChatStorage {}),
+            tool_persona(),
+            None,
+        );
+        chat_service.set_knowledge_limits(KnowledgeLimits {
+            min_similarity: Some(0.5),
+            max_tokens: Some(10),
+        });
+        let result = |url: &str, similarity: f32, content: &str| KnowledgeResult {
+            distance: similarity,
+            score: similarity,
+            data_source: DataSource::Internet(url.to_string()),
+            collection: DEFAULT_COLLECTION.to_string(),
+            content: Some(content.to_string()),
+            metadata: None,
+        };
+
+        let report = chat_service
+            .add_knowledge(vec![
+                result("https://a.example", 0.9, "About a"),
+                result("https://b.example", 0.8, &"b".repeat(80)),
+                result("https://c.example", 0.2, "About c"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(report.injected.len(), 1);
+        assert_eq!(report.injected[0].origin, "Internet.https://a.example");
+        assert_eq!((report.below_threshold, report.over_budget), (1, 1));
+
+        let report = chat_service
+            .add_knowledge(vec![result("https://a.example", 0.9, "About a")])
+            .await
+            .unwrap();
+        assert!(report.injected.is_empty());
+        assert_eq!(report.already_present, 1);
+        let knowledge = chat_service
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::Knowledge)
+            .count();
+        assert_eq!(knowledge, 1);
+    }
+
+    // Test that a result whose file cannot be read is skipped without failing the turn
+    #[tokio::test]
+    async fn test_add_knowledge_skips_unreadable_files() {
+        let mut chat_service = ChatService::new(
+            Box::new(MockChatBackend::new()),
+            Box::new(NilChatStorage {}),
+            tool_persona(),
+            None,
+        );
+        let result = |data_source: DataSource, content: Option<&str>| KnowledgeResult {
+            distance: 0.9,
+            score: 0.9,
+            data_source,
+            collection: DEFAULT_COLLECTION.to_string(),
+            content: content.map(str::to_string),
+            metadata: None,
+        };
+
+        let report = chat_service
+            .add_knowledge(vec![
+                result(DataSource::Context("deleted/file.rs".to_string()), None),
+                result(
+                    DataSource::Internet("https://a.example".to_string()),
+                    Some("About a"),
+                ),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(report.unreadable, 1);
+        assert_eq!(report.injected.len(), 1);
+        assert_eq!(report.injected[0].origin, "Internet.https://a.example");
+    }
+
+    // Test that cited knowledge is numbered across turns and mapped back to its origin
+    #[tokio::test]
+    async fn test_add_knowledge_with_citations() {
+        let mut chat_service = ChatService::new(
+            Box::new(MockChatBackend::new()),
+            Box::new(NilChatStorage {}),
+            tool_persona(),
+            None,
+        );
+        chat_service.set_cite_knowledge(true);
+        let result = |url: &str| KnowledgeResult {
+            distance: 0.9,
+            score: 0.9,
+            data_source: DataSource::Internet(url.to_string()),
+            collection: DEFAULT_COLLECTION.to_string(),
+            content: Some("Content".to_string()),
+            metadata: None,
+        };
+
+        for url in ["https://a.example", "https://b.example"] {
+            chat_service.add_knowledge(vec![result(url)]).await.unwrap();
+        }
+
+        let prompts = chat_service
+            .messages
+            .iter()
+            .filter(|m| m.content == CITATION_PROMPT)
+            .count();
+        assert_eq!(prompts, 1);
+        let last = chat_service.messages.last().unwrap();
+        assert!(last
+            .content
+            .starts_with("[2] Source: Internet.https://b.example\n"));
+        let sources = chat_service.cited_sources("As documented [2].");
+        assert_eq!(sources.0.len(), 1);
+        assert_eq!(sources.0[0].origin, "Internet.https://b.example");
+    }
+
+    fn add_turn(chat_service: &mut ChatService, turn: usize) {
+        chat_service.messages.push(Message {
+            role: MessageRole::User,
+            content: format!("Question {}", turn),
+            info: None,
+        });
+        chat_service.messages.push(Message {
+            role: MessageRole::Assistant,
+            content: format!("Answer {}", turn),
+            info: None,
+        });
+    }
+
+    fn tool_persona() -> Persona {
+        Persona {
+            name: "test".to_string(),
+            chat_prompt: "Test persona prompt".to_string(),
+            file_types: vec![],
+        }
+    }
+
+    // Requests `create_directory` until `remaining_calls` is used up, then answers
+    struct ToolCallingBackend {
+        arguments: String,
+        remaining_calls: usize,
+    }
+
+    #[async_trait]
+    impl ChatBackend for ToolCallingBackend {
+        async fn send_request(
+            &mut self,
+            messages: &[Message],
+            _tools: &[ToolDefinition],
+        ) -> Result<Message, Box<dyn Error>> {
+            if self.remaining_calls == 0 {
+                return Ok(Message {
+                    role: MessageRole::Assistant,
+                    content: "Done".to_string(),
+                    info: None,
+                });
+            }
+            self.remaining_calls -= 1;
+            Ok(Message {
+                role: MessageRole::ToolCall,
+                content: String::new(),
+                info: Some(MessageInfo::ToolCallInfo {
+                    tool_calls: vec![ToolCall {
+                        id: format!("call_{}", messages.len() - 2),
+                        name: "create_directory".to_string(),
+                        arguments: self.arguments.clone(),
+                    }],
+                }),
+            })
+        }
+
+        fn print_statistics(&self) {}
+    }
+
+    // Answers every request with a fixed summary
+    struct SummaryBackend;
+
+    #[async_trait]
+    impl ChatBackend for SummaryBackend {
+        async fn send_request(
+            &mut self,
+            messages: &[Message],
+            _tools: &[ToolDefinition],
+        ) -> Result<Message, Box<dyn Error>> {
+            assert!(messages[1].content.contains("User: Question 1"));
+            Ok(Message {
+                role: MessageRole::Assistant,
+                content: "Short summary".to_string(),
+                info: Some(MessageInfo::AssistantInfo {
+                    model: "cheap".to_string(),
+                    persona_name: String::new(),
+                    prompt_token: 0,
+                    completion_token: 0,
+                    timestamp: Utc::now(),
+                }),
+            })
+        }
+
+        fn print_statistics(&self) {}
+    }
+
     // Implement a simple ChatBackend mock
     struct MockChatBackend;
 
@@ -428,11 +1283,32 @@ mod tests {
         async fn send_request(
             &mut self,
             _messages: &[Message],
-            _use_tools: bool,
+            _tools: &[ToolDefinition],
         ) -> Result<Message, Box<dyn Error>> {
             Ok(Message::default())
         }
 
+        async fn send_request_stream(
+            &mut self,
+            _messages: &[Message],
+            _tools: &[ToolDefinition],
+            on_delta: &mut (dyn for<'a> FnMut(&'a str) + Send),
+        ) -> Result<Message, Box<dyn Error>> {
+            on_delta("Hello");
+            on_delta(" World");
+            Ok(Message {
+                role: MessageRole::Assistant,
+                content: "Hello World".to_string(),
+                info: Some(MessageInfo::AssistantInfo {
+                    model: "mock".to_string(),
+                    persona_name: String::new(),
+                    prompt_token: 0,
+                    completion_token: 0,
+                    timestamp: Utc::now(),
+                }),
+            })
+        }
+
         fn print_statistics(&self) {}
     }
 }
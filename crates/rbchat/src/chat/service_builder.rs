@@ -7,6 +7,7 @@
 //! - `storage`: A storage backend implementing the `ChatStorage` trait.
 //! - `persona`: A `Persona` that defines the character of the chat interactions.
 //! - `directory`: An optional directory for storing relevant data.
+//! - `tool_registry`: The tools offered to the model, the builtin file tools if not set.
 //!
 //! The `build` method will validate that all required fields are set and create an instance of `ChatService`.
 //!
@@ -14,12 +15,21 @@
 //!
 //! The `build` method will return an error if any of the required fields are not set.
 //! It will also return an error if the specified AI model cannot be found in the configuration.
+//!
+//! The model used to compact long sessions is taken from `compaction_model` or `commit_model`.
+//! If it is not configured, sessions are compacted with the chat model itself.
+//!
+//! The knowledge added per turn is limited by `knowledge_min_similarity` and
+//! `knowledge_max_tokens`, see `KnowledgeLimits`.
 use crate::chat::interface::{ChatBackend, ChatStorage};
+use crate::chat::knowledge_limits::KnowledgeLimits;
 use crate::chat::service::ChatService;
-use crate::config::{AIBackend, CONFIG};
+use crate::config::{AIBackend, Model, CONFIG};
 use crate::persona::Persona;
+use crate::provider::anthropic::anthropic_interface::AnthropicInterface;
 use crate::provider::ollama::ollama_interface::OllamaInterface;
 use crate::provider::openai::openai_interface::OpenAIInterface;
+use crate::tools::ToolRegistry;
 use log::debug;
 use std::error::Error;
 use std::path::PathBuf;
@@ -30,6 +40,7 @@ pub struct ChatServiceBuilder {
     storage: Option<Box<dyn ChatStorage>>,
     persona: Option<Persona>,
     directory: Option<Vec<PathBuf>>,
+    tools: Option<ToolRegistry>,
 }
 
 impl ChatServiceBuilder {
@@ -53,6 +64,11 @@ impl ChatServiceBuilder {
         self
     }
 
+    pub fn tool_registry(mut self, tools: ToolRegistry) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
     // Build method to construct the ChatServiceFactory
     pub fn build(self) -> Result<ChatService, Box<dyn Error>> {
         // Ensure all required fields are set
@@ -69,17 +85,61 @@ impl ChatServiceBuilder {
             .and_then(|models| models.iter().find(|m| m.name == model_name))
             .ok_or_else(|| format!("Model '{}' not found in configuration", model_name))?;
         let timeout_secs = config.ai.chat_timeout_secs;
-        // Check which provider to use based on the model
-        let backend: Box<dyn ChatBackend> = match &model.backend {
-            AIBackend::OpenAI => {
-                Box::new(OpenAIInterface::new(model.api_name.clone(), timeout_secs))
-            } // Additional backends can be added here
-            AIBackend::Ollama => Box::new(OllamaInterface::new(
-                model.api_name.clone(),
-                model.url.clone(),
-            )),
+        let backend = create_backend(model, timeout_secs)?;
+
+        // A cheaper model summarises the older turns when the session grows too large
+        let compaction_model_name = config
+            .ai
+            .compaction_model
+            .as_ref()
+            .unwrap_or(&config.ai.commit_model);
+        let compaction_backend = match config
+            .models
+            .as_ref()
+            .and_then(|models| models.iter().find(|m| &m.name == compaction_model_name))
+        {
+            Some(compaction_model) => Some(create_backend(compaction_model, timeout_secs)?),
+            None => {
+                debug!(
+                    "Compaction model '{}' not found in configuration, using '{}'",
+                    compaction_model_name, model_name
+                );
+                None
+            }
         };
+        let compaction_threshold = config
+            .ai
+            .compaction_threshold
+            .or(model.context_window.map(|window| window * 80 / 100));
 
-        Ok(ChatService::new(backend, storage, persona, self.directory))
+        let mut chat_service = ChatService::new(backend, storage, persona, self.directory);
+        chat_service.set_max_tool_iterations(config.ai.max_tool_iterations);
+        chat_service.set_context_window(model.context_window);
+        chat_service.set_compaction(compaction_backend, compaction_threshold);
+        chat_service.set_knowledge_limits(KnowledgeLimits::new(
+            config.ai.knowledge_min_similarity,
+            config.ai.knowledge_max_tokens,
+            model.context_window,
+        ));
+        if let Some(tools) = self.tools {
+            chat_service.set_tool_registry(tools);
+        }
+        Ok(chat_service)
     }
 }
+
+// Creates the backend of the provider the model is served by
+fn create_backend(
+    model: &Model,
+    timeout_secs: u64,
+) -> Result<Box<dyn ChatBackend>, Box<dyn Error>> {
+    let backend: Box<dyn ChatBackend> = match &model.backend {
+        AIBackend::OpenAI => Box::new(OpenAIInterface::from_model(model, timeout_secs)?), // Additional backends can be added here
+        AIBackend::Ollama => Box::new(OllamaInterface::new(
+            model.api_name.clone(),
+            model.url.clone(),
+        )),
+        AIBackend::Anthropic => Box::new(AnthropicInterface::from_model(model, timeout_secs)?),
+    };
+    Ok(backend)
+}
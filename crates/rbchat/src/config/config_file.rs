@@ -17,6 +17,15 @@
 //! - `ai`: The AI settings, containing the models used for various functionalities.
 //! - `personas`: A list of defined personas that can be utilized for tailored interactions.
 //! - `models`: Additional configurations for AI models, including their identifiers and APIs.
+//!   Each model may point to its own endpoint through `url`, name the environment variable
+//!   holding its key with `api_key_env` and add `headers`, an `organization` or a `project`.
+//!   With `context_window` the files loaded into the context are kept within the model's window.
+//! - `diff_tool`: An optional external diff tool, such as `bcomp` or `meld`, used to review
+//!   file changes proposed by tools. Without it a colored diff is printed to the terminal.
+//! - `embedding`: How texts are embedded: the texts per request, the files embedded at once,
+//!   the requests per minute, the retries of failed requests and the embedding cache.
+//! - `knowledge_store`: The database holding the knowledge. The embedded SurrealDB is used
+//!   unless `backend = "Sqlite"` selects a SQLite file or `url` names a SurrealDB server.
 //!
 //! Here’s an example of how you can utilize this module:
 //!
@@ -46,18 +55,40 @@
 //!
 //! ```toml
 //! default_persona = "rust"
+//! diff_tool = "bcomp"
 //!
 //! [ai]
 //! chat_model = "openai_complex"
 //! commit_model = "openai_fast"
 //! wish_model = "openai_complex"
 //! chat_timeout_secs = 30
+//! max_tool_iterations = 10
+//! knowledge_min_similarity = 0.3
 //!
 //! [[models]]
 //! name = "openai_complex"
 //! api_name = "gpt-4o"
 //! backend = "OpenAI"
 //!
+//! [[models]]
+//! name = "local_vllm"
+//! api_name = "Qwen/Qwen2.5-Coder-32B-Instruct"
+//! backend = "OpenAI"
+//! url = "http://localhost:8000/v1"
+//! api_key_env = "VLLM_KEY"
+//! headers = { "X-Team" = "tools" }
+//! context_window = 32768
+//!
+//! [embedding]
+//! batch_size = 64
+//! requests_per_minute = 500
+//!
+//! [knowledge_store]
+//! backend = "SurrealDb"
+//! url = "ws://localhost:8000"
+//! username = "root"
+//! password_env = "SURREALDB_PASSWORD"
+//!
 //! # Add further models and personas as necessary
 //! ```
 //!
@@ -66,6 +97,7 @@
 use crate::persona::Persona;
 use lazy_static::lazy_static;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
 use std::sync::Mutex;
 
@@ -83,6 +115,12 @@ pub struct Config {
     pub console_log_level: String,
     #[serde(default = "default_file_log_level")]
     pub file_log_level: String,
+    /// External diff tool used to review file changes instead of the built-in diff, e.g. `bcomp`.
+    pub diff_tool: Option<String>,
+    #[serde(default)]
+    pub embedding: EmbeddingConfig,
+    #[serde(default)]
+    pub knowledge_store: KnowledgeStoreConfig,
 }
 
 fn default_console_log_level() -> String {
@@ -108,6 +146,24 @@ pub struct AI {
 
     #[serde(default = "default_timeout_secs")]
     pub chat_timeout_secs: u64,
+
+    #[serde(default = "default_max_tool_iterations")]
+    pub max_tool_iterations: usize,
+
+    /// Model summarising older turns when a chat is compacted, `commit_model` if not set.
+    pub compaction_model: Option<String>,
+
+    /// Estimated prompt size in tokens above which a chat is compacted. Defaults to 80% of
+    /// the chat model's `context_window`.
+    pub compaction_threshold: Option<usize>,
+
+    /// Minimum cosine similarity of knowledge added to a chat, e.g. `0.3`. All results are
+    /// added if not set.
+    pub knowledge_min_similarity: Option<f32>,
+
+    /// Estimated tokens of the knowledge added to a chat per turn. Defaults to 20% of the chat
+    /// model's `context_window`, or 4000 tokens.
+    pub knowledge_max_tokens: Option<usize>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -116,6 +172,16 @@ pub struct Model {
     pub api_name: String,
     pub url: Option<String>,
     pub backend: AIBackend,
+    /// Name of the environment variable holding the API key, e.g. `VLLM_KEY`.
+    pub api_key_env: Option<String>,
+    /// Additional HTTP headers sent with every request to the model's endpoint.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub organization: Option<String>,
+    pub project: Option<String>,
+    /// Size of the model's context window in tokens. When set, the files loaded into the
+    /// context are kept within a share of it.
+    pub context_window: Option<usize>,
 }
 
 #[derive(Debug, Deserialize, Clone, Default)]
@@ -123,6 +189,83 @@ pub enum AIBackend {
     #[default]
     OpenAI,
     Ollama,
+    Anthropic,
+}
+
+/// How texts are embedded with the `embedding_model`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmbeddingConfig {
+    /// Texts sent with one embedding request.
+    #[serde(default = "default_embedding_batch_size")]
+    pub batch_size: usize,
+    /// Files `knowledge init` embeds at once.
+    #[serde(default = "default_embedding_concurrency")]
+    pub concurrency: usize,
+    /// Embedding requests sent per minute at most. Not limited if not set.
+    pub requests_per_minute: Option<u32>,
+    /// Times a failed request is repeated, with a growing pause before each.
+    #[serde(default = "default_embedding_retries")]
+    pub max_retries: u32,
+    /// Keeps the embeddings in `.rusty/embedding_cache`, so texts embedded before with the
+    /// same model are not sent again.
+    #[serde(default = "default_embedding_cache")]
+    pub cache: bool,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        EmbeddingConfig {
+            batch_size: default_embedding_batch_size(),
+            concurrency: default_embedding_concurrency(),
+            requests_per_minute: None,
+            max_retries: default_embedding_retries(),
+            cache: default_embedding_cache(),
+        }
+    }
+}
+
+fn default_embedding_batch_size() -> usize {
+    32
+}
+
+fn default_embedding_concurrency() -> usize {
+    10
+}
+
+fn default_embedding_retries() -> u32 {
+    3
+}
+
+fn default_embedding_cache() -> bool {
+    true
+}
+
+/// The database holding the knowledge.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct KnowledgeStoreConfig {
+    #[serde(default)]
+    pub backend: StoreBackend,
+    /// Address of a running SurrealDB server, e.g. `ws://localhost:8000`. The embedded engine
+    /// storing the knowledge in `.rusty/knowledge` is used if not set.
+    pub url: Option<String>,
+    /// Namespace on the SurrealDB server, `knowledge` if not set.
+    pub namespace: Option<String>,
+    /// Database on the SurrealDB server, `knowledge_db` if not set.
+    pub database: Option<String>,
+    /// Root user signing in to the SurrealDB server. Without it the connection is anonymous.
+    pub username: Option<String>,
+    /// Name of the environment variable holding the password of `username`.
+    pub password_env: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum StoreBackend {
+    /// SurrealDB, embedded or on a server.
+    #[default]
+    SurrealDb,
+    /// A SQLite file in `.rusty/knowledge.sqlite`, searched by brute force. Several processes
+    /// may use it at once.
+    Sqlite,
 }
 
 fn default_ai() -> AI {
@@ -132,6 +275,11 @@ fn default_ai() -> AI {
         chat_model: chat_model(),
         chat_timeout_secs: default_timeout_secs(),
         embedding_model: embedding_model(),
+        max_tool_iterations: default_max_tool_iterations(),
+        compaction_model: None,
+        compaction_threshold: None,
+        knowledge_min_similarity: None,
+        knowledge_max_tokens: None,
     }
 }
 
@@ -139,6 +287,10 @@ fn default_timeout_secs() -> u64 {
     30 // Default timeout duration in seconds
 }
 
+fn default_max_tool_iterations() -> usize {
+    10 // Rounds of tool calls the model may run before answering
+}
+
 fn default_model() -> String {
     "gpt-4o-2024-08-06".to_string()
 }
@@ -202,11 +354,19 @@ impl Default for Config {
                 wish_model: "".to_string(),
                 chat_timeout_secs: default_timeout_secs(),
                 embedding_model: "".to_string(),
+                max_tool_iterations: default_max_tool_iterations(),
+                compaction_model: None,
+                compaction_threshold: None,
+                knowledge_min_similarity: None,
+                knowledge_max_tokens: None,
             },
             personas: vec![],
             models: None,
             console_log_level: default_console_log_level(),
             file_log_level: default_file_log_level(),
+            diff_tool: None,
+            embedding: EmbeddingConfig::default(),
+            knowledge_store: KnowledgeStoreConfig::default(),
         }
     }
 }
@@ -74,6 +74,10 @@ use std::path::PathBuf;
 
 pub use config_file::AIBackend;
 pub use config_file::Config;
+pub use config_file::EmbeddingConfig;
+pub use config_file::KnowledgeStoreConfig;
+pub use config_file::Model;
+pub use config_file::StoreBackend;
 pub use config_file::CONFIG;
 
 static BASE_DIR: &str = ".rusty";
@@ -90,12 +94,40 @@ pub fn get_knowledge_dir() -> Result<PathBuf, String> {
     Ok(config_dir.join("knowledge"))
 }
 
+pub fn get_knowledge_db_file() -> Result<PathBuf, String> {
+    let config_file = get_config_file()?;
+    let config_dir = config_file.parent().expect("Expected a parent directory");
+    Ok(config_dir.join("knowledge.sqlite"))
+}
+
+pub fn get_embedding_cache_dir() -> Result<PathBuf, String> {
+    let config_file = get_config_file()?;
+    let config_dir = config_file.parent().expect("Expected a parent directory");
+    Ok(config_dir.join("embedding_cache"))
+}
+
+pub fn get_journal_dir() -> Result<PathBuf, String> {
+    let config_file = get_config_file()?;
+    let config_dir = config_file.parent().expect("Expected a parent directory");
+    Ok(config_dir.join("journal"))
+}
+
 pub fn get_log_file() -> Result<PathBuf, String> {
     let config_file = get_config_file()?;
     let config_dir = config_file.parent().expect("Expected a parent directory");
     Ok(config_dir.join("rusty.log"))
 }
 
+/// Returns the project root, the directory that contains the `.rusty` directory.
+pub fn get_project_root() -> Result<PathBuf, String> {
+    let config_file = get_config_file()?;
+    let config_dir = config_file.parent().expect("Expected a parent directory");
+    Ok(config_dir
+        .parent()
+        .expect("Expected a project directory")
+        .to_path_buf())
+}
+
 pub fn get_config_file() -> Result<PathBuf, String> {
     get_config_file_from_dir(
         env::current_dir().map_err(|e| format!("Failed to get the current directory: {}", e))?,
@@ -0,0 +1,323 @@
+//! This module decides which files fit into the context window of the model.
+//!
+//! When a model has a `context_window` configured, the files collected for the context are
+//! planned against a `ContextBudget` instead of being added in full:
+//!
+//! - Generated files, such as lock files, minified assets or files marked as generated,
+//!   are skipped.
+//! - Files that are git-dirty come first, followed by the most recently modified files.
+//! - A single file may use at most a quarter of the budget; larger files are truncated.
+//! - Files that no longer fit into the remaining budget are dropped.
+//!
+//! The outcome is described by a `ContextReport`, listing what was included and what was
+//! dropped, so the user knows what the model can see.
+
+use crate::context::tokens::{estimate_tokens, truncate_to_tokens};
+use std::fmt;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Share of the context window, in percent, that files may fill. The rest is left to the
+/// conversation and the answer.
+const FILE_SHARE_PERCENT: usize = 60;
+
+/// File names that are generated by tools and rarely help the model.
+const GENERATED_FILE_NAMES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "composer.lock",
+    "Gemfile.lock",
+    "go.sum",
+];
+
+/// File name suffixes of generated files.
+const GENERATED_SUFFIXES: &[&str] = &[".min.js", ".min.css", ".map", ".pb.go", "_pb2.py"];
+
+/// Markers in the first lines of a file that flag it as generated.
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT", "auto-generated"];
+
+/// The number of tokens files may take up in the context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextBudget {
+    pub max_tokens: usize,
+    /// Files above this size are truncated.
+    pub max_file_tokens: usize,
+}
+
+impl ContextBudget {
+    pub fn for_context_window(context_window: usize) -> Self {
+        let max_tokens = context_window * FILE_SHARE_PERCENT / 100;
+        ContextBudget {
+            max_tokens,
+            max_file_tokens: max_tokens / 4,
+        }
+    }
+}
+
+/// A file that may be added to the context.
+#[derive(Debug, Clone)]
+pub struct CandidateFile {
+    pub path: PathBuf,
+    pub content: String,
+    pub modified: Option<SystemTime>,
+    /// True if the file has uncommitted changes.
+    pub dirty: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncludedFile {
+    pub path: PathBuf,
+    pub tokens: usize,
+    /// True if only the beginning of the file was included.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropReason {
+    Generated,
+    OverBudget,
+    /// The file could not be read, for the given reason.
+    Unreadable(String),
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DropReason::Generated => write!(f, "generated"),
+            DropReason::OverBudget => write!(f, "over budget"),
+            DropReason::Unreadable(reason) => write!(f, "unreadable: {}", reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DroppedFile {
+    pub path: PathBuf,
+    pub tokens: usize,
+    pub reason: DropReason,
+}
+
+/// Describes which files were added to the context and which were left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextReport {
+    pub included: Vec<IncludedFile>,
+    pub dropped: Vec<DroppedFile>,
+}
+
+impl ContextReport {
+    pub fn included_tokens(&self) -> usize {
+        self.included.iter().map(|file| file.tokens).sum()
+    }
+
+    /// True if every file was included in full.
+    pub fn is_complete(&self) -> bool {
+        self.dropped.is_empty() && !self.included.iter().any(|file| file.truncated)
+    }
+}
+
+impl fmt::Display for ContextReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Context: {} files with ~{} tokens included, {} dropped",
+            self.included.len(),
+            self.included_tokens(),
+            self.dropped.len()
+        )?;
+        for file in &self.included {
+            if file.truncated {
+                writeln!(
+                    f,
+                    "  ~ {} (truncated to ~{} tokens)",
+                    file.path.display(),
+                    file.tokens
+                )?;
+            } else {
+                writeln!(f, "  + {} (~{} tokens)", file.path.display(), file.tokens)?;
+            }
+        }
+        for file in &self.dropped {
+            writeln!(
+                f,
+                "  - {} (~{} tokens, {})",
+                file.path.display(),
+                file.tokens,
+                file.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Plans the files to add to the context. Without a budget every file is included in the
+/// given order. Returns the files with the content to add and the report.
+pub fn plan_context(
+    mut candidates: Vec<CandidateFile>,
+    budget: Option<&ContextBudget>,
+) -> (Vec<(PathBuf, String)>, ContextReport) {
+    let mut report = ContextReport::default();
+    let mut selected = Vec::new();
+
+    let Some(budget) = budget else {
+        for candidate in candidates {
+            report.included.push(IncludedFile {
+                path: candidate.path.clone(),
+                tokens: estimate_tokens(&candidate.content),
+                truncated: false,
+            });
+            selected.push((candidate.path, candidate.content));
+        }
+        return (selected, report);
+    };
+
+    // Dirty files first, then the most recently modified ones
+    candidates.sort_by(|a, b| {
+        b.dirty
+            .cmp(&a.dirty)
+            .then_with(|| b.modified.cmp(&a.modified))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+
+    let mut remaining = budget.max_tokens;
+    for candidate in candidates {
+        let tokens = estimate_tokens(&candidate.content);
+        if is_generated(&candidate) {
+            report.dropped.push(DroppedFile {
+                path: candidate.path,
+                tokens,
+                reason: DropReason::Generated,
+            });
+            continue;
+        }
+
+        let (content, included_tokens, truncated) = if tokens > budget.max_file_tokens {
+            let head = truncate_to_tokens(&candidate.content, budget.max_file_tokens);
+            let content = format!(
+                "{}\n[... truncated, the file has ~{} tokens]\n",
+                head, tokens
+            );
+            let included_tokens = estimate_tokens(&content);
+            (content, included_tokens, true)
+        } else {
+            (candidate.content, tokens, false)
+        };
+
+        if included_tokens > remaining {
+            report.dropped.push(DroppedFile {
+                path: candidate.path,
+                tokens,
+                reason: DropReason::OverBudget,
+            });
+            continue;
+        }
+        remaining -= included_tokens;
+        report.included.push(IncludedFile {
+            path: candidate.path.clone(),
+            tokens: included_tokens,
+            truncated,
+        });
+        selected.push((candidate.path, content));
+    }
+    (selected, report)
+}
+
+fn is_generated(candidate: &CandidateFile) -> bool {
+    let name = candidate
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    GENERATED_FILE_NAMES.contains(&&*name)
+        || GENERATED_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+        || candidate
+            .content
+            .lines()
+            .take(5)
+            .any(|line| GENERATED_MARKERS.iter().any(|marker| line.contains(marker)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn candidate(path: &str, tokens: usize, age_secs: u64, dirty: bool) -> CandidateFile {
+        CandidateFile {
+            path: PathBuf::from(path),
+            content: "abc\n".repeat(tokens),
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 - age_secs)),
+            dirty,
+        }
+    }
+
+    #[test]
+    fn test_plan_without_budget_keeps_everything() {
+        let candidates = vec![
+            candidate("a.rs", 10, 0, false),
+            candidate("b.rs", 20, 0, false),
+        ];
+
+        let (selected, report) = plan_context(candidates, None);
+
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[0].0, PathBuf::from("a.rs"));
+        assert!(report.is_complete());
+        assert_eq!(report.included_tokens(), 30);
+    }
+
+    #[test]
+    fn test_plan_prioritises_dirty_and_recent_files() {
+        let budget = ContextBudget {
+            max_tokens: 100,
+            max_file_tokens: 60,
+        };
+        let candidates = vec![
+            candidate("old.rs", 40, 500, false),
+            candidate("recent.rs", 40, 10, false),
+            candidate("dirty.rs", 40, 900, true),
+        ];
+
+        let (selected, report) = plan_context(candidates, Some(&budget));
+
+        let paths: Vec<_> = selected.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("dirty.rs"), PathBuf::from("recent.rs")]
+        );
+        assert_eq!(report.dropped[0].path, PathBuf::from("old.rs"));
+        assert_eq!(report.dropped[0].reason, DropReason::OverBudget);
+    }
+
+    #[test]
+    fn test_plan_skips_generated_and_truncates_large_files() {
+        let budget = ContextBudget {
+            max_tokens: 1000,
+            max_file_tokens: 50,
+        };
+        let mut generated = candidate("schema.rs", 5, 0, false);
+        generated.content = format!("// @generated by a tool\n{}", generated.content);
+        let candidates = vec![
+            candidate("Cargo.lock", 5, 0, false),
+            generated,
+            candidate("big.rs", 200, 0, false),
+        ];
+
+        let (selected, report) = plan_context(candidates, Some(&budget));
+
+        assert_eq!(selected.len(), 1);
+        assert!(selected[0].1.contains("[... truncated"));
+        assert!(report.included[0].truncated);
+        assert!(report.included[0].tokens < 100);
+        assert_eq!(report.dropped.len(), 2);
+        assert!(report
+            .dropped
+            .iter()
+            .all(|file| file.reason == DropReason::Generated));
+        assert!(!report.is_complete());
+    }
+}
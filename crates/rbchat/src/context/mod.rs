@@ -20,21 +20,44 @@
 //!   the context of any `ContextConsumer`, filtering based on provided file types
 //!   and respecting `.gitignore`.
 //!
+//! - `load_files_into_context_with_budget`: Loads the files of several directories,
+//!   planning them against the token budget of the model (see `budget`) and returning
+//!   a `ContextReport` of what was included and dropped.
+//!
 //! - `add_to_context`: Appends the contents of a file to the context,
 //!   including the relative path and its content to aid in maintaining an accurate
 //!   context for the conversation.
 //!
+//! - `read_project_file`: Reads a project file as it is. Only PDF and DOCX documents and
+//!   notebooks go through their extractor, so the model sees the same lines the file tools
+//!   edit, markup included.
+//!
+//! The `tokens` submodule estimates the token count of texts.
+//!
 //! In the example above, `load_files_into_context` is used to populate the context
 //! of `ChatService` with all relevant files from the specified directory.
 //! This implementation supports a decoupled and reusable context management
 //!
 //! system, enhancing the application’s modularity.
 
+mod budget;
+mod tokens;
+
+pub use budget::{
+    plan_context, CandidateFile, ContextBudget, ContextReport, DropReason, DroppedFile,
+    IncludedFile,
+};
+pub use tokens::{estimate_tokens, truncate_to_tokens};
+
+use crate::extract::{DocxExtractor, ExtractorRegistry, NotebookExtractor, PdfExtractor};
 use ignore::WalkBuilder;
+use log::{info, warn};
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 /// This trait defines a common interface for managing context in chat applications.       
 /// It allows different components to implement context-related functionalities,           
@@ -51,6 +74,11 @@ use std::path::Path;
 ///                                                                                        
 pub trait ContextConsumer {
     fn consume(&mut self, filename: Cow<str>, content: Cow<str>) -> Result<(), Box<dyn Error>>;
+
+    /// Called for a file that could not be read; it is left out of the context.
+    fn skip(&mut self, filename: Cow<str>, error: Box<dyn Error>) {
+        warn!("Skipping {}: {}", filename, error);
+    }
 }
 
 /// Loads files from a specified directory into a context, filtering based on                                
@@ -84,11 +112,97 @@ pub fn load_files_into_context<T: ContextConsumer>(
     directory: &Path,
     file_types_or_names: &[String], // A slice of strings representing file extensions or specific filenames to include
 ) -> Result<(), Box<dyn Error>> {
+    for file_path in matching_files(directory, file_types_or_names)? {
+        // Add the file content to the context
+        add_to_context(consumer, &file_path)?;
+    }
+    Ok(())
+}
+
+/// Loads the matching files of all `directories` into the context, keeping them within
+/// `budget` if one is given. Without a budget every file is added in full, like
+/// `load_files_into_context` does.
+///
+/// # Returns
+///
+/// * `Result<ContextReport, Box<dyn Error>>` - The files that were included and dropped.
+pub fn load_files_into_context_with_budget<T: ContextConsumer>(
+    consumer: &mut T,
+    directories: &[PathBuf],
+    file_types_or_names: &[String],
+    budget: Option<&ContextBudget>,
+) -> Result<ContextReport, Box<dyn Error>> {
+    let current_dir = std::env::current_dir()
+        .map_err(|e| format!("Failed to get current directory for context: {}", e))?;
+
+    let mut candidates = Vec::new();
+    let mut unreadable = Vec::new();
+    for directory in directories {
+        let dirty = git_dirty_files(directory);
+        for file_path in matching_files(directory, file_types_or_names)? {
+            // Show the path relative to the current directory
+            let path = file_path
+                .strip_prefix(&current_dir)
+                .unwrap_or(&file_path)
+                .to_path_buf();
+            let content = match read_project_file(&file_path) {
+                Ok(content) => content,
+                Err(e) => {
+                    warn!("{}", e);
+                    unreadable.push(DroppedFile {
+                        path,
+                        tokens: 0,
+                        reason: DropReason::Unreadable(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+            let modified = fs::metadata(&file_path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            let is_dirty = file_path
+                .canonicalize()
+                .map(|path| dirty.contains(&path))
+                .unwrap_or(false);
+            candidates.push(CandidateFile {
+                path,
+                content,
+                modified,
+                dirty: is_dirty,
+            });
+        }
+    }
+
+    let (selected, mut report) = plan_context(candidates, budget);
+    report.dropped.extend(unreadable);
+    for (path, content) in selected {
+        consumer.consume(path.to_string_lossy(), Cow::Owned(content))?;
+    }
+    info!("{}", report);
+    Ok(report)
+}
+
+/// Returns the text of the project file at `path`. PDF and DOCX documents and notebooks are
+/// read by their extractor, every other file is read as plain text.
+pub fn read_project_file(path: &Path) -> Result<String, Box<dyn Error>> {
+    let mut extractors = ExtractorRegistry::empty();
+    extractors.register_extractor(Box::new(PdfExtractor));
+    extractors.register_extractor(Box::new(DocxExtractor));
+    extractors.register_extractor(Box::new(NotebookExtractor));
+    extractors.extract_file(path)
+}
+
+/// Collects the files below `directory` matching the file types or names, respecting `.gitignore`.
+pub(crate) fn matching_files(
+    directory: &Path,
+    file_types_or_names: &[String],
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
     // Create a directory walker using the `WalkBuilder`, which respects .gitignore rules
     let walker = WalkBuilder::new(directory)
         .standard_filters(true) // Apply standard .gitignore rules
         .build();
 
+    let mut files = Vec::new();
     // Iterate over the directory entries returned by the walker
     for result in walker {
         let entry = result?;
@@ -104,13 +218,41 @@ pub fn load_files_into_context<T: ContextConsumer>(
                     .iter()
                     .any(|filter| name == filter || name.ends_with(&format!(".{}", filter)))
                 {
-                    // Add the file content to the context
-                    add_to_context(consumer, file_path)?;
+                    files.push(file_path.to_path_buf());
                 }
             }
         }
     }
-    Ok(())
+    Ok(files)
+}
+
+/// Returns the canonical paths of the files with uncommitted changes in the git repository
+/// containing `directory`. Outside of a repository, or without git, the set is empty.
+fn git_dirty_files(directory: &Path) -> HashSet<PathBuf> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(directory)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let (Some(top_level), Some(status)) = (
+        git(&["rev-parse", "--show-toplevel"]),
+        git(&["status", "--porcelain", "--no-renames"]),
+    ) else {
+        return HashSet::new();
+    };
+    let top_level = PathBuf::from(top_level.trim());
+
+    status
+        .lines()
+        // Each line is "XY path", with the path relative to the top level of the repository
+        .filter_map(|line| line.get(3..))
+        .filter_map(|path| top_level.join(path.trim_matches('"')).canonicalize().ok())
+        .collect()
 }
 
 /// Helper function to append the content of a file to the context, given its path.
@@ -123,12 +265,12 @@ pub fn load_files_into_context<T: ContextConsumer>(
 ///
 /// # Returns
 ///
-/// * `Result<(), Box<dyn Error>>` - A result indicating success or failure to read the file content.
+/// * `Result<(), Box<dyn Error>>` - A result indicating success or failure to add the file content.
+///   A file that cannot be read is passed to `ContextConsumer::skip` instead.
 ///
 /// # Errors
 ///
-/// This function can return errors due to failures in reading the file or finding the current
-/// working directory.
+/// This function can return errors due to failures in finding the current working directory.
 fn add_to_context<T: ContextConsumer>(
     consumer: &mut T,
     file_path: &Path,
@@ -137,16 +279,58 @@ fn add_to_context<T: ContextConsumer>(
     let current_dir = std::env::current_dir()
         .map_err(|e| format!("Failed to get current directory for context: {}", e))?;
 
-    // Read the file content, returning a formatted error message if unsuccessful
-    let content = fs::read_to_string(file_path)
-        .map_err(|e| format!("Failed to read file '{}': {}", file_path.display(), e))?;
-
     // Strip the current directory prefix from the file path to display a relative path
     let relative_path = file_path
         .strip_prefix(&current_dir)
         .unwrap_or(file_path)
         .to_string_lossy();
-    consumer.consume(relative_path, Cow::Owned(content))?;
+
+    // Read the text of the file, skipping it if unsuccessful
+    match read_project_file(file_path) {
+        Ok(content) => consumer.consume(relative_path, Cow::Owned(content))?,
+        Err(e) => consumer.skip(relative_path, e),
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Files(Vec<(String, String)>);
+
+    impl ContextConsumer for Files {
+        fn consume(&mut self, filename: Cow<str>, content: Cow<str>) -> Result<(), Box<dyn Error>> {
+            self.0.push((filename.into_owned(), content.into_owned()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_project_files_are_read_as_text_and_unreadable_files_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let html = "<html><nav>Menu</nav><script>init();</script></html>\n";
+        fs::write(dir.path().join("index.html"), html).unwrap();
+        fs::write(dir.path().join("broken.pdf"), b"not a pdf").unwrap();
+
+        let mut files = Files::default();
+        let report = load_files_into_context_with_budget(
+            &mut files,
+            &[dir.path().to_path_buf()],
+            &["html".to_string(), "pdf".to_string()],
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(files.0.len(), 1);
+        assert_eq!(files.0[0].1, html);
+        assert_eq!(report.dropped.len(), 1);
+        assert!(report.dropped[0].path.ends_with("broken.pdf"));
+        assert!(matches!(
+            report.dropped[0].reason,
+            DropReason::Unreadable(_)
+        ));
+    }
+}
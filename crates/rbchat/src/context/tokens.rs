@@ -0,0 +1,40 @@
+//! This module estimates how many tokens a text takes up in the model's context window.
+//!
+//! The backends use different tokenizers, so an exact count is not available before a request
+//! is sent. The estimate assumes about four characters per token, which is close for English
+//! text and source code with the tokenizers of OpenAI, Anthropic and the common Ollama models.
+
+/// Average number of characters per token assumed by the estimate.
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimates the number of tokens of `text`, rounding up.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Cuts `text` after roughly `max_tokens` tokens, at a line end where possible.
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> &str {
+    let max_chars = max_tokens * CHARS_PER_TOKEN;
+    let Some((cut, _)) = text.char_indices().nth(max_chars) else {
+        return text;
+    };
+    match text[..cut].rfind('\n') {
+        Some(line_end) => &text[..=line_end],
+        None => &text[..cut],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_and_truncate() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcde"), 2);
+
+        let text = "line one\nline two\nline three\n";
+        assert_eq!(truncate_to_tokens(text, 100), text);
+        assert_eq!(truncate_to_tokens(text, 5), "line one\nline two\n");
+    }
+}
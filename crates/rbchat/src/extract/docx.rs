@@ -0,0 +1,83 @@
+use crate::extract::TextExtractor;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::error::Error;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// Reads the text of the paragraphs of Word documents. Headers, footers and comments are
+/// left out.
+pub struct DocxExtractor;
+
+impl TextExtractor for DocxExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/vnd.openxmlformats-officedocument.wordprocessingml.document"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["docx"]
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+        // A DOCX file is a ZIP archive, the body of the document is an XML file in it
+        let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+        let mut xml = String::new();
+        archive
+            .by_name("word/document.xml")?
+            .read_to_string(&mut xml)?;
+        document_text(&xml)
+    }
+}
+
+// Collects the runs of text of `word/document.xml`, one paragraph per line
+fn document_text(xml: &str) -> Result<String, Box<dyn Error>> {
+    let mut reader = Reader::from_str(xml);
+    let mut text = String::new();
+    let mut in_text = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) if element.name().as_ref() == b"w:t" => in_text = true,
+            Event::End(element) => match element.name().as_ref() {
+                b"w:t" => in_text = false,
+                b"w:p" => text.push('\n'),
+                _ => {}
+            },
+            Event::Empty(element) => match element.name().as_ref() {
+                b"w:p" | b"w:br" | b"w:cr" => text.push('\n'),
+                b"w:tab" => text.push('\t'),
+                _ => {}
+            },
+            Event::Text(content) if in_text => text.push_str(&content.unescape()?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    #[test]
+    fn test_docx_paragraphs() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+<w:p><w:r><w:t>Rust &amp; </w:t></w:r><w:r><w:t xml:space="preserve">Cargo</w:t></w:r></w:p>
+<w:p/>
+<w:p><w:r><w:t>Step</w:t><w:tab/><w:t>one</w:t></w:r></w:p>
+</w:body></w:document>"#;
+        let mut docx = ZipWriter::new(Cursor::new(Vec::new()));
+        docx.start_file("word/document.xml", SimpleFileOptions::default())
+            .unwrap();
+        docx.write_all(xml.as_bytes()).unwrap();
+        let bytes = docx.finish().unwrap().into_inner();
+
+        let text = DocxExtractor.extract(&bytes).unwrap();
+
+        assert_eq!(text, "Rust & Cargo\n\nStep\tone\n");
+    }
+}
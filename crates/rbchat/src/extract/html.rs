@@ -0,0 +1,145 @@
+use crate::extract::TextExtractor;
+use scraper::{ElementRef, Html, Selector};
+use std::error::Error;
+
+/// Elements that hold no readable content of the page.
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "canvas", "iframe", "nav", "header",
+    "footer", "aside", "form", "button",
+];
+
+/// Elements that start a new line.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "hr",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "ul",
+    "ol",
+    "dl",
+    "dt",
+    "dd",
+    "tr",
+    "table",
+    "blockquote",
+    "pre",
+    "figure",
+    "figcaption",
+];
+
+/// Reads the readable text of HTML pages.
+pub struct HtmlExtractor;
+
+impl TextExtractor for HtmlExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["text/html", "application/xhtml+xml"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["html", "htm", "xhtml"]
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+        let document = Html::parse_document(&String::from_utf8_lossy(bytes));
+        // The main content of the page, if it is marked, leaves out the rest of the layout
+        let main = Selector::parse("main, article, [role=main]").unwrap();
+        let body = Selector::parse("body").unwrap();
+        let root = document
+            .select(&main)
+            .next()
+            .or_else(|| document.select(&body).next())
+            .unwrap_or_else(|| document.root_element());
+
+        let mut text = String::new();
+        collect_text(root, &mut text);
+        Ok(tidy_lines(&text))
+    }
+}
+
+// Appends the text of `element` to `text`, starting a line for every block
+fn collect_text(element: ElementRef, text: &mut String) {
+    let name = element.value().name();
+    if SKIPPED_ELEMENTS.contains(&name) {
+        return;
+    }
+    if name == "br" {
+        text.push('\n');
+        return;
+    }
+    let is_block = BLOCK_ELEMENTS.contains(&name);
+    if is_block {
+        text.push('\n');
+    }
+    for child in element.children() {
+        if let Some(child) = ElementRef::wrap(child) {
+            collect_text(child, text);
+        } else if let Some(content) = child.value().as_text() {
+            if name == "pre" {
+                text.push_str(content);
+            } else {
+                // Line breaks in the markup are not line breaks of the page
+                let words: Vec<&str> = content.split_whitespace().collect();
+                if content.starts_with(char::is_whitespace)
+                    && !words.is_empty()
+                    && !text.ends_with(char::is_whitespace)
+                {
+                    text.push(' ');
+                }
+                text.push_str(&words.join(" "));
+                if content.ends_with(char::is_whitespace) && !words.is_empty() {
+                    text.push(' ');
+                }
+            }
+        }
+    }
+    if is_block {
+        text.push('\n');
+    }
+}
+
+// Trims the lines and keeps at most one empty line between paragraphs
+fn tidy_lines(text: &str) -> String {
+    let mut tidy = String::new();
+    let mut empty_lines = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            empty_lines += 1;
+            continue;
+        }
+        if !tidy.is_empty() {
+            tidy.push_str(if empty_lines > 0 { "\n\n" } else { "\n" });
+        }
+        tidy.push_str(line);
+        empty_lines = 0;
+    }
+    tidy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_boilerplate_is_stripped() {
+        let page = r#"<html><head><title>Guide</title><style>p { color: red }</style></head>
+            <body><nav><a href="/">Home</a></nav>
+            <main><h1>Installing</h1><p>Run
+                <code>cargo install</code>.</p><script>track()</script>
+            <pre>line 1
+  line 2</pre></main>
+            <footer>Copyright</footer></body></html>"#;
+
+        let text = HtmlExtractor.extract(page.as_bytes()).unwrap();
+
+        assert_eq!(text, "Installing\n\nRun cargo install.\n\nline 1\n  line 2");
+    }
+}
@@ -0,0 +1,160 @@
+//! This module turns documents into the plain text that is embedded as knowledge or loaded
+//! into the context of a chat.
+//!
+//! Source code and other text files are used as they are. Other formats are read by a
+//! `TextExtractor`, chosen by the MIME type of the document or, if it is unknown, by the
+//! extension of its name:
+//!
+//! - **HTML:** The readable text of the page. Scripts, styles, navigation, headers, footers
+//!   and forms are stripped; the `main` or `article` element is used if there is one.
+//! - **PDF:** The text of all pages.
+//! - **DOCX:** The text of the paragraphs of a Word document.
+//! - **Notebooks:** The Markdown and code cells of a Jupyter notebook, without outputs.
+//!
+//! Further formats can be supported by registering an extractor with an `ExtractorRegistry`.
+
+mod docx;
+mod html;
+mod notebook;
+mod pdf;
+
+pub use docx::DocxExtractor;
+pub use html::HtmlExtractor;
+pub use notebook::NotebookExtractor;
+pub use pdf::PdfExtractor;
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Reads the text of documents of some formats.
+pub trait TextExtractor: Send + Sync {
+    /// The MIME types of the documents read, e.g. `application/pdf`.
+    fn mime_types(&self) -> &'static [&'static str];
+    /// The file extensions of the documents read, without the dot.
+    fn extensions(&self) -> &'static [&'static str];
+    fn extract(&self, bytes: &[u8]) -> Result<String, Box<dyn Error>>;
+}
+
+/// Holds the extractors and picks the one for a document. Documents no extractor reads are
+/// taken as UTF-8 text.
+pub struct ExtractorRegistry {
+    extractors: Vec<Box<dyn TextExtractor>>,
+}
+
+impl Default for ExtractorRegistry {
+    fn default() -> Self {
+        let mut registry = ExtractorRegistry::empty();
+        registry.register_extractor(Box::new(HtmlExtractor));
+        registry.register_extractor(Box::new(PdfExtractor));
+        registry.register_extractor(Box::new(DocxExtractor));
+        registry.register_extractor(Box::new(NotebookExtractor));
+        registry
+    }
+}
+
+impl ExtractorRegistry {
+    /// A registry without extractors, which reads every document as text.
+    pub fn empty() -> Self {
+        ExtractorRegistry { extractors: vec![] }
+    }
+
+    /// Adds an extractor. It takes precedence over the extractors registered before.
+    pub fn register_extractor(&mut self, extractor: Box<dyn TextExtractor>) {
+        self.extractors.insert(0, extractor);
+    }
+
+    /// Returns the extractor for a document of type `mime_type` named `name`, a path or URL.
+    pub fn find(&self, mime_type: Option<&str>, name: &str) -> Option<&dyn TextExtractor> {
+        // Parameters like `; charset=utf-8` do not change the format
+        let mime_type = mime_type
+            .and_then(|mime_type| mime_type.split(';').next())
+            .map(|mime_type| mime_type.trim().to_lowercase());
+        let by_mime_type = mime_type.and_then(|mime_type| {
+            self.extractors
+                .iter()
+                .find(|extractor| extractor.mime_types().contains(&mime_type.as_str()))
+        });
+        let by_extension = || {
+            let extension = extension_of(name)?;
+            self.extractors
+                .iter()
+                .find(|extractor| extractor.extensions().contains(&extension.as_str()))
+        };
+        by_mime_type
+            .or_else(by_extension)
+            .map(|extractor| &**extractor)
+    }
+
+    /// Returns the text of the document `bytes` of type `mime_type` named `name`.
+    pub fn extract(
+        &self,
+        bytes: &[u8],
+        mime_type: Option<&str>,
+        name: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        match self.find(mime_type, name) {
+            Some(extractor) => extractor.extract(bytes),
+            None => Ok(String::from_utf8(bytes.to_vec())
+                .map_err(|_| format!("'{}' is neither text nor a supported document", name))?),
+        }
+    }
+
+    /// Returns the text of the file at `path`, chosen by its extension.
+    pub fn extract_file(&self, path: &Path) -> Result<String, Box<dyn Error>> {
+        let bytes = fs::read(path)
+            .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+        self.extract(&bytes, None, &path.to_string_lossy())
+            .map_err(|e| {
+                format!("Failed to extract the text of '{}': {}", path.display(), e).into()
+            })
+    }
+}
+
+/// Returns the text of the file at `path` with the builtin extractors.
+pub fn extract_file(path: &Path) -> Result<String, Box<dyn Error>> {
+    ExtractorRegistry::default().extract_file(path)
+}
+
+// Returns the lowercase extension of a path or URL, ignoring the query and fragment of URLs
+fn extension_of(name: &str) -> Option<String> {
+    let path = name.split(['?', '#']).next()?;
+    let file_name = path.rsplit(['/', '\\']).next()?;
+    let (_, extension) = file_name.rsplit_once('.')?;
+    Some(extension.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extractor_is_chosen_by_mime_type_or_extension() {
+        let registry = ExtractorRegistry::default();
+        let extensions = |mime_type, name| {
+            registry
+                .find(mime_type, name)
+                .map(|extractor| extractor.extensions()[0])
+        };
+
+        assert_eq!(
+            extensions(Some("text/html; charset=utf-8"), "https://example.com/"),
+            Some("html")
+        );
+        assert_eq!(
+            extensions(None, "https://example.com/paper.PDF?download=1"),
+            Some("pdf")
+        );
+        assert_eq!(
+            extensions(Some("application/octet-stream"), "docs/spec.docx"),
+            Some("docx")
+        );
+        assert_eq!(extensions(None, "src/main.rs"), None);
+
+        let text = registry
+            .extract(b"fn main() {}", None, "src/main.rs")
+            .unwrap();
+        assert_eq!(text, "fn main() {}");
+        assert!(registry.extract(&[0xff, 0xfe], None, "image.bin").is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use crate::extract::TextExtractor;
+use serde_json::Value;
+use std::error::Error;
+
+/// Reads the Markdown and code cells of Jupyter notebooks. Outputs are left out, they are
+/// often large and can be produced again.
+pub struct NotebookExtractor;
+
+impl TextExtractor for NotebookExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/x-ipynb+json"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["ipynb"]
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+        let notebook: Value = serde_json::from_slice(bytes)?;
+        let language = notebook["metadata"]["language_info"]["name"]
+            .as_str()
+            .or_else(|| notebook["metadata"]["kernelspec"]["language"].as_str())
+            .unwrap_or("python");
+        let cells = notebook["cells"]
+            .as_array()
+            .ok_or("The notebook has no cells")?;
+
+        let mut sections = Vec::new();
+        for cell in cells {
+            let source = cell_source(&cell["source"]);
+            if source.trim().is_empty() {
+                continue;
+            }
+            match cell["cell_type"].as_str() {
+                Some("markdown") => sections.push(source),
+                Some("code") => {
+                    sections.push(format!("```{}\n{}\n```", language, source.trim_end()))
+                }
+                _ => {}
+            }
+        }
+        Ok(sections.join("\n\n"))
+    }
+}
+
+// The source of a cell is a string or a list of lines
+fn cell_source(source: &Value) -> String {
+    match source {
+        Value::String(source) => source.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notebook_cells_without_outputs() {
+        let notebook = r##"{
+            "metadata": {"kernelspec": {"language": "python"}},
+            "cells": [
+                {"cell_type": "markdown", "source": ["# Analysis\n", "Load the data."]},
+                {"cell_type": "code", "source": "df = load()\n", "outputs": [{"text": "ok"}]},
+                {"cell_type": "code", "source": []}
+            ]
+        }"##;
+
+        let text = NotebookExtractor.extract(notebook.as_bytes()).unwrap();
+
+        assert_eq!(
+            text,
+            "# Analysis\nLoad the data.\n\n```python\ndf = load()\n```"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use crate::extract::TextExtractor;
+use std::error::Error;
+
+/// Reads the text of all pages of PDF documents.
+pub struct PdfExtractor;
+
+impl TextExtractor for PdfExtractor {
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/pdf"]
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pdf"]
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+        let text = pdf_extract::extract_text_from_mem(bytes)
+            .map_err(|e| format!("Failed to read the PDF: {}", e))?;
+        if text.trim().is_empty() {
+            // Scanned documents only contain images of their pages
+            return Err("The PDF contains no text".into());
+        }
+        Ok(text)
+    }
+}
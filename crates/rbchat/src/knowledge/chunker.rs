@@ -0,0 +1,260 @@
+//! This module splits documents into overlapping chunks before they are embedded.
+//!
+//! Embedding a whole file as one vector hides everything the embedding model cuts off, and a
+//! single vector describes a large file only vaguely. The `Chunker` therefore cuts a text into
+//! windows of at most `max_chars` characters, each repeating about `overlap_chars` characters
+//! of its predecessor, so a passage split at a window border is still found as a whole.
+//!
+//! - Windows end at line breaks, so code is never cut in the middle of a line. Only lines
+//!   longer than a window are split.
+//! - Markdown files are first split at their headings. Small sections are merged into one
+//!   window, so a chunk does not span a heading unless the sections are short.
+//!
+//! Every chunk knows its index and line range, which are stored with the record as
+//! `KnowledgeMetadata`.
+
+use crate::knowledge::KnowledgeMetadata;
+use std::ops::Range;
+
+/// Default size of a chunk, about 1000 tokens.
+pub const DEFAULT_MAX_CHARS: usize = 4_000;
+/// Default number of characters a chunk repeats from its predecessor.
+pub const DEFAULT_OVERLAP_CHARS: usize = 400;
+
+/// File extensions that are split at their headings.
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdx"];
+
+/// A part of a document that is embedded as its own record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// Position of the chunk within the document, starting at 0.
+    pub index: usize,
+    /// First line of the chunk, 1-based.
+    pub start_line: usize,
+    /// Last line of the chunk, 1-based and inclusive.
+    pub end_line: usize,
+    pub content: String,
+}
+
+impl Chunk {
+    /// Returns the metadata stored with the record of this chunk of `file`.
+    pub fn metadata(&self, file: &str) -> KnowledgeMetadata {
+        KnowledgeMetadata {
+            file: file.to_string(),
+            chunk_index: self.index,
+            start_line: self.start_line,
+            end_line: self.end_line,
+            ..KnowledgeMetadata::default()
+        }
+    }
+}
+
+/// Splits texts into overlapping chunks.
+#[derive(Debug, Clone)]
+pub struct Chunker {
+    max_chars: usize,
+    overlap_chars: usize,
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Chunker::new(DEFAULT_MAX_CHARS, DEFAULT_OVERLAP_CHARS)
+    }
+}
+
+/// A line of the document, or a piece of a line longer than a chunk.
+struct Line<'a> {
+    number: usize,
+    text: &'a str,
+}
+
+impl Line<'_> {
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+}
+
+impl Chunker {
+    /// Creates a chunker for windows of `max_chars` characters. The overlap is limited to half
+    /// a window, so every window moves on.
+    pub fn new(max_chars: usize, overlap_chars: usize) -> Self {
+        let max_chars = max_chars.max(1);
+        Chunker {
+            max_chars,
+            overlap_chars: overlap_chars.min(max_chars / 2),
+        }
+    }
+
+    /// Splits `text`, read from `path`, into chunks. The path selects the splitting strategy;
+    /// texts that only consist of whitespace yield no chunks.
+    pub fn chunk(&self, path: &str, text: &str) -> Vec<Chunk> {
+        let lines = self.split_lines(text);
+        let sections = if is_markdown(path) {
+            markdown_sections(&lines)
+        } else {
+            // The whole text is one section
+            std::iter::once(0..lines.len()).collect()
+        };
+
+        let mut chunks = Vec::new();
+        for section in self.merge_sections(&lines, sections) {
+            self.window(&lines[section], &mut chunks);
+        }
+        chunks
+    }
+
+    // Splits the text into lines, keeping the line breaks, and cuts lines that do not fit
+    // into a single chunk
+    fn split_lines<'a>(&self, text: &'a str) -> Vec<Line<'a>> {
+        let mut lines = Vec::new();
+        for (index, mut line) in text.split_inclusive('\n').enumerate() {
+            while let Some((cut, _)) = line.char_indices().nth(self.max_chars) {
+                lines.push(Line {
+                    number: index + 1,
+                    text: &line[..cut],
+                });
+                line = &line[cut..];
+            }
+            lines.push(Line {
+                number: index + 1,
+                text: line,
+            });
+        }
+        lines
+    }
+
+    // Combines neighbouring sections as long as they fit into one chunk together
+    fn merge_sections(&self, lines: &[Line], sections: Vec<Range<usize>>) -> Vec<Range<usize>> {
+        let size = |range: Range<usize>| lines[range].iter().map(Line::len).sum::<usize>();
+        let mut merged: Vec<Range<usize>> = Vec::new();
+        for section in sections {
+            if let Some(last) = merged.last_mut() {
+                if size(last.clone()) + size(section.clone()) <= self.max_chars {
+                    last.end = section.end;
+                    continue;
+                }
+            }
+            merged.push(section);
+        }
+        merged
+    }
+
+    // Cuts the lines into windows of at most `max_chars`, each starting with the last lines of
+    // its predecessor
+    fn window(&self, lines: &[Line], chunks: &mut Vec<Chunk>) {
+        let mut start = 0;
+        while start < lines.len() {
+            let mut end = start;
+            let mut size = 0;
+            while end < lines.len() && (end == start || size + lines[end].len() <= self.max_chars) {
+                size += lines[end].len();
+                end += 1;
+            }
+
+            let content: String = lines[start..end].iter().map(|line| line.text).collect();
+            if !content.trim().is_empty() {
+                chunks.push(Chunk {
+                    index: chunks.len(),
+                    start_line: lines[start].number,
+                    end_line: lines[end - 1].number,
+                    content,
+                });
+            }
+            if end == lines.len() {
+                break;
+            }
+
+            // Step back over the lines that are repeated, but always move forward
+            let mut next = end;
+            let mut overlap = 0;
+            while next > start + 1 && overlap + lines[next - 1].len() <= self.overlap_chars {
+                next -= 1;
+                overlap += lines[next].len();
+            }
+            start = next;
+        }
+    }
+}
+
+fn is_markdown(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| MARKDOWN_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+// Splits the lines at Markdown headings, ignoring `#` lines in fenced code blocks
+fn markdown_sections(lines: &[Line]) -> Vec<Range<usize>> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut in_fence = false;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.text.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        let is_heading = !in_fence
+            && trimmed.starts_with('#')
+            && trimmed
+                .trim_start_matches('#')
+                .starts_with(|c: char| c.is_whitespace());
+        if is_heading && i > start {
+            sections.push(start..i);
+            start = i;
+        }
+    }
+    if start < lines.len() {
+        sections.push(start..lines.len());
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered_lines(count: usize) -> String {
+        (1..=count).map(|i| format!("line {:03}\n", i)).collect()
+    }
+
+    #[test]
+    fn test_code_chunks_overlap_and_cover_all_lines() {
+        // Every line has 9 characters, so a chunk holds 10 lines and repeats 2 of them
+        let chunker = Chunker::new(90, 18);
+        let chunks = chunker.chunk("src/main.rs", &numbered_lines(25));
+
+        let ranges: Vec<(usize, usize)> = chunks
+            .iter()
+            .map(|chunk| (chunk.start_line, chunk.end_line))
+            .collect();
+        assert_eq!(ranges, vec![(1, 10), (9, 18), (17, 25)]);
+        assert!(chunks[1].content.starts_with("line 009\n"));
+        assert_eq!(chunks[2].index, 2);
+    }
+
+    #[test]
+    fn test_markdown_is_split_at_headings() {
+        let text = "# Intro\nshort\n## Usage\nshort\n# Details\n```\n# not a heading\n```\n"
+            .to_string()
+            + &"detail text\n".repeat(10);
+        let chunker = Chunker::new(100, 0);
+
+        let chunks = chunker.chunk("docs/guide.md", &text);
+
+        assert_eq!(chunks[0].content, "# Intro\nshort\n## Usage\nshort\n");
+        assert!(chunks[1]
+            .content
+            .starts_with("# Details\n```\n# not a heading\n"));
+        assert_eq!(chunks[1].start_line, 5);
+    }
+
+    #[test]
+    fn test_long_lines_are_split() {
+        let chunker = Chunker::new(10, 0);
+
+        let chunks = chunker.chunk("data.txt", &"x".repeat(25));
+
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| chunk.start_line == 1));
+        assert!(chunker.chunk("empty.txt", " \n\n").is_empty());
+    }
+}
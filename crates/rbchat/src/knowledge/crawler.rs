@@ -0,0 +1,524 @@
+//! This module crawls a website and adds its pages to a `KnowledgeStore`.
+//!
+//! Starting from one page, the `Crawler` follows the links of HTML pages breadth first:
+//!
+//! - Only pages of the start page's origin (scheme, host and port) are crawled. Redirects to
+//!   another origin are not followed, and a page redirected to a path excluded by robots.txt
+//!   is not stored.
+//! - `max_depth` limits the number of links followed from the start page and `max_pages`
+//!   the number of pages fetched.
+//! - Links have to match one of the `include` patterns, if there are any, and none of the
+//!   `exclude` patterns. The patterns are globs on the whole URL, e.g. `*/guide/*`.
+//! - The rules of the site's `robots.txt` are respected, see `Robots`.
+//! - The pages listed in the site's sitemaps are crawled as if the start page linked them.
+//!
+//! `crawl_knowledge` stores every page as its own `DataSource::Internet` source, chunked like
+//! any other document.
+
+use crate::extract::ExtractorRegistry;
+use crate::knowledge::robots::Robots;
+use crate::knowledge::{index_document, Chunker, DataSource, KnowledgeStore, PathPattern};
+use log::{info, warn};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+use scraper::{Html, Selector};
+use std::collections::{HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+/// The user agent the crawler identifies with, also to `robots.txt`.
+const USER_AGENT: &str = "rusty-buddy";
+
+/// Number of sitemaps read at most, sitemap indexes can list many of them.
+const MAX_SITEMAPS: usize = 10;
+
+/// Limits of a crawl.
+#[derive(Debug, Clone)]
+pub struct CrawlOptions {
+    /// Number of links followed from the start page, 0 to fetch the start page only.
+    pub max_depth: usize,
+    pub max_pages: usize,
+    pub include: Vec<PathPattern>,
+    pub exclude: Vec<PathPattern>,
+    pub respect_robots: bool,
+    pub use_sitemap: bool,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        CrawlOptions {
+            max_depth: 2,
+            max_pages: 50,
+            include: vec![],
+            exclude: vec![],
+            respect_robots: true,
+            use_sitemap: true,
+        }
+    }
+}
+
+/// A page fetched by the `Crawler`, with the text extracted from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrawledPage {
+    pub url: String,
+    /// Number of links followed from the start page to this page.
+    pub depth: usize,
+    pub content: String,
+}
+
+/// Counts the pages added by `crawl_knowledge`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CrawlReport {
+    pub pages: usize,
+    pub chunks: usize,
+    /// Pages that could not be fetched or read.
+    pub failed: usize,
+}
+
+impl fmt::Display for CrawlReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Stored {} chunks of {} pages", self.chunks, self.pages)?;
+        if self.failed > 0 {
+            write!(f, ", failed {}", self.failed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the pages of a site one by one, see `next_page`.
+pub struct Crawler {
+    client: Client,
+    options: CrawlOptions,
+    extractors: ExtractorRegistry,
+    start: Url,
+    robots: Robots,
+    queue: VecDeque<(Url, usize)>,
+    seen: HashSet<String>,
+    fetched: usize,
+}
+
+impl Crawler {
+    /// Prepares a crawl from `start`, reading the `robots.txt` and sitemaps of its site.
+    pub async fn new(start: &str, options: CrawlOptions) -> Result<Self, Box<dyn Error>> {
+        let mut start = Url::parse(start).map_err(|e| format!("Invalid URL '{}': {}", start, e))?;
+        if !matches!(start.scheme(), "http" | "https") {
+            return Err(format!("Only http and https URLs can be crawled, not '{}'", start).into());
+        }
+        start.set_fragment(None);
+
+        // Following a redirect to another site would fetch pages outside of the origin
+        let origin = start.origin();
+        let redirect_policy = Policy::custom(move |attempt| {
+            if attempt.url().origin() != origin {
+                let target = attempt.url().to_string();
+                attempt.error(format!(
+                    "Redirected to {}, outside of the crawled site",
+                    target
+                ))
+            } else if attempt.previous().len() > 10 {
+                attempt.error("Too many redirects")
+            } else {
+                attempt.follow()
+            }
+        });
+
+        let mut crawler = Crawler {
+            client: Client::builder()
+                .user_agent(USER_AGENT)
+                .redirect(redirect_policy)
+                .build()?,
+            options,
+            extractors: ExtractorRegistry::default(),
+            start: start.clone(),
+            robots: Robots::default(),
+            queue: VecDeque::new(),
+            seen: HashSet::new(),
+            fetched: 0,
+        };
+        if crawler.options.respect_robots {
+            crawler.robots = crawler.fetch_robots().await;
+            if !crawler.robots.is_allowed(&robots_path(&start)) {
+                return Err(format!(
+                    "The robots.txt of the site does not allow crawling {}",
+                    start
+                )
+                .into());
+            }
+        }
+        crawler.seen.insert(start.to_string());
+        crawler.queue.push_back((start, 0));
+        if crawler.options.use_sitemap {
+            for url in crawler.sitemap_urls().await {
+                crawler.enqueue(url, 1);
+            }
+        }
+        Ok(crawler)
+    }
+
+    /// Fetches the next page, or returns `None` when the site or the page limit is exhausted.
+    pub async fn next_page(&mut self) -> Option<Result<CrawledPage, Box<dyn Error>>> {
+        if self.fetched >= self.options.max_pages {
+            return None;
+        }
+        let (url, depth) = self.queue.pop_front()?;
+        self.fetched += 1;
+        let location = url.to_string();
+        Some(
+            self.fetch_page(url, depth)
+                .await
+                .map_err(|e| format!("Failed to crawl {}: {}", location, e).into()),
+        )
+    }
+
+    async fn fetch_page(&mut self, url: Url, depth: usize) -> Result<CrawledPage, Box<dyn Error>> {
+        info!("Crawling {}", url);
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        // Redirects lead to the page's actual URL, which has to be allowed itself
+        let url = response.url().clone();
+        if url.origin() != self.start.origin() || !self.robots.is_allowed(&robots_path(&url)) {
+            return Err(format!("Redirected to {}, which may not be crawled", url).into());
+        }
+        self.seen.insert(url.to_string());
+        let mime_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let bytes = response.bytes().await?;
+
+        let is_html = mime_type
+            .as_deref()
+            .is_none_or(|mime_type| mime_type.contains("html"));
+        if is_html && depth < self.options.max_depth {
+            for link in links(&bytes, &url) {
+                self.enqueue(link, depth + 1);
+            }
+        }
+        let content = self
+            .extractors
+            .extract(&bytes, mime_type.as_deref(), url.as_str())?;
+        Ok(CrawledPage {
+            url: url.to_string(),
+            depth,
+            content,
+        })
+    }
+
+    // Queues `url` unless it was seen before or the options or robots.txt exclude it
+    fn enqueue(&mut self, mut url: Url, depth: usize) {
+        url.set_fragment(None);
+        let location = url.to_string();
+        let is_wanted = depth <= self.options.max_depth
+            && url.origin() == self.start.origin()
+            && (self.options.include.is_empty()
+                || self
+                    .options
+                    .include
+                    .iter()
+                    .any(|pattern| pattern.matches(&location)))
+            && !self
+                .options
+                .exclude
+                .iter()
+                .any(|pattern| pattern.matches(&location))
+            && self.robots.is_allowed(&robots_path(&url));
+        if is_wanted && self.seen.insert(location) {
+            self.queue.push_back((url, depth));
+        }
+    }
+
+    // A site without a readable robots.txt may be crawled completely
+    async fn fetch_robots(&self) -> Robots {
+        let Ok(url) = self.start.join("/robots.txt") else {
+            return Robots::default();
+        };
+        match self.fetch_text(url).await {
+            Ok(text) => Robots::parse(&text, USER_AGENT),
+            Err(e) => {
+                info!("No robots.txt: {}", e);
+                Robots::default()
+            }
+        }
+    }
+
+    // Collects the pages of the sitemaps named in robots.txt, or of `/sitemap.xml`
+    async fn sitemap_urls(&self) -> Vec<Url> {
+        let mut sitemaps: VecDeque<Url> = self
+            .robots
+            .sitemaps
+            .iter()
+            .filter_map(|sitemap| Url::parse(sitemap).ok())
+            .collect();
+        if sitemaps.is_empty() {
+            sitemaps.extend(self.start.join("/sitemap.xml").ok());
+        }
+
+        let mut urls = Vec::new();
+        let mut read = 0;
+        while let Some(sitemap) = sitemaps.pop_front() {
+            if read == MAX_SITEMAPS {
+                break;
+            }
+            read += 1;
+            let parsed = match self.fetch_text(sitemap.clone()).await {
+                Ok(xml) => parse_sitemap(&xml),
+                Err(e) => Err(e),
+            };
+            match parsed {
+                Ok(parsed) => {
+                    let locations = parsed
+                        .locations
+                        .iter()
+                        .filter_map(|location| Url::parse(location).ok());
+                    if parsed.is_index {
+                        sitemaps.extend(locations);
+                    } else {
+                        urls.extend(locations);
+                    }
+                }
+                Err(e) => info!("Failed to read the sitemap {}: {}", sitemap, e),
+            }
+        }
+        urls
+    }
+
+    async fn fetch_text(&self, url: Url) -> Result<String, Box<dyn Error>> {
+        Ok(self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    }
+}
+
+/// Crawls the site from `start` and stores every page as a source of `collection`.
+pub async fn crawl_knowledge(
+    store: &dyn KnowledgeStore,
+    chunker: &Chunker,
+    collection: &str,
+    start: &str,
+    options: CrawlOptions,
+) -> Result<CrawlReport, Box<dyn Error>> {
+    let mut crawler = Crawler::new(start, options).await?;
+    let mut report = CrawlReport::default();
+    while let Some(page) = crawler.next_page().await {
+        match page {
+            Ok(page) => {
+                let chunks = index_document(
+                    store,
+                    chunker,
+                    collection,
+                    DataSource::Internet(page.url.clone()),
+                    &page.content,
+                    None,
+                )
+                .await?;
+                info!("Stored {} chunks of {}", chunks, page.url);
+                report.pages += 1;
+                report.chunks += chunks;
+            }
+            Err(e) => {
+                warn!("{}", e);
+                report.failed += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+// The path and query of `url`, which the rules of robots.txt apply to
+fn robots_path(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+// Returns the links of an HTML page, skipping the ones marked `nofollow`
+fn links(html: &[u8], base: &Url) -> Vec<Url> {
+    let document = Html::parse_document(&String::from_utf8_lossy(html));
+    let anchors = Selector::parse("a[href]").unwrap();
+    document
+        .select(&anchors)
+        .filter(|anchor| {
+            !anchor
+                .value()
+                .attr("rel")
+                .is_some_and(|rel| rel.contains("nofollow"))
+        })
+        .filter_map(|anchor| base.join(anchor.value().attr("href")?).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .collect()
+}
+
+/// The entries of a sitemap, which are pages or, for a sitemap index, further sitemaps.
+struct Sitemap {
+    is_index: bool,
+    locations: Vec<String>,
+}
+
+fn parse_sitemap(xml: &str) -> Result<Sitemap, Box<dyn Error>> {
+    let mut reader = Reader::from_str(xml);
+    let mut sitemap = Sitemap {
+        is_index: false,
+        locations: vec![],
+    };
+    let mut in_location = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"sitemapindex" => sitemap.is_index = true,
+                b"loc" => in_location = true,
+                _ => {}
+            },
+            Event::End(element) if element.local_name().as_ref() == b"loc" => in_location = false,
+            Event::Text(text) if in_location => {
+                sitemap.locations.push(text.unescape()?.trim().to_string())
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(sitemap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Answers requests for the given paths with their content type and body, others with 404.
+    // Pages of the content type `redirect` redirect to the URL given as their body.
+    fn serve(listener: TcpListener, pages: Vec<(&'static str, &'static str, String)>) {
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]);
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let response = match pages.iter().find(|(page, _, _)| *page == path) {
+                    Some((_, "redirect", location)) => format!(
+                        "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\
+                         Connection: close\r\n\r\n",
+                        location
+                    ),
+                    page => {
+                        let (status, content_type, body) = match page {
+                            Some((_, content_type, body)) => {
+                                ("200 OK", *content_type, body.as_str())
+                            }
+                            None => ("404 Not Found", "text/plain", ""),
+                        };
+                        format!(
+                            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+                             Connection: close\r\n\r\n{}",
+                            status,
+                            content_type,
+                            body.len(),
+                            body
+                        )
+                    }
+                };
+                stream.write_all(response.as_bytes()).await.ok();
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_crawl_follows_the_rules_of_the_site() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let html = "text/html; charset=utf-8";
+        // Another port is another origin
+        let other_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let other = format!("http://{}", other_listener.local_addr().unwrap());
+        serve(
+            other_listener,
+            vec![("/elsewhere", html, "Elsewhere".to_string())],
+        );
+        serve(
+            listener,
+            vec![
+                (
+                    "/robots.txt",
+                    "text/plain",
+                    "User-agent: *\nDisallow: /private\n".to_string(),
+                ),
+                (
+                    "/sitemap.xml",
+                    "application/xml",
+                    format!(
+                        "<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\
+                         <url><loc>{}/from-sitemap</loc></url></urlset>",
+                        base
+                    ),
+                ),
+                (
+                    "/",
+                    html,
+                    r#"<a href="/guide#intro">Guide</a> <a href="private/notes">Private</a>
+                    <a href="http://other.invalid/">Other</a> <a href="/skip/me">Skip</a>
+                    <a href="/moved">Moved</a> <a href="/old">Old</a>"#
+                        .to_string(),
+                ),
+                ("/moved", "redirect", format!("{}/elsewhere", other)),
+                ("/old", "redirect", "/private/old".to_string()),
+                ("/private/old", html, "Private".to_string()),
+                (
+                    "/guide",
+                    html,
+                    r#"<main><p>Guide text</p><a href="/guide/advanced">More</a></main>"#
+                        .to_string(),
+                ),
+                ("/guide/advanced", html, "Advanced".to_string()),
+                ("/from-sitemap", html, "From the sitemap".to_string()),
+                ("/private/notes", html, "Private".to_string()),
+                ("/skip/me", html, "Skipped".to_string()),
+            ],
+        );
+        let options = CrawlOptions {
+            max_depth: 1,
+            exclude: vec!["*/skip/*".parse().unwrap()],
+            ..CrawlOptions::default()
+        };
+
+        let mut crawler = Crawler::new(&base, options).await.unwrap();
+        let mut pages = Vec::new();
+        let mut failed = Vec::new();
+        while let Some(page) = crawler.next_page().await {
+            match page {
+                Ok(page) => pages.push(page),
+                Err(e) => failed.push(e.to_string()),
+            }
+        }
+
+        let mut urls: Vec<&str> = pages.iter().map(|page| page.url.as_str()).collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                format!("{}/", base),
+                format!("{}/from-sitemap", base),
+                format!("{}/guide", base),
+            ]
+        );
+        let guide = pages
+            .iter()
+            .find(|page| page.url.ends_with("/guide"))
+            .unwrap();
+        assert!(guide.content.starts_with("Guide text"));
+        assert_eq!(guide.depth, 1);
+        // Redirects to another origin and to paths robots.txt disallows are not followed
+        failed.sort();
+        assert_eq!(failed.len(), 2);
+        assert!(failed[0].contains("/moved"));
+        assert!(failed[1].contains("/old"));
+    }
+}
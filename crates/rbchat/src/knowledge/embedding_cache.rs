@@ -0,0 +1,188 @@
+//! This module keeps the embeddings of texts on disk, so a text is embedded once per model.
+//!
+//! Re-running `knowledge init` after a crash, re-adding a file with mostly unchanged chunks or
+//! rebuilding the store with another backend then costs no embedding requests for the texts
+//! embedded before. Every embedding is a file in `.rusty/embedding_cache`, named by the
+//! SHA-256 hash of the model and the text. Deleting the directory empties the cache.
+
+use crate::knowledge::EmbeddingService;
+use async_trait::async_trait;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+
+/// Encodes an embedding as little-endian `f32` values.
+pub(crate) fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Decodes an embedding encoded by `encode_embedding`.
+pub(crate) fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+        .collect()
+}
+
+/// An `EmbeddingService` answering from the cache and embedding the texts it misses with
+/// the service it wraps.
+pub(crate) struct CachedEmbeddingService {
+    inner: Arc<dyn EmbeddingService>,
+    model: String,
+    dir: PathBuf,
+}
+
+impl CachedEmbeddingService {
+    /// Caches the embeddings of `model`, computed by `inner`, in `dir`. `model` names the
+    /// model the way the provider does, an alias may change the model it stands for.
+    pub fn new(inner: Arc<dyn EmbeddingService>, model: &str, dir: PathBuf) -> Self {
+        CachedEmbeddingService {
+            inner,
+            model: model.to_string(),
+            dir,
+        }
+    }
+
+    // Files are spread over subdirectories named by the first two digits of the hash
+    fn path(&self, content: &str) -> PathBuf {
+        let hash = Sha256::new()
+            .chain_update(self.model.as_bytes())
+            .chain_update([0])
+            .chain_update(content.as_bytes())
+            .finalize();
+        let name = format!("{:x}", hash);
+        self.dir.join(&name[..2]).join(name)
+    }
+
+    fn read(&self, content: &str) -> Option<Vec<f32>> {
+        let bytes = fs::read(self.path(content)).ok()?;
+        // A file cut short is taken as missing and written again
+        if bytes.is_empty() || bytes.len() % 4 != 0 {
+            return None;
+        }
+        Some(decode_embedding(&bytes))
+    }
+
+    // A failed write only costs another request later, so it is not an error
+    fn write(&self, content: &str, embedding: &[f32]) {
+        let path = self.path(content);
+        if let Err(e) = write_atomically(&path, &encode_embedding(embedding)) {
+            warn!("Failed to cache embedding in {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Writes a temporary file and moves it into place, so readers never see a partial file
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    let dir = path.parent().ok_or("The cache file has no directory")?;
+    fs::create_dir_all(dir)?;
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(bytes)?;
+    file.persist(path)?;
+    Ok(())
+}
+
+#[async_trait]
+impl EmbeddingService for CachedEmbeddingService {
+    async fn get_embedding(&self, content: Cow<'_, str>) -> Result<Box<Vec<f32>>, Box<dyn Error>> {
+        if let Some(embedding) = self.read(&content) {
+            return Ok(Box::new(embedding));
+        }
+        let embedding = self
+            .inner
+            .get_embedding(Cow::Borrowed(content.as_ref()))
+            .await?;
+        self.write(&content, &embedding);
+        Ok(embedding)
+    }
+
+    async fn get_embeddings(&self, contents: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        let mut embeddings: Vec<Option<Vec<f32>>> =
+            contents.iter().map(|content| self.read(content)).collect();
+        let missing: Vec<String> = contents
+            .iter()
+            .zip(&embeddings)
+            .filter(|(_, embedding)| embedding.is_none())
+            .map(|(content, _)| content.clone())
+            .collect();
+        info!(
+            "{} of {} embeddings found in the cache",
+            contents.len() - missing.len(),
+            contents.len()
+        );
+
+        if !missing.is_empty() {
+            let mut embedded = self.inner.get_embeddings(&missing).await?.into_iter();
+            for (content, embedding) in contents.iter().zip(embeddings.iter_mut()) {
+                if embedding.is_none() {
+                    let new = embedded
+                        .next()
+                        .ok_or("The embedding service returned fewer embeddings than texts")?;
+                    self.write(content, &new);
+                    *embedding = Some(new);
+                }
+            }
+        }
+        Ok(embeddings.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    // Embeds a text as its length and counts the texts embedded
+    #[derive(Default)]
+    struct CountingService {
+        embedded: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingService for CountingService {
+        async fn get_embedding(
+            &self,
+            content: Cow<'_, str>,
+        ) -> Result<Box<Vec<f32>>, Box<dyn Error>> {
+            self.embedded.fetch_add(1, Ordering::SeqCst);
+            Ok(Box::new(vec![content.len() as f32, 1.0]))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_texts_are_not_embedded_again() {
+        let dir = TempDir::new().unwrap();
+        let counting = Arc::new(CountingService::default());
+        let cache = CachedEmbeddingService::new(counting.clone(), "small", dir.path().into());
+        let texts = |texts: &[&str]| texts.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        let embeddings = cache.get_embeddings(&texts(&["a", "bb"])).await.unwrap();
+        assert_eq!(embeddings, vec![vec![1.0, 1.0], vec![2.0, 1.0]]);
+        assert_eq!(counting.embedded.load(Ordering::SeqCst), 2);
+
+        let embeddings = cache
+            .get_embeddings(&texts(&["ccc", "bb", "a"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            embeddings,
+            vec![vec![3.0, 1.0], vec![2.0, 1.0], vec![1.0, 1.0]]
+        );
+        assert_eq!(counting.embedded.load(Ordering::SeqCst), 3);
+
+        // Another model does not share the embeddings
+        let other = CachedEmbeddingService::new(counting.clone(), "large", dir.path().into());
+        other.get_embedding(Cow::Borrowed("a")).await.unwrap();
+        assert_eq!(counting.embedded.load(Ordering::SeqCst), 4);
+    }
+}
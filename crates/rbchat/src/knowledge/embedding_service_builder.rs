@@ -1,4 +1,6 @@
-use crate::config::{AIBackend, CONFIG};
+use crate::config::{get_embedding_cache_dir, AIBackend, Model, CONFIG};
+use crate::knowledge::embedding_cache::CachedEmbeddingService;
+use crate::knowledge::throttle::ThrottledEmbeddingService;
 use crate::knowledge::{EmbeddingService, EmbeddingServiceHandle};
 use crate::provider::ollama::ollama_interface::OllamaInterface;
 use crate::provider::openai::openai_interface::OpenAIInterface;
@@ -40,15 +42,41 @@ impl EmbeddingServiceBuilder {
         let timeout_secs = config.ai.chat_timeout_secs;
         // Check which provider to use based on the model
         let service: Arc<dyn EmbeddingService> = match &model.backend {
-            AIBackend::OpenAI => {
-                Arc::new(OpenAIInterface::new(model.api_name.clone(), timeout_secs))
-            } // Additional backends can be added here
+            AIBackend::OpenAI => Arc::new(OpenAIInterface::from_model(model, timeout_secs)?), // Additional backends can be added here
             AIBackend::Ollama => Arc::new(OllamaInterface::new(
                 model.api_name.clone(),
                 model.url.clone(),
             )),
+            AIBackend::Anthropic => {
+                return Err(format!(
+                    "Model '{}' uses the Anthropic backend, which does not provide embeddings",
+                    model_name
+                )
+                .into())
+            }
         };
+        // Texts found in the cache are neither sent nor counted against the rate limit
+        let mut service: Arc<dyn EmbeddingService> =
+            Arc::new(ThrottledEmbeddingService::new(service, &config.embedding));
+        if config.embedding.cache {
+            // Another server may serve another model under the same name
+            let cache_key = match &model.url {
+                Some(url) => format!("{} {}", model_id(model), url),
+                None => model_id(model),
+            };
+            service = Arc::new(CachedEmbeddingService::new(
+                service,
+                &cache_key,
+                get_embedding_cache_dir()?,
+            ));
+        }
 
-        Ok(EmbeddingServiceHandle::new(service))
+        Ok(EmbeddingServiceHandle::new(service, model_id(model)))
     }
 }
+
+// Names the vector space of a model. The configured name of the model is only an alias,
+// its backend and `api_name` may change while it stays the same.
+fn model_id(model: &Model) -> String {
+    format!("{:?}/{}", model.backend, model.api_name)
+}
@@ -0,0 +1,249 @@
+//! This module moves the contents of a knowledge store between machines, e.g. to share the
+//! embeddings of a large repository with a team or a CI job instead of paying for them again.
+//!
+//! The export is a JSON Lines file. Its first line is a header naming the format, the
+//! embedding model and the dimension of the embeddings:
+//!
+//! ```text
+//! {"format":"rusty-buddy-knowledge","version":1,"model":"OpenAI/text-embedding-3-small","dimension":1536,"records":2}
+//! ```
+//!
+//! Every further line is one record with its source, collection, content, metadata and
+//! embedding, as it is stored. The model is named by its backend and the provider's name, so
+//! machines configuring it under different names can share an export. An import is refused
+//! if the file was embedded with another model than the configured one, since its embeddings
+//! could not be compared with queries.
+
+use crate::knowledge::{DataSource, EmbeddingData, EmbeddingInfo, KnowledgeStore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+const FORMAT: &str = "rusty-buddy-knowledge";
+const VERSION: u32 = 1;
+
+/// The first line of an export.
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportHeader {
+    format: String,
+    version: u32,
+    #[serde(flatten)]
+    embedding: EmbeddingInfo,
+    /// Number of records following the header.
+    records: usize,
+}
+
+/// Counts what `import_knowledge` added to the store.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    pub sources: usize,
+    pub records: usize,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} records of {} sources",
+            self.records, self.sources
+        )
+    }
+}
+
+/// Writes every record of `store` to `writer` and returns their number.
+pub async fn export_knowledge(
+    store: &dyn KnowledgeStore,
+    writer: &mut dyn Write,
+) -> Result<usize, Box<dyn Error>> {
+    let records = store.list_records().await?;
+    let Some(first) = records.first() else {
+        return Err("The knowledge store is empty, there is nothing to export".into());
+    };
+    // Stores written before the model was recorded hold embeddings of the configured model
+    let embedding = store
+        .recorded_embedding()
+        .await?
+        .unwrap_or_else(|| EmbeddingInfo {
+            model: store.embedding_model().to_string(),
+            dimension: first.embedding.len(),
+        });
+
+    let header = ExportHeader {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        embedding,
+        records: records.len(),
+    };
+    serde_json::to_writer(&mut *writer, &header)?;
+    writeln!(writer)?;
+    for record in &records {
+        serde_json::to_writer(&mut *writer, record)?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(records.len())
+}
+
+/// Adds the records exported to `reader` to `store`. The records of a source replace those
+/// stored for it before.
+///
+/// Fails without changing the store if the export was embedded with another model than the
+/// configured one, or with another model or dimension than the stored records.
+pub async fn import_knowledge(
+    store: &dyn KnowledgeStore,
+    reader: impl BufRead,
+) -> Result<ImportReport, Box<dyn Error>> {
+    let mut lines = reader.lines();
+    let header = lines.next().ok_or("The knowledge export is empty")??;
+    let header: ExportHeader = serde_json::from_str(&header)
+        .map_err(|e| format!("The file is not a knowledge export: {}", e))?;
+    if header.format != FORMAT || header.version != VERSION {
+        return Err(format!(
+            "Unsupported knowledge export format '{}' version {}",
+            header.format, header.version
+        )
+        .into());
+    }
+
+    let EmbeddingInfo { model, dimension } = &header.embedding;
+    if model != store.embedding_model() {
+        return Err(format!(
+            "The knowledge export holds embeddings of model '{}', but the configured \
+             embedding model is '{}'. Configure an `embedding_model` with the backend and \
+             `api_name` of '{}' to import it.",
+            model,
+            store.embedding_model(),
+            model
+        )
+        .into());
+    }
+    if let Some(recorded) = store.recorded_embedding().await? {
+        recorded.check(model, *dimension)?;
+    }
+
+    // Read every record before storing any, so a broken file leaves the store as it was
+    let mut records = Vec::with_capacity(header.records);
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // The header is line 1
+        let record: EmbeddingData = serde_json::from_str(&line)
+            .map_err(|e| format!("Invalid record on line {}: {}", index + 2, e))?;
+        if record.embedding.len() != *dimension {
+            return Err(format!(
+                "The record on line {} has {} dimensions instead of {}",
+                index + 2,
+                record.embedding.len(),
+                dimension
+            )
+            .into());
+        }
+        records.push(record);
+    }
+
+    let mut sources: HashSet<(String, DataSource)> = HashSet::new();
+    for record in &records {
+        let source = (record.collection.clone(), record.data_source.clone());
+        if !sources.contains(&source) {
+            store
+                .remove_knowledge(&record.collection, &record.data_source)
+                .await?;
+            sources.insert(source);
+        }
+    }
+    let count = records.len();
+    for record in records {
+        store.store_knowledge(record).await?;
+    }
+    Ok(ImportReport {
+        sources: sources.len(),
+        records: count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::memory_store::MemoryStore;
+    use crate::knowledge::KnowledgeMetadata;
+
+    fn record(file: &str, chunk_index: usize, embedding: Vec<f32>) -> EmbeddingData {
+        EmbeddingData {
+            data_source: DataSource::LocalFiles(file.to_string()),
+            collection: "code".to_string(),
+            embedding,
+            content: Some(format!("chunk {} of {}", chunk_index, file)),
+            metadata: Some(KnowledgeMetadata {
+                file: file.to_string(),
+                chunk_index,
+                start_line: 1,
+                end_line: 3,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let source = MemoryStore::with_model("OpenAI/text-embedding-3-small");
+        source
+            .store_knowledge(record("src/a.rs", 0, vec![0.1, 0.2]))
+            .await
+            .unwrap();
+        source
+            .store_knowledge(record("src/a.rs", 1, vec![0.3, 0.4]))
+            .await
+            .unwrap();
+        source
+            .store_knowledge(record("src/b.rs", 0, vec![0.5, 0.6]))
+            .await
+            .unwrap();
+        let mut export = Vec::new();
+        assert_eq!(export_knowledge(&source, &mut export).await.unwrap(), 3);
+
+        let target = MemoryStore::with_model("OpenAI/text-embedding-3-small");
+        target
+            .store_knowledge(record("src/a.rs", 5, vec![0.9, 0.9]))
+            .await
+            .unwrap();
+        let report = import_knowledge(&target, export.as_slice()).await.unwrap();
+
+        assert_eq!(
+            report,
+            ImportReport {
+                sources: 2,
+                records: 3
+            }
+        );
+        let records = target.list_records().await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].embedding, vec![0.5, 0.6]);
+        assert_eq!(records[2].content.as_deref(), Some("chunk 0 of src/b.rs"));
+        assert_eq!(target.embedded(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_import_refuses_other_embedding_model() {
+        let source = MemoryStore::with_model("OpenAI/text-embedding-3-small");
+        source
+            .store_knowledge(record("src/a.rs", 0, vec![0.1, 0.2]))
+            .await
+            .unwrap();
+        let mut export = Vec::new();
+        export_knowledge(&source, &mut export).await.unwrap();
+
+        let target = MemoryStore::with_model("OpenAI/text-embedding-3-large");
+        let error = import_knowledge(&target, export.as_slice())
+            .await
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("model 'OpenAI/text-embedding-3-small'"));
+        assert!(target.list_records().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+//! This module restricts knowledge queries to the records a user is interested in.
+//!
+//! A `KnowledgeFilter` combines several criteria, all of which have to match:
+//!
+//! - the collections and the kinds of data source,
+//! - a glob on the path or URL, e.g. `src/**` when asking about the implementation,
+//! - the language, derived from the file extension,
+//! - the time the record was added.
+//!
+//! The collections are part of the database query. The other criteria are checked on the
+//! results, so the store fetches more candidates when they are set.
+
+use crate::knowledge::{KnowledgeResult, SourceKind};
+use chrono::{DateTime, NaiveDate, Utc};
+use globset::{Glob, GlobMatcher};
+use std::str::FromStr;
+
+/// Number of candidates a store fetches per requested result when results are filtered.
+const FILTER_CANDIDATES: usize = 5;
+
+/// Languages of common file extensions.
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["rs"]),
+    ("python", &["py", "pyi", "ipynb"]),
+    ("javascript", &["js", "mjs", "cjs", "jsx"]),
+    ("typescript", &["ts", "tsx"]),
+    ("java", &["java"]),
+    ("kotlin", &["kt", "kts"]),
+    ("swift", &["swift"]),
+    ("go", &["go"]),
+    ("c", &["c", "h"]),
+    ("cpp", &["cpp", "cc", "cxx", "hpp", "hh"]),
+    ("csharp", &["cs"]),
+    ("ruby", &["rb"]),
+    ("php", &["php"]),
+    ("shell", &["sh", "bash", "zsh"]),
+    ("markdown", &["md", "markdown", "mdx"]),
+    ("html", &["html", "htm"]),
+    ("css", &["css", "scss"]),
+    ("json", &["json"]),
+    ("yaml", &["yml", "yaml"]),
+    ("toml", &["toml"]),
+    ("xml", &["xml"]),
+    ("sql", &["sql"]),
+];
+
+/// A glob on the path or URL of a record, such as `src/**/*.rs`.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    matcher: GlobMatcher,
+}
+
+impl PathPattern {
+    pub fn matches(&self, path: &str) -> bool {
+        self.matcher.is_match(path)
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let glob = Glob::new(s).map_err(|e| format!("Invalid path pattern '{}': {}", s, e))?;
+        Ok(PathPattern {
+            matcher: glob.compile_matcher(),
+        })
+    }
+}
+
+/// Criteria the results of a knowledge query have to match. The default filter matches every
+/// record.
+#[derive(Debug, Clone, Default)]
+pub struct KnowledgeFilter {
+    /// The collections to search, all collections if empty.
+    pub collections: Vec<String>,
+    /// The kinds of data source to search, all kinds if empty.
+    pub kinds: Vec<SourceKind>,
+    pub path: Option<PathPattern>,
+    /// A language like `rust`, or a file extension like `rs`.
+    pub language: Option<String>,
+    /// Only records added at or after this time. Records stored before the time was recorded
+    /// do not match.
+    pub added_after: Option<DateTime<Utc>>,
+}
+
+impl KnowledgeFilter {
+    /// A filter for the given collections only.
+    pub fn for_collections(collections: Vec<String>) -> Self {
+        KnowledgeFilter {
+            collections,
+            ..KnowledgeFilter::default()
+        }
+    }
+
+    /// True if results are checked after the query, see `matches`.
+    pub fn filters_results(&self) -> bool {
+        !self.kinds.is_empty()
+            || self.path.is_some()
+            || self.language.is_some()
+            || self.added_after.is_some()
+    }
+
+    /// The number of candidates to fetch for `limit` results.
+    pub fn candidates(&self, limit: usize) -> usize {
+        if self.filters_results() {
+            limit * FILTER_CANDIDATES
+        } else {
+            limit
+        }
+    }
+
+    pub fn matches(&self, result: &KnowledgeResult) -> bool {
+        let path = match &result.metadata {
+            Some(metadata) => metadata.file.as_str(),
+            None => result.data_source.name(),
+        };
+        (self.collections.is_empty() || self.collections.contains(&result.collection))
+            && (self.kinds.is_empty() || self.kinds.contains(&result.data_source.kind()))
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|pattern| pattern.matches(path))
+            && self
+                .language
+                .as_ref()
+                .is_none_or(|language| is_language(path, language))
+            && self.added_after.is_none_or(|after| {
+                result
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.added)
+                    .is_some_and(|added| added >= after)
+            })
+    }
+
+    /// Keeps the first `limit` results that match.
+    pub fn apply(&self, mut results: Vec<KnowledgeResult>, limit: usize) -> Vec<KnowledgeResult> {
+        results.retain(|result| self.matches(result));
+        results.truncate(limit);
+        results
+    }
+}
+
+/// Returns the language of the file at `path`, derived from its extension.
+fn language_of(path: &str) -> Option<&'static str> {
+    let (_, extension) = path.rsplit_once('.')?;
+    let extension = extension.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map(|(language, _)| *language)
+}
+
+fn is_language(path: &str, language: &str) -> bool {
+    let language = language.to_lowercase();
+    language_of(path) == Some(language.as_str())
+        || path
+            .rsplit_once('.')
+            .is_some_and(|(_, extension)| extension.eq_ignore_ascii_case(&language))
+}
+
+/// Parses a date like `2025-03-01`, taken as midnight UTC, or a RFC 3339 timestamp.
+pub fn parse_date(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| {
+            format!(
+                "Invalid date '{}', expected YYYY-MM-DD or an RFC 3339 timestamp",
+                s
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::{DataSource, KnowledgeMetadata, DEFAULT_COLLECTION};
+
+    fn result(data_source: DataSource, added: Option<&str>) -> KnowledgeResult {
+        let file = data_source.name().to_string();
+        KnowledgeResult {
+            distance: 0.5,
+            score: 0.5,
+            data_source,
+            collection: DEFAULT_COLLECTION.to_string(),
+            content: None,
+            metadata: Some(KnowledgeMetadata {
+                file,
+                added: added.map(|added| parse_date(added).unwrap()),
+                ..KnowledgeMetadata::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_path_language_and_kind() {
+        let code = result(DataSource::Context("src/knowledge/mod.rs".into()), None);
+        let docs = result(DataSource::LocalFiles("docs/guide.md".into()), None);
+        let page = result(DataSource::Internet("https://example.com".into()), None);
+
+        let filter = KnowledgeFilter {
+            path: Some("src/**".parse().unwrap()),
+            ..KnowledgeFilter::default()
+        };
+        assert!(filter.matches(&code) && !filter.matches(&docs));
+
+        let filter = KnowledgeFilter {
+            language: Some("Markdown".to_string()),
+            ..KnowledgeFilter::default()
+        };
+        assert!(!filter.matches(&code) && filter.matches(&docs));
+
+        let filter = KnowledgeFilter {
+            kinds: vec![SourceKind::Context, SourceKind::Internet],
+            ..KnowledgeFilter::default()
+        };
+        assert!(filter.matches(&code) && !filter.matches(&docs) && filter.matches(&page));
+        assert!(filter.filters_results());
+        assert!(!KnowledgeFilter::for_collections(vec!["code".into()]).filters_results());
+    }
+
+    #[test]
+    fn test_filter_by_date_added() {
+        let filter = KnowledgeFilter {
+            added_after: Some(parse_date("2025-03-01").unwrap()),
+            ..KnowledgeFilter::default()
+        };
+
+        let results = vec![
+            result(DataSource::Context("new.rs".into()), Some("2025-03-02")),
+            result(DataSource::Context("old.rs".into()), Some("2025-02-28")),
+            result(DataSource::Context("unknown.rs".into()), None),
+        ];
+        let kept = filter.apply(results, 10);
+
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].data_source.name(), "new.rs");
+        assert!(parse_date("2025-03-01T12:00:00+02:00").is_ok());
+        assert!(parse_date("March").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! This module adds documents to a `KnowledgeStore`.
+//!
+//! `index_document` splits a document with the `Chunker`, embeds every chunk and stores it as
+//! its own record of the document's `DataSource`. The records stored for the source before
+//! are replaced at once, so a document that shrank leaves no stale chunks behind and a failed
+//! update keeps the old ones.
+//!
+//! Every record remembers the SHA-256 hash of its document and the modification time of its
+//! file, so `sync` can tell which documents changed since they were indexed.
+//!
+//! `reindex_knowledge` embeds every stored record again with the configured model, e.g. after
+//! `embedding_model` was changed.
+
+use crate::context::read_project_file;
+use crate::extract::extract_file;
+use crate::knowledge::{file_modified, Chunker, DataSource, EmbeddingData, KnowledgeStore};
+use chrono::{DateTime, Utc};
+use log::warn;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// Counts the records re-embedded by `reindex_knowledge`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReindexReport {
+    pub records: usize,
+    /// Sources without stored content whose file no longer exists. They were dropped.
+    pub skipped: Vec<DataSource>,
+}
+
+impl fmt::Display for ReindexReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Re-embedded {} records", self.records)?;
+        if !self.skipped.is_empty() {
+            write!(
+                f,
+                ", dropped {} sources without content",
+                self.skipped.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Stores the chunks of `content` as the records of `data_source` in `collection` and returns
+/// their number. `modified` is the modification time of the file the content was read from,
+/// if any.
+pub async fn index_document(
+    store: &dyn KnowledgeStore,
+    chunker: &Chunker,
+    collection: &str,
+    data_source: DataSource,
+    content: &str,
+    modified: Option<DateTime<Utc>>,
+) -> Result<usize, Box<dyn Error>> {
+    // Embed every chunk before the old records are removed, so a failure keeps them
+    let records =
+        embed_document(store, chunker, collection, &data_source, content, modified).await?;
+    if let (Some(recorded), Some(record)) = (store.recorded_embedding().await?, records.first()) {
+        recorded.check(store.embedding_model(), record.embedding.len())?;
+    }
+
+    // The old records are replaced at once, so a failure never leaves part of the chunks
+    let count = records.len();
+    store
+        .replace_source(collection, &data_source, records)
+        .await?;
+    Ok(count)
+}
+
+/// Embeds every stored record again with the configured embedding model and replaces the
+/// contents of the store with the new records.
+///
+/// Records store the text of their chunk and are embedded from it. Records of files indexed
+/// without their text are chunked again from the file; if the file is gone, the source is
+/// dropped and listed in the report.
+pub async fn reindex_knowledge(
+    store: &dyn KnowledgeStore,
+    chunker: &Chunker,
+) -> Result<ReindexReport, Box<dyn Error>> {
+    let mut report = ReindexReport::default();
+    let mut records = Vec::new();
+    let mut without_content = HashSet::new();
+    let mut with_content = Vec::new();
+    for record in store.list_records().await? {
+        match &record.content {
+            Some(_) => with_content.push(record),
+            None => {
+                without_content.insert((record.collection, record.data_source));
+            }
+        }
+    }
+    let texts: Vec<String> = with_content
+        .iter()
+        .filter_map(|record| record.content.clone())
+        .collect();
+    let embeddings = store.get_embeddings(&texts).await?;
+    for (record, embedding) in with_content.into_iter().zip(embeddings) {
+        records.push(EmbeddingData {
+            embedding,
+            ..record
+        });
+    }
+    for (collection, data_source) in without_content {
+        // Project files are read as they are, added documents by their extractor
+        let path = match &data_source {
+            DataSource::Context(name) => Some((name.as_str(), read_project_file(Path::new(name)))),
+            DataSource::LocalFiles(name) => Some((name.as_str(), extract_file(Path::new(name)))),
+            DataSource::Internet(_) => None,
+        };
+        match path {
+            Some((path, Ok(content))) => {
+                let modified = file_modified(Path::new(path));
+                records.extend(
+                    embed_document(
+                        store,
+                        chunker,
+                        &collection,
+                        &data_source,
+                        &content,
+                        modified,
+                    )
+                    .await?,
+                );
+            }
+            _ => {
+                warn!("Dropping {}, its content is not stored", data_source);
+                report.skipped.push(data_source);
+            }
+        }
+    }
+
+    // All records are embedded before they replace the old ones at once, so a failure at any
+    // point keeps the old records
+    report.records = records.len();
+    store.replace_knowledge(records).await?;
+    Ok(report)
+}
+
+// Splits `content` into chunks and embeds them as records of `data_source` in `collection`
+async fn embed_document(
+    store: &dyn KnowledgeStore,
+    chunker: &Chunker,
+    collection: &str,
+    data_source: &DataSource,
+    content: &str,
+    modified: Option<DateTime<Utc>>,
+) -> Result<Vec<EmbeddingData>, Box<dyn Error>> {
+    let name = data_source.name().to_string();
+    let hash = content_hash(content);
+    let chunks = chunker.chunk(&name, content);
+    // The chunks of a document are embedded together, in as few requests as possible
+    let texts: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+    let embeddings = store.get_embeddings(&texts).await?;
+    let mut records = Vec::new();
+    for (chunk, embedding) in chunks.into_iter().zip(embeddings) {
+        let mut metadata = chunk.metadata(&name);
+        metadata.content_hash = Some(hash.clone());
+        metadata.modified = modified;
+        metadata.added = Some(Utc::now());
+        records.push(EmbeddingData {
+            data_source: data_source.clone(),
+            collection: collection.to_string(),
+            embedding,
+            metadata: Some(metadata),
+            content: Some(chunk.content),
+        });
+    }
+    Ok(records)
+}
+
+/// Returns the hex encoded SHA-256 hash of `content`.
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
@@ -1,32 +1,75 @@
+use crate::knowledge::KnowledgeFilter;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::error::Error;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::sync::Arc;
 
+/// The collection records are stored in unless another one is named.
+pub const DEFAULT_COLLECTION: &str = "default";
+
+fn default_collection() -> String {
+    DEFAULT_COLLECTION.to_string()
+}
+
+// Records of the default collection keep the ids they had before collections existed
+fn record_id(
+    collection: &str,
+    data_source: &DataSource,
+    metadata: &Option<KnowledgeMetadata>,
+) -> String {
+    let id = match metadata {
+        Some(metadata) => format!("{}#{}", data_source, metadata.chunk_index),
+        None => data_source.to_string(),
+    };
+    if collection == DEFAULT_COLLECTION {
+        id
+    } else {
+        format!("{}/{}", collection, id)
+    }
+}
+
 /// Wrapper type for `Arc<dyn EmbeddingService>`            
 pub struct EmbeddingServiceHandle {
     pub inner: Arc<dyn EmbeddingService>,
+    /// The backend and the provider's name of the model, e.g. `OpenAI/text-embedding-3-small`.
+    /// Stores record it with their embeddings, the configured name is only an alias.
+    pub model: String,
 }
 
 impl EmbeddingServiceHandle {
-    pub fn new(service: Arc<dyn EmbeddingService>) -> Self {
-        EmbeddingServiceHandle { inner: service }
+    pub fn new(service: Arc<dyn EmbeddingService>, model: String) -> Self {
+        EmbeddingServiceHandle {
+            inner: service,
+            model,
+        }
     }
 }
 
 #[async_trait]
 pub trait EmbeddingService: Send + Sync {
     async fn get_embedding(&self, content: Cow<'_, str>) -> Result<Box<Vec<f32>>, Box<dyn Error>>;
-    fn embedding_len(&self) -> usize;
+
+    /// Embeds several texts and returns their embeddings in the same order. Backends that
+    /// accept several inputs per request embed them at once, the others one after another.
+    async fn get_embeddings(&self, contents: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        let mut embeddings = Vec::with_capacity(contents.len());
+        for content in contents {
+            embeddings.push(*self.get_embedding(Cow::Borrowed(content)).await?);
+        }
+        Ok(embeddings)
+    }
 }
 
 impl Clone for EmbeddingServiceHandle {
     fn clone(&self) -> Self {
         EmbeddingServiceHandle {
             inner: Arc::clone(&self.inner), // Clone the inner Arc
+            model: self.model.clone(),
         }
     }
 }
@@ -51,6 +94,9 @@ pub trait KnowledgeStore: Send + Sync {
     /// # Arguments
     ///
     /// * `user_input` - The user input as a string, which will be used to generate the embedding.
+    /// * `limit` - The maximum number of results.
+    /// * `mode` - Whether to search by embedding, by keywords or both.
+    /// * `filter` - Restricts the results, e.g. to some collections or paths.
     ///
     /// # Returns
     ///
@@ -61,27 +107,237 @@ pub trait KnowledgeStore: Send + Sync {
         &self,
         user_input: Cow<'_, str>,
         limit: usize,
+        mode: SearchMode,
+        filter: &KnowledgeFilter,
     ) -> Result<Vec<KnowledgeResult>, Box<dyn Error>>;
     async fn store_knowledge(&self, knowledge: EmbeddingData) -> Result<(), Box<dyn Error>>;
+    /// Removes all records of `data_source` in `collection`, e.g. the chunks of a file before
+    /// it is added again.
+    async fn remove_knowledge(
+        &self,
+        collection: &str,
+        data_source: &DataSource,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Replaces the records of `data_source` in `collection` with `records` at once, e.g. the
+    /// chunks of a file added again. A failure leaves the records of the source as they were.
+    async fn replace_source(
+        &self,
+        collection: &str,
+        data_source: &DataSource,
+        records: Vec<EmbeddingData>,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Lists the stored data sources with the state of their documents when they were indexed.
+    async fn list_sources(&self) -> Result<Vec<IndexedSource>, Box<dyn Error>>;
+    /// Describes the size of the store and the embeddings it holds.
+    async fn stats(&self) -> Result<KnowledgeStats, Box<dyn Error>>;
+    /// Returns every stored record, including its embedding.
+    async fn list_records(&self) -> Result<Vec<EmbeddingData>, Box<dyn Error>>;
+    /// Removes every record from the store, together with the recorded embedding model, so
+    /// the next record may use another model.
+    async fn purge_knowledge(&self) -> Result<(), Box<dyn Error>>;
+    /// Replaces every record of the store with `records` at once and records their embedding
+    /// model anew. A failure leaves the store as it was.
+    async fn replace_knowledge(&self, records: Vec<EmbeddingData>) -> Result<(), Box<dyn Error>>;
+    /// The embedding model configured for new embeddings, as backend and provider's name.
+    fn embedding_model(&self) -> &str;
+    /// The model and dimension of the stored embeddings, recorded with the first record.
+    async fn recorded_embedding(&self) -> Result<Option<EmbeddingInfo>, Box<dyn Error>>;
     async fn get_embedding(&self, content: Cow<'_, str>) -> Result<Box<Vec<f32>>, Box<dyn Error>>;
+    /// Embeds several texts with the configured model, in as few requests as the embedding
+    /// service allows.
+    async fn get_embeddings(&self, contents: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        let mut embeddings = Vec::with_capacity(contents.len());
+        for content in contents {
+            embeddings.push(*self.get_embedding(Cow::Borrowed(content)).await?);
+        }
+        Ok(embeddings)
+    }
 }
 
 /// Embedding data stored in the database with file name and calculated embedding.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingData {
     pub data_source: DataSource,
+    /// The collection the record belongs to, e.g. `code` or `api-docs`.
+    #[serde(default = "default_collection")]
+    pub collection: String,
     pub embedding: Vec<f32>, // Adjust size based on the model
     pub content: Option<String>,
-    pub metadata: Option<String>,
+    pub metadata: Option<KnowledgeMetadata>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl EmbeddingData {
+    /// The id of the record. Every chunk of a data source is stored as its own record.
+    pub fn record_id(&self) -> String {
+        record_id(&self.collection, &self.data_source, &self.metadata)
+    }
+}
+
+/// Locates the content of a record within the file or page it was taken from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeMetadata {
+    /// The file path or URL the chunk was taken from.
+    pub file: String,
+    /// Position of the chunk within the file, starting at 0.
+    pub chunk_index: usize,
+    /// First line of the chunk, 1-based.
+    pub start_line: usize,
+    /// Last line of the chunk, 1-based and inclusive.
+    pub end_line: usize,
+    /// SHA-256 of the whole document, used to detect changes.
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Modification time of the file when it was indexed.
+    #[serde(default)]
+    pub modified: Option<DateTime<Utc>>,
+    /// Time the chunk was added to the store.
+    #[serde(default)]
+    pub added: Option<DateTime<Utc>>,
+}
+
+/// A data source in the knowledge store and the state of its document when it was indexed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedSource {
+    pub data_source: DataSource,
+    pub collection: String,
+    pub content_hash: Option<String>,
+    pub modified: Option<DateTime<Utc>>,
+    /// Number of records stored for the source.
+    pub chunks: usize,
+}
+
+/// The embedding model the records of a store were created with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingInfo {
+    pub model: String,
+    /// Length of the embedding vectors, as returned by the model.
+    pub dimension: usize,
+}
+
+impl EmbeddingInfo {
+    /// Fails if embeddings of `model` with `dimension` cannot be compared with the stored ones.
+    pub fn check(&self, model: &str, dimension: usize) -> Result<(), String> {
+        if self.model != model || self.dimension != dimension {
+            return Err(format!(
+                "The knowledge store holds embeddings of model '{}' with {} dimensions, but \
+                 the configured embedding model '{}' returns {} dimensions. Run \
+                 `rusty-buddy knowledge reindex` to re-embed the knowledge with '{}'.",
+                self.model, self.dimension, model, dimension, model
+            ));
+        }
+        Ok(())
+    }
+
+    /// Describes the embeddings of `records`, made with `model`, or `None` if there are no
+    /// records. Fails if their dimensions differ.
+    pub fn of_records(model: &str, records: &[EmbeddingData]) -> Result<Option<Self>, String> {
+        let Some(first) = records.first() else {
+            return Ok(None);
+        };
+        let info = EmbeddingInfo {
+            model: model.to_string(),
+            dimension: first.embedding.len(),
+        };
+        for record in records {
+            info.check(model, record.embedding.len())?;
+        }
+        Ok(Some(info))
+    }
+}
+
+/// Size and embedding settings of a knowledge store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnowledgeStats {
+    pub records: usize,
+    pub sources: usize,
+    /// The names of the collections holding records, sorted.
+    pub collections: Vec<String>,
+    /// The model of the stored embeddings, if anything was stored yet.
+    pub embedding: Option<EmbeddingInfo>,
+    /// The model configured for new embeddings.
+    pub configured_model: String,
+    /// Bytes the store takes up on disk.
+    pub size_on_disk: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum DataSource {
     Context(String),
     Internet(String),
     LocalFiles(String),
 }
 
+/// The kinds of `DataSource`, e.g. to filter sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceKind {
+    Context,
+    Internet,
+    LocalFiles,
+}
+
+impl FromStr for SourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "context" => Ok(SourceKind::Context),
+            "internet" => Ok(SourceKind::Internet),
+            "localfiles" | "local-files" => Ok(SourceKind::LocalFiles),
+            _ => Err(format!(
+                "Unknown source kind '{}', expected context, internet or local-files",
+                s
+            )),
+        }
+    }
+}
+
+/// How `query_knowledge` finds relevant records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Cosine similarity of the embeddings.
+    Vector,
+    /// Full-text search on the content, good at identifiers, error codes and config keys.
+    Keyword,
+    /// Both searches, with the rankings fused.
+    #[default]
+    Hybrid,
+}
+
+impl FromStr for SearchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "vector" => Ok(SearchMode::Vector),
+            "keyword" => Ok(SearchMode::Keyword),
+            "hybrid" => Ok(SearchMode::Hybrid),
+            _ => Err(format!(
+                "Unknown search mode '{}', expected vector, keyword or hybrid",
+                s
+            )),
+        }
+    }
+}
+
+impl DataSource {
+    pub fn kind(&self) -> SourceKind {
+        match self {
+            DataSource::Context(_) => SourceKind::Context,
+            DataSource::Internet(_) => SourceKind::Internet,
+            DataSource::LocalFiles(_) => SourceKind::LocalFiles,
+        }
+    }
+
+    /// The file path or URL of the source.
+    pub fn name(&self) -> &str {
+        match self {
+            DataSource::Context(name)
+            | DataSource::Internet(name)
+            | DataSource::LocalFiles(name) => name,
+        }
+    }
+}
+
 impl Display for DataSource {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -94,8 +350,35 @@ impl Display for DataSource {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct KnowledgeResult {
+    /// Cosine similarity of the record to the query, 0 for hits of a keyword search.
+    #[serde(default)]
     pub distance: f32,
+    /// The score the results are ranked by: the cosine similarity, the BM25 score of the
+    /// keyword search or the fused score of a hybrid search.
+    #[serde(default)]
+    pub score: f32,
     pub data_source: DataSource,
+    #[serde(default = "default_collection")]
+    pub collection: String,
     pub content: Option<String>,
-    pub metadata: Option<String>,
+    pub metadata: Option<KnowledgeMetadata>,
+}
+
+impl KnowledgeResult {
+    /// The id of the record the result was found in, see `EmbeddingData::record_id`.
+    pub fn record_id(&self) -> String {
+        record_id(&self.collection, &self.data_source, &self.metadata)
+    }
+
+    /// Describes where the hit was found, the file or URL with the line range of the chunk.
+    /// Records stored without chunk metadata are named by their data source.
+    pub fn location(&self) -> String {
+        match &self.metadata {
+            Some(metadata) => format!(
+                "{}:{}-{}",
+                metadata.file, metadata.start_line, metadata.end_line
+            ),
+            None => self.data_source.to_string(),
+        }
+    }
 }
@@ -0,0 +1,177 @@
+//! A `KnowledgeStore` keeping its records in memory, for the tests of the knowledge module.
+
+use crate::knowledge::{
+    DataSource, EmbeddingData, EmbeddingInfo, IndexedSource, KnowledgeFilter, KnowledgeResult,
+    KnowledgeStats, KnowledgeStore, SearchMode,
+};
+use async_trait::async_trait;
+use std::borrow::Cow;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Keeps the records in memory and counts the embedded chunks. Embeddings are the length of
+/// the content, so they have one dimension.
+pub(crate) struct MemoryStore {
+    pub records: Mutex<Vec<EmbeddingData>>,
+    pub embeddings: AtomicUsize,
+    pub model: String,
+    pub recorded: Mutex<Option<EmbeddingInfo>>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        MemoryStore::with_model("test")
+    }
+}
+
+impl MemoryStore {
+    pub fn with_model(model: &str) -> Self {
+        MemoryStore {
+            records: Mutex::new(vec![]),
+            embeddings: AtomicUsize::new(0),
+            model: model.to_string(),
+            recorded: Mutex::new(None),
+        }
+    }
+
+    pub fn embedded(&self) -> usize {
+        self.embeddings.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl KnowledgeStore for MemoryStore {
+    async fn query_knowledge(
+        &self,
+        _user_input: Cow<'_, str>,
+        _limit: usize,
+        _mode: SearchMode,
+        _filter: &KnowledgeFilter,
+    ) -> Result<Vec<KnowledgeResult>, Box<dyn Error>> {
+        Ok(vec![])
+    }
+
+    async fn store_knowledge(&self, knowledge: EmbeddingData) -> Result<(), Box<dyn Error>> {
+        let mut recorded = self.recorded.lock().unwrap();
+        match recorded.as_ref() {
+            Some(recorded) => recorded.check(&self.model, knowledge.embedding.len())?,
+            None => {
+                *recorded = Some(EmbeddingInfo {
+                    model: self.model.clone(),
+                    dimension: knowledge.embedding.len(),
+                })
+            }
+        }
+        self.records.lock().unwrap().push(knowledge);
+        Ok(())
+    }
+
+    async fn remove_knowledge(
+        &self,
+        collection: &str,
+        data_source: &DataSource,
+    ) -> Result<(), Box<dyn Error>> {
+        self.records
+            .lock()
+            .unwrap()
+            .retain(|record| record.collection != collection || &record.data_source != data_source);
+        Ok(())
+    }
+
+    async fn replace_source(
+        &self,
+        collection: &str,
+        data_source: &DataSource,
+        records: Vec<EmbeddingData>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(first) = records.first() {
+            let mut recorded = self.recorded.lock().unwrap();
+            match recorded.as_ref() {
+                Some(recorded) => recorded.check(&self.model, first.embedding.len())?,
+                None => {
+                    *recorded = Some(EmbeddingInfo {
+                        model: self.model.clone(),
+                        dimension: first.embedding.len(),
+                    })
+                }
+            }
+        }
+        let mut stored = self.records.lock().unwrap();
+        stored
+            .retain(|record| record.collection != collection || &record.data_source != data_source);
+        stored.extend(records);
+        Ok(())
+    }
+
+    async fn list_sources(&self) -> Result<Vec<IndexedSource>, Box<dyn Error>> {
+        let mut sources: Vec<IndexedSource> = Vec::new();
+        for record in self.records.lock().unwrap().iter() {
+            match sources.iter_mut().find(|source| {
+                source.collection == record.collection && source.data_source == record.data_source
+            }) {
+                Some(source) => source.chunks += 1,
+                None => {
+                    let metadata = record.metadata.clone().unwrap_or_default();
+                    sources.push(IndexedSource {
+                        data_source: record.data_source.clone(),
+                        collection: record.collection.clone(),
+                        content_hash: metadata.content_hash,
+                        modified: metadata.modified,
+                        chunks: 1,
+                    });
+                }
+            }
+        }
+        Ok(sources)
+    }
+
+    async fn stats(&self) -> Result<KnowledgeStats, Box<dyn Error>> {
+        let sources = self.list_sources().await?.len();
+        let records = self.records.lock().unwrap().clone();
+        let mut collections: Vec<String> = records
+            .iter()
+            .map(|record| record.collection.clone())
+            .collect();
+        collections.sort();
+        collections.dedup();
+        Ok(KnowledgeStats {
+            records: records.len(),
+            sources,
+            collections,
+            embedding: self.recorded.lock().unwrap().clone(),
+            configured_model: self.model.clone(),
+            size_on_disk: 0,
+        })
+    }
+
+    async fn list_records(&self) -> Result<Vec<EmbeddingData>, Box<dyn Error>> {
+        Ok(self.records.lock().unwrap().clone())
+    }
+
+    async fn purge_knowledge(&self) -> Result<(), Box<dyn Error>> {
+        self.records.lock().unwrap().clear();
+        *self.recorded.lock().unwrap() = None;
+        Ok(())
+    }
+
+    async fn replace_knowledge(&self, records: Vec<EmbeddingData>) -> Result<(), Box<dyn Error>> {
+        let embedding = EmbeddingInfo::of_records(&self.model, &records)?;
+        *self.records.lock().unwrap() = records;
+        *self.recorded.lock().unwrap() = embedding;
+        Ok(())
+    }
+
+    fn embedding_model(&self) -> &str {
+        &self.model
+    }
+
+    async fn recorded_embedding(&self) -> Result<Option<EmbeddingInfo>, Box<dyn Error>> {
+        Ok(self.recorded.lock().unwrap().clone())
+    }
+
+    async fn get_embedding(&self, content: Cow<'_, str>) -> Result<Box<Vec<f32>>, Box<dyn Error>> {
+        self.embeddings.fetch_add(1, Ordering::SeqCst);
+        Ok(Box::new(vec![content.len() as f32]))
+    }
+}
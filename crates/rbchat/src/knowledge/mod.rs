@@ -1,8 +1,32 @@
+mod chunker;
+mod crawler;
+mod embedding_cache;
 mod embedding_service_builder;
+mod export;
+mod filter;
+mod indexing;
 mod interface;
+#[cfg(test)]
+mod memory_store;
+mod robots;
+mod search;
+mod sqlite_store;
 mod store_builder;
 mod store_impl;
+mod sync;
+mod throttle;
 
+pub use chunker::{Chunk, Chunker, DEFAULT_MAX_CHARS, DEFAULT_OVERLAP_CHARS};
+pub use crawler::{crawl_knowledge, CrawlOptions, CrawlReport, CrawledPage, Crawler};
 pub(super) use embedding_service_builder::EmbeddingServiceBuilder;
+pub use export::{export_knowledge, import_knowledge, ImportReport};
+pub use filter::{parse_date, KnowledgeFilter, PathPattern};
+pub use indexing::{content_hash, index_document, reindex_knowledge, ReindexReport};
 pub use interface::*;
+pub use robots::Robots;
+pub use sqlite_store::SqliteStore;
 pub use store_builder::StoreBuilder;
+pub use sync::{
+    file_modified, remove_missing_files, sync_document, sync_file, sync_knowledge, SyncOutcome,
+    SyncReport,
+};
@@ -0,0 +1,140 @@
+//! This module reads the `robots.txt` of a site, so the crawler keeps out of the pages the
+//! site excludes.
+//!
+//! The rules of the group naming the crawler's user agent apply, or the rules of the `*`
+//! group if there is none. The longest matching `Allow` or `Disallow` rule decides, `Allow`
+//! winning ties. Rules may use `*` wildcards and end with `$` to match the end of the path.
+
+/// The rules of a `robots.txt` for one user agent, and the sitemaps it lists.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    pub sitemaps: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+impl Robots {
+    /// Parses `text` for the crawler named `user_agent`.
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_lowercase();
+        let mut robots = Robots::default();
+        let mut own_rules = Vec::new();
+        let mut any_rules = Vec::new();
+        // The agents of the current group, a group ends when its rules are followed by an agent
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match field.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty `Disallow` allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = Rule {
+                        allow: field.trim().eq_ignore_ascii_case("allow"),
+                        pattern: value.to_string(),
+                    };
+                    if agents
+                        .iter()
+                        .any(|agent| user_agent.contains(agent.as_str()) && agent != "*")
+                    {
+                        own_rules.push(rule);
+                    } else if agents.iter().any(|agent| agent == "*") {
+                        any_rules.push(rule);
+                    }
+                }
+                "sitemap" => robots.sitemaps.push(value.to_string()),
+                _ => {}
+            }
+        }
+
+        robots.rules = if own_rules.is_empty() {
+            any_rules
+        } else {
+            own_rules
+        };
+        robots
+    }
+
+    /// True if the crawler may fetch `path`, including its query.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| matches_pattern(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+// Matches `path` against a rule, which is a prefix with `*` wildcards and an optional `$` end
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        // The last part of an anchored pattern has to match the end of the path
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots_rules() {
+        let text = "# Example\n\
+            User-agent: *\n\
+            Disallow: /private/\n\
+            Allow: /private/public.html\n\
+            Disallow: /*.pdf$\n\
+            \n\
+            User-agent: other-bot\n\
+            Disallow: /\n\
+            \n\
+            Sitemap: https://example.com/sitemap.xml\n";
+
+        let robots = Robots::parse(text, "rusty-buddy");
+
+        assert!(robots.is_allowed("/guide/index.html"));
+        assert!(!robots.is_allowed("/private/notes.html"));
+        assert!(robots.is_allowed("/private/public.html"));
+        assert!(!robots.is_allowed("/files/manual.pdf"));
+        assert!(robots.is_allowed("/files/manual.pdf?download=1"));
+        assert_eq!(robots.sitemaps, vec!["https://example.com/sitemap.xml"]);
+        assert!(!Robots::parse(text, "other-bot/1.0").is_allowed("/guide/"));
+    }
+}
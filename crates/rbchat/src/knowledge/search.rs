@@ -0,0 +1,99 @@
+//! This module combines the results of the vector and the keyword search.
+//!
+//! Cosine similarities and BM25 scores are not comparable, so a hybrid search fuses the two
+//! rankings by reciprocal rank fusion: every result scores `1 / (RRF_K + rank)` in each
+//! ranking it appears in, and the scores are added up. Records found by both searches rise to
+//! the top, while a record that only one search finds keeps its place among the others.
+
+use crate::knowledge::{KnowledgeFilter, KnowledgeResult};
+use std::collections::HashMap;
+
+/// Dampens the lead of the first ranks, the value proposed with the method.
+const RRF_K: f32 = 60.0;
+
+/// Number of candidates each search of a hybrid search contributes, per requested result.
+const HYBRID_CANDIDATES: usize = 2;
+
+/// Number of results each search of a hybrid search for `limit` results looks for.
+pub(crate) fn hybrid_candidates(filter: &KnowledgeFilter, limit: usize) -> usize {
+    filter.candidates(limit) * HYBRID_CANDIDATES
+}
+
+/// Fuses the results of the vector and the keyword search of a hybrid search into the `limit`
+/// best results matching `filter`.
+pub(crate) fn fuse_hybrid(
+    vector: Vec<KnowledgeResult>,
+    keyword: Vec<KnowledgeResult>,
+    filter: &KnowledgeFilter,
+    limit: usize,
+) -> Vec<KnowledgeResult> {
+    let candidates = hybrid_candidates(filter, limit);
+    // Results are filtered before fusing, so they are ranked among the matching ones
+    let rankings = vec![
+        filter.apply(vector, candidates),
+        filter.apply(keyword, candidates),
+    ];
+    fuse_rankings(rankings, limit)
+}
+
+/// Fuses rankings of results, best first, into one ranking of at most `limit` results.
+pub(crate) fn fuse_rankings(
+    rankings: Vec<Vec<KnowledgeResult>>,
+    limit: usize,
+) -> Vec<KnowledgeResult> {
+    let mut fused: Vec<KnowledgeResult> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for ranking in rankings {
+        for (rank, result) in ranking.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            match positions.get(&result.record_id()) {
+                Some(&position) => {
+                    let existing = &mut fused[position];
+                    existing.score += score;
+                    existing.distance = existing.distance.max(result.distance);
+                }
+                None => {
+                    positions.insert(result.record_id(), fused.len());
+                    fused.push(KnowledgeResult { score, ..result });
+                }
+            }
+        }
+    }
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused.truncate(limit);
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::{DataSource, DEFAULT_COLLECTION};
+
+    fn result(name: &str, distance: f32) -> KnowledgeResult {
+        KnowledgeResult {
+            distance,
+            score: distance,
+            data_source: DataSource::Context(name.to_string()),
+            collection: DEFAULT_COLLECTION.to_string(),
+            content: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_results_found_by_both_searches_rank_first() {
+        let vector = vec![
+            result("a.rs", 0.9),
+            result("b.rs", 0.8),
+            result("c.rs", 0.7),
+        ];
+        let keyword = vec![result("c.rs", 0.0), result("d.rs", 0.0)];
+
+        let fused = fuse_rankings(vec![vector, keyword], 3);
+
+        let names: Vec<_> = fused.iter().map(|r| r.data_source.name()).collect();
+        assert_eq!(names, vec!["c.rs", "a.rs", "b.rs"]);
+        // The similarity of the vector search is kept for results found by both
+        assert_eq!(fused[0].distance, 0.7);
+    }
+}
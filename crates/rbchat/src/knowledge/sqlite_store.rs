@@ -0,0 +1,812 @@
+//! This module implements a `KnowledgeStore` on a SQLite file, selected with
+//! `backend = "Sqlite"` in the `knowledge_store` configuration.
+//!
+//! The store needs no server and takes no exclusive lock, so several processes can read and
+//! write the knowledge at once. It has no vector index: a vector search compares the query
+//! with every embedding of the searched collections, which is fast enough for the few
+//! hundred thousand chunks of a large repository.
+//!
+//! The keyword search uses an FTS5 table ranked by BM25. Its terms are split like the
+//! analyzer of the SurrealDB store splits them, so `ChatService` is found by `chat service`.
+
+use crate::config::{get_knowledge_db_file, CONFIG};
+use crate::knowledge::embedding_cache::{decode_embedding, encode_embedding};
+use crate::knowledge::search::{fuse_hybrid, hybrid_candidates};
+use crate::knowledge::{
+    DataSource, EmbeddingData, EmbeddingInfo, EmbeddingServiceBuilder, EmbeddingServiceHandle,
+    IndexedSource, KnowledgeFilter, KnowledgeMetadata, KnowledgeResult, KnowledgeStats,
+    KnowledgeStore, SearchMode,
+};
+use async_trait::async_trait;
+use log::info;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Transaction};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long a write waits for another process writing to the file.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS knowledge_records (
+        row INTEGER PRIMARY KEY,
+        id TEXT NOT NULL UNIQUE,
+        collection TEXT NOT NULL,
+        data_source TEXT NOT NULL,
+        content TEXT,
+        metadata TEXT,
+        embedding BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_records_source ON knowledge_records (collection, data_source);
+    CREATE VIRTUAL TABLE IF NOT EXISTS knowledge_terms USING fts5(terms);
+    CREATE TABLE IF NOT EXISTS knowledge_meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+";
+
+const RECORD_COLUMNS: &str = "r.collection, r.data_source, r.content, r.metadata, r.embedding";
+
+/// A `KnowledgeStore` keeping its records in a SQLite file.
+///
+/// Records are rows of `knowledge_records`, with their data source and metadata as JSON and
+/// their embedding as little-endian `f32` values. The words of their content are indexed in
+/// `knowledge_terms` under the same row id. The embedding model is recorded in
+/// `knowledge_meta` with the first record, like the SurrealDB store records it.
+pub struct SqliteStore {
+    embedding_model: String,
+    embedding_service: EmbeddingServiceHandle,
+    path: PathBuf,
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens `.rusty/knowledge.sqlite`, creating it if needed, with the configured embedding
+    /// model.
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let embedding_model = {
+            let config = CONFIG.lock().unwrap();
+            config.ai.embedding_model.clone()
+        };
+        let embedding_service = EmbeddingServiceBuilder::new()
+            .model_name(Cow::Borrowed(&embedding_model))
+            .build()?;
+        SqliteStore::open(&get_knowledge_db_file()?, embedding_service)
+    }
+
+    /// Opens the database at `path`, creating it if needed. The model of `embedding_service`
+    /// is recorded with the embeddings.
+    pub fn open(
+        path: &Path,
+        embedding_service: EmbeddingServiceHandle,
+    ) -> Result<Self, Box<dyn Error>> {
+        info!("Opening knowledge database {}", path.display());
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        // Readers do not block the writer of another process
+        connection.query_row("PRAGMA journal_mode = WAL;", [], |_| Ok(()))?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore {
+            embedding_model: embedding_service.model.clone(),
+            embedding_service,
+            path: path.to_path_buf(),
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+/// A row of `knowledge_records`, before its JSON columns are parsed.
+struct StoredRecord {
+    collection: String,
+    data_source: String,
+    content: Option<String>,
+    metadata: Option<String>,
+    embedding: Vec<u8>,
+}
+
+impl StoredRecord {
+    fn read(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(StoredRecord {
+            collection: row.get(0)?,
+            data_source: row.get(1)?,
+            content: row.get(2)?,
+            metadata: row.get(3)?,
+            embedding: row.get(4)?,
+        })
+    }
+
+    fn into_data(self) -> Result<EmbeddingData, Box<dyn Error>> {
+        Ok(EmbeddingData {
+            data_source: serde_json::from_str(&self.data_source)?,
+            collection: self.collection,
+            embedding: decode_embedding(&self.embedding),
+            content: self.content,
+            metadata: self
+                .metadata
+                .map(|metadata| serde_json::from_str(&metadata))
+                .transpose()?,
+        })
+    }
+
+    fn into_result(self, distance: f32, score: f32) -> Result<KnowledgeResult, Box<dyn Error>> {
+        let data = self.into_data()?;
+        Ok(KnowledgeResult {
+            distance,
+            score,
+            data_source: data.data_source,
+            collection: data.collection,
+            content: data.content,
+            metadata: data.metadata,
+        })
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|b| b * b).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+// Splits text into lowercase words like the analyzer of the SurrealDB store: at blanks and
+// punctuation, between letters and digits, and at the humps of camel case
+fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous: Option<char> = None;
+    for c in text.chars() {
+        let boundary = match previous {
+            Some(previous) if c.is_alphanumeric() => {
+                previous.is_alphabetic() != c.is_alphabetic()
+                    || (previous.is_lowercase() && c.is_uppercase())
+            }
+            _ => true,
+        };
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+            previous = Some(c);
+        } else {
+            previous = None;
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+// Restricts a search of the records `r` to `collections`, unless all collections are searched
+fn collection_filter(collections: &[String]) -> String {
+    if collections.is_empty() {
+        String::new()
+    } else {
+        let placeholders = vec!["?"; collections.len()].join(", ");
+        format!(" AND r.collection IN ({})", placeholders)
+    }
+}
+
+fn read_embedding(connection: &Connection) -> Result<Option<EmbeddingInfo>, Box<dyn Error>> {
+    let value: Option<String> = connection
+        .query_row(
+            "SELECT value FROM knowledge_meta WHERE key = 'embedding';",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value
+        .map(|value| serde_json::from_str(&value))
+        .transpose()?)
+}
+
+// Removes the records matching `condition` and the terms indexed for them
+fn delete_records(
+    transaction: &Transaction,
+    condition: &str,
+    params: impl rusqlite::Params + Clone,
+) -> Result<(), Box<dyn Error>> {
+    transaction.execute(
+        &format!(
+            "DELETE FROM knowledge_terms WHERE rowid IN \
+             (SELECT row FROM knowledge_records WHERE {});",
+            condition
+        ),
+        params.clone(),
+    )?;
+    transaction.execute(
+        &format!("DELETE FROM knowledge_records WHERE {};", condition),
+        params,
+    )?;
+    Ok(())
+}
+
+fn record_embedding(transaction: &Transaction, info: &EmbeddingInfo) -> Result<(), Box<dyn Error>> {
+    transaction.execute(
+        "INSERT INTO knowledge_meta (key, value) VALUES ('embedding', ?1);",
+        params![serde_json::to_string(info)?],
+    )?;
+    Ok(())
+}
+
+// Adds a record and indexes the words of its content under its row id
+fn insert_record(
+    transaction: &Transaction,
+    knowledge: &EmbeddingData,
+) -> Result<(), Box<dyn Error>> {
+    transaction.execute(
+        "INSERT INTO knowledge_records \
+         (id, collection, data_source, content, metadata, embedding) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+        params![
+            knowledge.record_id(),
+            knowledge.collection,
+            serde_json::to_string(&knowledge.data_source)?,
+            knowledge.content,
+            knowledge
+                .metadata
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            encode_embedding(&knowledge.embedding),
+        ],
+    )?;
+    let terms = words(knowledge.content.as_deref().unwrap_or_default()).join(" ");
+    transaction.execute(
+        "INSERT INTO knowledge_terms (rowid, terms) VALUES (?1, ?2);",
+        params![transaction.last_insert_rowid(), terms],
+    )?;
+    Ok(())
+}
+
+// Compares `embedding` with every record of `collections` and returns the closest ones
+fn vector_search(
+    connection: &Connection,
+    embedding: &[f32],
+    collections: &[String],
+    limit: usize,
+) -> Result<Vec<KnowledgeResult>, Box<dyn Error>> {
+    info!("Searching for knowledge for embedding");
+    let mut statement = connection.prepare(&format!(
+        "SELECT {} FROM knowledge_records r WHERE 1 = 1{};",
+        RECORD_COLUMNS,
+        collection_filter(collections)
+    ))?;
+    let mut scored = Vec::new();
+    for record in statement.query_map(params_from_iter(collections), StoredRecord::read)? {
+        let record = record?;
+        let similarity = cosine_similarity(embedding, &decode_embedding(&record.embedding));
+        scored.push((similarity, record));
+    }
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(limit);
+    scored
+        .into_iter()
+        .map(|(similarity, record)| record.into_result(similarity, similarity))
+        .collect()
+}
+
+// Searches the indexed words by BM25. With an embedding, the similarity of the hits is
+// computed as well.
+fn keyword_search(
+    connection: &Connection,
+    user_input: &str,
+    embedding: Option<&[f32]>,
+    collections: &[String],
+    limit: usize,
+) -> Result<Vec<KnowledgeResult>, Box<dyn Error>> {
+    info!("Searching for knowledge by keywords");
+    let words = words(user_input);
+    if words.is_empty() {
+        return Ok(vec![]);
+    }
+    // Quoted words are taken literally, whatever FTS5 operators the input holds
+    let query = words
+        .iter()
+        .map(|word| format!("\"{}\"", word))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let mut statement = connection.prepare(&format!(
+        "SELECT {}, bm25(knowledge_terms) AS rank \
+         FROM knowledge_terms JOIN knowledge_records r ON r.row = knowledge_terms.rowid \
+         WHERE knowledge_terms MATCH ?{} \
+         ORDER BY rank LIMIT {};",
+        RECORD_COLUMNS,
+        collection_filter(collections),
+        limit
+    ))?;
+    let params = std::iter::once(&query).chain(collections);
+    let rows = statement.query_map(params_from_iter(params), |row| {
+        Ok((StoredRecord::read(row)?, row.get::<_, f64>(5)?))
+    })?;
+    let mut found = Vec::new();
+    for row in rows {
+        let (record, rank) = row?;
+        let distance = embedding.map_or(0.0, |embedding| {
+            cosine_similarity(embedding, &decode_embedding(&record.embedding))
+        });
+        // BM25 ranks of SQLite are negative, the best match has the lowest
+        found.push(record.into_result(distance, -rank as f32)?);
+    }
+    Ok(found)
+}
+
+#[async_trait]
+impl KnowledgeStore for SqliteStore {
+    async fn query_knowledge(
+        &self,
+        user_input: Cow<'_, str>,
+        limit: usize,
+        mode: SearchMode,
+        filter: &KnowledgeFilter,
+    ) -> Result<Vec<KnowledgeResult>, Box<dyn Error>> {
+        let collections = &filter.collections;
+        let candidates = filter.candidates(limit);
+        if mode == SearchMode::Keyword {
+            let connection = self.connection.lock().unwrap();
+            let found = keyword_search(&connection, &user_input, None, collections, candidates)?;
+            return Ok(filter.apply(found, limit));
+        }
+
+        // The embedding is fetched before the connection is locked
+        let embedding = self
+            .embedding_service
+            .inner
+            .get_embedding(user_input.clone())
+            .await?;
+        let connection = self.connection.lock().unwrap();
+        if let Some(recorded) = read_embedding(&connection)? {
+            recorded.check(&self.embedding_model, embedding.len())?;
+        }
+        if mode == SearchMode::Vector {
+            let found = vector_search(&connection, &embedding, collections, candidates)?;
+            return Ok(filter.apply(found, limit));
+        }
+
+        let candidates = hybrid_candidates(filter, limit);
+        let vector = vector_search(&connection, &embedding, collections, candidates)?;
+        let keyword = keyword_search(
+            &connection,
+            &user_input,
+            Some(&embedding),
+            collections,
+            candidates,
+        )?;
+        Ok(fuse_hybrid(vector, keyword, filter, limit))
+    }
+
+    async fn store_knowledge(&self, knowledge: EmbeddingData) -> Result<(), Box<dyn Error>> {
+        info!("Storing knowledge for: {}", knowledge.data_source);
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        match read_embedding(&transaction)? {
+            Some(recorded) => recorded.check(&self.embedding_model, knowledge.embedding.len())?,
+            None => {
+                let info = EmbeddingInfo {
+                    model: self.embedding_model.clone(),
+                    dimension: knowledge.embedding.len(),
+                };
+                record_embedding(&transaction, &info)?;
+            }
+        }
+
+        delete_records(&transaction, "id = ?1", params![knowledge.record_id()])?;
+        insert_record(&transaction, &knowledge)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    async fn remove_knowledge(
+        &self,
+        collection: &str,
+        data_source: &DataSource,
+    ) -> Result<(), Box<dyn Error>> {
+        info!("Removing knowledge for: {} in {}", data_source, collection);
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        delete_records(
+            &transaction,
+            "collection = ?1 AND data_source = ?2",
+            params![collection, serde_json::to_string(data_source)?],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    async fn replace_source(
+        &self,
+        collection: &str,
+        data_source: &DataSource,
+        records: Vec<EmbeddingData>,
+    ) -> Result<(), Box<dyn Error>> {
+        info!(
+            "Replacing knowledge for: {} in {} with {} records",
+            data_source,
+            collection,
+            records.len()
+        );
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        if let Some(first) = records.first() {
+            match read_embedding(&transaction)? {
+                Some(recorded) => recorded.check(&self.embedding_model, first.embedding.len())?,
+                None => {
+                    let info = EmbeddingInfo {
+                        model: self.embedding_model.clone(),
+                        dimension: first.embedding.len(),
+                    };
+                    record_embedding(&transaction, &info)?;
+                }
+            }
+        }
+
+        delete_records(
+            &transaction,
+            "collection = ?1 AND data_source = ?2",
+            params![collection, serde_json::to_string(data_source)?],
+        )?;
+        for record in &records {
+            insert_record(&transaction, record)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    async fn list_sources(&self) -> Result<Vec<IndexedSource>, Box<dyn Error>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT collection, data_source, metadata FROM knowledge_records ORDER BY row;",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+
+        // Every chunk is a record of its own, the sources are listed once
+        let mut sources: Vec<IndexedSource> = Vec::new();
+        let mut positions: HashMap<(String, String), usize> = HashMap::new();
+        for row in rows {
+            let (collection, data_source, metadata) = row?;
+            let key = (collection, data_source);
+            match positions.get(&key) {
+                Some(&position) => sources[position].chunks += 1,
+                None => {
+                    let metadata: KnowledgeMetadata = metadata
+                        .map(|metadata| serde_json::from_str(&metadata))
+                        .transpose()?
+                        .unwrap_or_default();
+                    positions.insert(key.clone(), sources.len());
+                    let (collection, data_source) = key;
+                    sources.push(IndexedSource {
+                        data_source: serde_json::from_str(&data_source)?,
+                        collection,
+                        content_hash: metadata.content_hash,
+                        modified: metadata.modified,
+                        chunks: 1,
+                    });
+                }
+            }
+        }
+        Ok(sources)
+    }
+
+    async fn stats(&self) -> Result<KnowledgeStats, Box<dyn Error>> {
+        let sources = self.list_sources().await?;
+        let embedding = self.recorded_embedding().await?;
+        // The write-ahead log holds the changes not yet moved into the file
+        let mut wal = self.path.clone().into_os_string();
+        wal.push("-wal");
+        let size_on_disk = [self.path.clone(), PathBuf::from(wal)]
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        let mut collections: Vec<String> = sources
+            .iter()
+            .map(|source| source.collection.clone())
+            .collect();
+        collections.sort();
+        collections.dedup();
+        Ok(KnowledgeStats {
+            records: sources.iter().map(|source| source.chunks).sum(),
+            sources: sources.len(),
+            collections,
+            embedding,
+            configured_model: self.embedding_model.clone(),
+            size_on_disk,
+        })
+    }
+
+    async fn list_records(&self) -> Result<Vec<EmbeddingData>, Box<dyn Error>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM knowledge_records r ORDER BY r.row;",
+            RECORD_COLUMNS
+        ))?;
+        let records = statement
+            .query_map([], StoredRecord::read)?
+            .collect::<Result<Vec<_>, _>>()?;
+        records.into_iter().map(StoredRecord::into_data).collect()
+    }
+
+    async fn purge_knowledge(&self) -> Result<(), Box<dyn Error>> {
+        info!("Removing all knowledge");
+        let connection = self.connection.lock().unwrap();
+        connection.execute_batch(
+            "BEGIN;
+             DELETE FROM knowledge_terms;
+             DELETE FROM knowledge_records;
+             DELETE FROM knowledge_meta;
+             COMMIT;",
+        )?;
+        Ok(())
+    }
+
+    async fn replace_knowledge(&self, records: Vec<EmbeddingData>) -> Result<(), Box<dyn Error>> {
+        info!("Replacing all knowledge with {} records", records.len());
+        let embedding = EmbeddingInfo::of_records(&self.embedding_model, &records)?;
+        let mut connection = self.connection.lock().unwrap();
+        // Other processes keep seeing the old records until the transaction is committed
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(
+            "DELETE FROM knowledge_terms;
+             DELETE FROM knowledge_records;
+             DELETE FROM knowledge_meta;",
+        )?;
+        if let Some(embedding) = &embedding {
+            record_embedding(&transaction, embedding)?;
+        }
+        for record in &records {
+            insert_record(&transaction, record)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    async fn recorded_embedding(&self) -> Result<Option<EmbeddingInfo>, Box<dyn Error>> {
+        let connection = self.connection.lock().unwrap();
+        read_embedding(&connection)
+    }
+
+    async fn get_embedding(&self, content: Cow<'_, str>) -> Result<Box<Vec<f32>>, Box<dyn Error>> {
+        Ok(self.embedding_service.inner.get_embedding(content).await?)
+    }
+
+    async fn get_embeddings(&self, contents: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        self.embedding_service.inner.get_embeddings(contents).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::EmbeddingService;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    // Embeds a text as the number of times it names each of three topics
+    struct TopicEmbedding;
+
+    #[async_trait]
+    impl EmbeddingService for TopicEmbedding {
+        async fn get_embedding(
+            &self,
+            content: Cow<'_, str>,
+        ) -> Result<Box<Vec<f32>>, Box<dyn Error>> {
+            let content = content.to_lowercase();
+            let topics = ["chat", "knowledge", "config"];
+            Ok(Box::new(
+                topics
+                    .iter()
+                    .map(|topic| content.matches(topic).count() as f32)
+                    .collect(),
+            ))
+        }
+    }
+
+    async fn store_text(store: &SqliteStore, collection: &str, file: &str, content: &str) {
+        let embedding = store.get_embedding(Cow::Borrowed(content)).await.unwrap();
+        store
+            .store_knowledge(EmbeddingData {
+                data_source: DataSource::LocalFiles(file.to_string()),
+                collection: collection.to_string(),
+                embedding: *embedding,
+                content: Some(content.to_string()),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_words_split_identifiers() {
+        assert_eq!(
+            words("ChatService::max_tokens E0308"),
+            vec!["chat", "service", "max", "tokens", "e", "0308"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store_searches_and_removes() {
+        let dir = TempDir::new().unwrap();
+        let service = EmbeddingServiceHandle::new(Arc::new(TopicEmbedding), "topics".into());
+        let store = SqliteStore::open(&dir.path().join("knowledge.sqlite"), service).unwrap();
+        store_text(&store, "code", "chat.rs", "struct ChatService; chat chat").await;
+        store_text(&store, "code", "store.rs", "trait KnowledgeStore").await;
+        store_text(&store, "docs", "config.md", "Configure the chat model").await;
+
+        let filter = KnowledgeFilter::default();
+        let found = store
+            .query_knowledge(Cow::Borrowed("chat"), 2, SearchMode::Vector, &filter)
+            .await
+            .unwrap();
+        let names: Vec<_> = found.iter().map(|r| r.data_source.name()).collect();
+        assert_eq!(names, vec!["chat.rs", "config.md"]);
+
+        let found = store
+            .query_knowledge(
+                Cow::Borrowed("knowledge store"),
+                5,
+                SearchMode::Keyword,
+                &filter,
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].data_source.name(), "store.rs");
+
+        store
+            .remove_knowledge("code", &DataSource::LocalFiles("chat.rs".to_string()))
+            .await
+            .unwrap();
+        let sources = store.list_sources().await.unwrap();
+        assert_eq!(sources.len(), 2);
+        let found = store
+            .query_knowledge(
+                Cow::Borrowed("chat service"),
+                5,
+                SearchMode::Keyword,
+                &filter,
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].data_source.name(), "config.md");
+        assert_eq!(
+            store.recorded_embedding().await.unwrap(),
+            Some(EmbeddingInfo {
+                model: "topics".to_string(),
+                dimension: 3
+            })
+        );
+    }
+
+    fn record(file: &str, embedding: Vec<f32>) -> EmbeddingData {
+        EmbeddingData {
+            data_source: DataSource::LocalFiles(file.to_string()),
+            collection: "code".to_string(),
+            embedding,
+            content: Some(format!("content of {}", file)),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replace_knowledge_is_atomic() {
+        let dir = TempDir::new().unwrap();
+        let service = EmbeddingServiceHandle::new(Arc::new(TopicEmbedding), "topics".into());
+        let store = SqliteStore::open(&dir.path().join("knowledge.sqlite"), service).unwrap();
+        store_text(&store, "code", "chat.rs", "struct ChatService; chat chat").await;
+
+        // Records of mismatching dimensions are refused as a whole
+        let error = store
+            .replace_knowledge(vec![
+                record("a.rs", vec![1.0, 0.0]),
+                record("b.rs", vec![1.0, 0.0, 0.0, 0.0]),
+            ])
+            .await;
+        assert!(error.is_err());
+        let records = store.list_records().await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].data_source.name(), "chat.rs");
+
+        // A duplicate record fails after the old records were deleted, which is rolled back
+        let error = store
+            .replace_knowledge(vec![
+                record("a.rs", vec![1.0, 0.0]),
+                record("a.rs", vec![0.0, 1.0]),
+            ])
+            .await;
+        assert!(error.is_err());
+        assert_eq!(store.list_records().await.unwrap().len(), 1);
+        assert_eq!(
+            store.recorded_embedding().await.unwrap().unwrap().dimension,
+            3
+        );
+
+        store
+            .replace_knowledge(vec![
+                record("a.rs", vec![1.0, 0.0]),
+                record("b.rs", vec![0.0, 1.0]),
+            ])
+            .await
+            .unwrap();
+        let names: Vec<_> = store
+            .list_records()
+            .await
+            .unwrap()
+            .iter()
+            .map(|record| record.data_source.name().to_string())
+            .collect();
+        assert_eq!(names, vec!["a.rs", "b.rs"]);
+        assert_eq!(
+            store.recorded_embedding().await.unwrap().unwrap().dimension,
+            2
+        );
+        let found = store
+            .query_knowledge(
+                Cow::Borrowed("content"),
+                5,
+                SearchMode::Keyword,
+                &KnowledgeFilter::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_replace_source_is_atomic() {
+        let dir = TempDir::new().unwrap();
+        let service = EmbeddingServiceHandle::new(Arc::new(TopicEmbedding), "topics".into());
+        let store = SqliteStore::open(&dir.path().join("knowledge.sqlite"), service).unwrap();
+        store_text(&store, "code", "a.rs", "struct ChatService; chat chat").await;
+        store_text(&store, "code", "b.rs", "trait KnowledgeStore").await;
+        let source = DataSource::LocalFiles("a.rs".to_string());
+
+        // A duplicate record fails after the old records were deleted, which is rolled back
+        let error = store
+            .replace_source(
+                "code",
+                &source,
+                vec![
+                    record("a.rs", vec![1.0, 0.0, 0.0]),
+                    record("a.rs", vec![0.0, 1.0, 0.0]),
+                ],
+            )
+            .await;
+        assert!(error.is_err());
+        let records = store.list_records().await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].content.as_deref(),
+            Some("struct ChatService; chat chat")
+        );
+
+        store
+            .replace_source("code", &source, vec![record("a.rs", vec![1.0, 0.0, 0.0])])
+            .await
+            .unwrap();
+        let contents: Vec<_> = store
+            .list_records()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|record| record.content)
+            .collect();
+        assert_eq!(contents, vec!["trait KnowledgeStore", "content of a.rs"]);
+    }
+}
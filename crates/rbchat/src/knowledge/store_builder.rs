@@ -1,3 +1,5 @@
+use crate::config::{StoreBackend, CONFIG};
+use crate::knowledge::sqlite_store::SqliteStore;
 use crate::knowledge::store_impl::KnowledgeStoreImpl;
 use crate::knowledge::{ConnectionMode, KnowledgeStore};
 use std::error::Error;
@@ -15,11 +17,19 @@ impl StoreBuilder {
 }
 
 impl StoreBuilder {
-    // Build method to construct the ChatServiceFactory
+    // Build method to construct the store of the configured backend
     pub async fn build(&self) -> Result<Arc<dyn KnowledgeStore>, Box<dyn Error>> {
-        Ok(Arc::new(
-            KnowledgeStoreImpl::new(self.connection_mode).await?,
-        ))
+        let backend = {
+            let config = CONFIG.lock().unwrap();
+            config.knowledge_store.backend
+        };
+        match backend {
+            StoreBackend::SurrealDb => Ok(Arc::new(
+                KnowledgeStoreImpl::new(self.connection_mode).await?,
+            )),
+            // SQLite opens its file without locking it, so the connection mode does not matter
+            StoreBackend::Sqlite => Ok(Arc::new(SqliteStore::new()?)),
+        }
     }
 
     pub fn connection_mode(&mut self, mode: ConnectionMode) -> &mut Self {
@@ -1,131 +1,389 @@
-use crate::config::{get_knowledge_dir, CONFIG};
+use crate::config::{get_knowledge_dir, KnowledgeStoreConfig, CONFIG};
+use crate::knowledge::search::{fuse_hybrid, hybrid_candidates};
 use crate::knowledge::{
-    ConnectionMode, EmbeddingData, EmbeddingServiceBuilder, EmbeddingServiceHandle,
-    KnowledgeResult, KnowledgeStore,
+    ConnectionMode, DataSource, EmbeddingData, EmbeddingInfo, EmbeddingServiceBuilder,
+    EmbeddingServiceHandle, IndexedSource, KnowledgeFilter, KnowledgeMetadata, KnowledgeResult,
+    KnowledgeStats, KnowledgeStore, SearchMode, DEFAULT_COLLECTION,
 };
 use async_trait::async_trait;
+use dotenvy::dotenv;
 use log::{info, warn};
+use serde::Deserialize;
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
 use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::sync::Arc;
-use surrealdb::engine::local::{Db, RocksDb};
-use surrealdb::Surreal;
+use surrealdb::engine::any::{connect, Any};
+use surrealdb::opt::auth::Root;
+use surrealdb::{Response, Surreal};
 
 /// Concrete implementation of the `KnowledgeStore` trait.
 /// This implementation is backed by SurrealDB and generates embeddings
 /// using the EmbeddingServiceHandle.
+///
+/// The database is embedded and kept in `.rusty/knowledge`, unless the `url` of the
+/// `knowledge_store` configuration names a SurrealDB server. The embedded engine locks its
+/// directory, so only one process can use it at a time; a server has no such limit.
+///
+/// The model and dimension of the embeddings are recorded in `knowledge_meta:embedding` when
+/// the first record is stored, and the vector index is defined for that dimension. Records and
+/// queries embedded with another model are refused until the store is re-indexed.
+///
+/// All collections share the table; every record names its collection in the `collection`
+/// field, so a query can search one, several or all of them.
 pub struct KnowledgeStoreImpl {
+    embedding_model: String,
     embedding_service: EmbeddingServiceHandle,
-    db: Option<Arc<Surreal<Db>>>,
+    store_config: KnowledgeStoreConfig,
+    db: Option<Arc<Surreal<Any>>>,
 }
 
 impl KnowledgeStoreImpl {
     pub async fn new(mode: ConnectionMode) -> Result<Self, Box<dyn Error>> {
-        let embedding_model = {
+        let (embedding_model, store_config) = {
             let config = CONFIG.lock().unwrap();
-            config.ai.embedding_model.clone()
+            (
+                config.ai.embedding_model.clone(),
+                config.knowledge_store.clone(),
+            )
         };
         let embedding_service = EmbeddingServiceBuilder::new()
-            .model_name(embedding_model.into())
+            .model_name(Cow::Borrowed(&embedding_model))
             .build()?;
+        // The provider's model is recorded, not the name it is configured under
+        let embedding_model = embedding_service.model.clone();
 
         let db = match mode {
-            ConnectionMode::Persistent => {
-                Some(connect_to_db(embedding_service.inner.embedding_len()).await?)
-            }
+            ConnectionMode::Persistent => Some(connect_to_db(&store_config).await?),
             ConnectionMode::OnDemand => None,
         };
 
         Ok(KnowledgeStoreImpl {
+            embedding_model,
             embedding_service,
+            store_config,
             db,
         })
     }
 
-    async fn connect(&self, idx_len: usize) -> Result<Arc<Surreal<Db>>, Box<dyn Error>> {
-        connect_to_db(idx_len).await
+    async fn connect(&self) -> Result<Arc<Surreal<Any>>, Box<dyn Error>> {
+        match &self.db {
+            Some(db) => Ok(Arc::clone(db)),
+            None => connect_to_db(&self.store_config).await,
+        }
+    }
+
+    // Records the model of the first record and defines the vector index for its dimension
+    async fn record_embedding(
+        &self,
+        db_handle: &Surreal<Any>,
+        dimension: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        // Stores created before the model was recorded may hold vectors of another length
+        let mut results = db_handle
+            .query("SELECT array::len(embedding) AS dimension FROM context_embeddings LIMIT 1;")
+            .await?;
+        let raw: Vec<serde_json::Value> = results.take(0)?;
+        if let Some(stored) = raw.first().and_then(|value| value["dimension"].as_u64()) {
+            if stored as usize != dimension {
+                return Err(format!(
+                    "The knowledge store holds embeddings with {} dimensions, but the configured \
+                     embedding model '{}' returns {} dimensions. Run `rusty-buddy knowledge \
+                     reindex` to re-embed the knowledge with '{}'.",
+                    stored, self.embedding_model, dimension, self.embedding_model
+                )
+                .into());
+            }
+        }
+
+        info!(
+            "Recording embedding model {} with {} dimensions",
+            self.embedding_model, dimension
+        );
+        db_handle
+            .query(format!(
+                "DEFINE INDEX OVERWRITE idx_mtree_cosine ON context_embeddings FIELDS embedding MTREE DIMENSION {} DIST COSINE TYPE F32;",
+                dimension
+            ))
+            .await?
+            .check()?;
+        let info = EmbeddingInfo {
+            model: self.embedding_model.clone(),
+            dimension,
+        };
+        db_handle
+            .upsert::<Option<serde_json::Value>>(("knowledge_meta", "embedding"))
+            .content(serde_json::to_value(info)?)
+            .await?;
+        Ok(())
+    }
+}
+
+/// The fields of a record needed to describe its source.
+#[derive(Deserialize)]
+struct SourceRecord {
+    data_source: DataSource,
+    collection: String,
+    metadata: Option<KnowledgeMetadata>,
+}
+
+// Sums up the sizes of the files below `path`
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
     }
+    Ok(size)
 }
 
-async fn connect_to_db(idx_len: usize) -> Result<Arc<Surreal<Db>>, Box<dyn Error>> {
-    info!("Connecting to db");
-    let db = Surreal::new::<RocksDb>(get_knowledge_dir()?.to_str().unwrap()).await?;
-    db.use_ns("knowledge").use_db("knowledge_db").await?;
-    db.query(format!(
-        "DEFINE INDEX idx_mtree_cosine ON context_embeddings FIELDS embedding MTREE DIMENSION {} DIST COSINE TYPE F32;",
-        idx_len
-    ))
+async fn connect_to_db(config: &KnowledgeStoreConfig) -> Result<Arc<Surreal<Any>>, Box<dyn Error>> {
+    let db = match &config.url {
+        Some(url) => {
+            info!("Connecting to SurrealDB server {}", url);
+            let db = connect(url.as_str()).await?;
+            if let Some(username) = &config.username {
+                db.signin(Root {
+                    username: username.clone(),
+                    password: server_password(config)?,
+                })
+                .await?;
+            }
+            db
+        }
+        None => {
+            info!("Connecting to db");
+            connect(format!("rocksdb://{}", get_knowledge_dir()?.display())).await?
+        }
+    };
+    db.use_ns(config.namespace.as_deref().unwrap_or("knowledge"))
+        .use_db(config.database.as_deref().unwrap_or("knowledge_db"))
         .await?;
+    // The analyzer splits identifiers like `max_tokens`, `ChatService` or `E0308` into words
+    db.query(
+        "DEFINE ANALYZER IF NOT EXISTS knowledge_analyzer TOKENIZERS blank, class, camel FILTERS lowercase, ascii;",
+    )
+    .query(
+        "DEFINE INDEX IF NOT EXISTS idx_content_search ON context_embeddings FIELDS content FULLTEXT ANALYZER knowledge_analyzer BM25;",
+    )
+    .await?
+    .check()?;
     Ok(Arc::new(db))
 }
 
+// Reads the password of the server's user from the variable named by `password_env`
+fn server_password(config: &KnowledgeStoreConfig) -> Result<String, Box<dyn Error>> {
+    dotenv().ok();
+    let Some(variable) = &config.password_env else {
+        return Err(
+            "`password_env` must name the variable holding the password of the \
+             SurrealDB user."
+                .into(),
+        );
+    };
+    env::var(variable).map_err(|_| {
+        format!(
+            "The environment variable '{}' must be set to connect to the SurrealDB server.",
+            variable
+        )
+        .into()
+    })
+}
+
+// Records stored before collections existed have none and belong to the default collection
+fn collection_field() -> String {
+    format!("(collection ?? '{}')", DEFAULT_COLLECTION)
+}
+
+// Restricts a search to `collections`, unless all collections are searched
+fn collection_filter(collections: &[String]) -> String {
+    if collections.is_empty() {
+        String::new()
+    } else {
+        format!(" AND {} INSIDE $collections", collection_field())
+    }
+}
+
+// Queries the knowledge base for the closest embeddings (most relevant documents)
+async fn vector_search(
+    db_handle: &Surreal<Any>,
+    embedding: &[f32],
+    collections: &[String],
+    limit: usize,
+) -> Result<Vec<KnowledgeResult>, Box<dyn Error>> {
+    info!("Searching for knowledge for embedding");
+    let query = format!("SELECT \
+        data_source, {} AS collection, content, metadata, vector::similarity::cosine(embedding, $embedding) AS distance \
+        FROM context_embeddings \
+        WHERE embedding <|{}|> $embedding{} \
+        ORDER BY distance DESC;", collection_field(), limit, collection_filter(collections));
+    let results = match db_handle
+        .query(query)
+        .bind(("embedding", embedding.to_vec()))
+        .bind(("collections", collections.to_vec()))
+        .await
+    {
+        Ok(results) => {
+            info!("Successfully searched knowledge for embedding");
+            results
+        }
+        Err(e) => {
+            warn!("Failed to search for knowledge for embedding: {}", e);
+            return Err(Box::new(e));
+        }
+    };
+    let mut found = parse_results(results)?;
+    for result in &mut found {
+        result.score = result.distance;
+    }
+    Ok(found)
+}
+
+// Searches the content with the full-text index. With an embedding, the similarity of the hits
+// is computed as well.
+async fn keyword_search(
+    db_handle: &Surreal<Any>,
+    user_input: &str,
+    embedding: Option<&[f32]>,
+    collections: &[String],
+    limit: usize,
+) -> Result<Vec<KnowledgeResult>, Box<dyn Error>> {
+    info!("Searching for knowledge by keywords");
+    let distance = match embedding {
+        Some(_) => ", vector::similarity::cosine(embedding, $embedding) AS distance",
+        None => "",
+    };
+    let query = format!(
+        "SELECT data_source, {} AS collection, content, metadata, search::score(1) AS score{} \
+        FROM context_embeddings \
+        WHERE content @1@ $keywords{} \
+        ORDER BY score DESC LIMIT {};",
+        collection_field(),
+        distance,
+        collection_filter(collections),
+        limit
+    );
+    let mut request = db_handle
+        .query(query)
+        .bind(("keywords", user_input.to_string()))
+        .bind(("collections", collections.to_vec()));
+    if let Some(embedding) = embedding {
+        request = request.bind(("embedding", embedding.to_vec()));
+    }
+    let results = match request.await {
+        Ok(results) => results,
+        Err(e) => {
+            warn!("Failed to search for knowledge by keywords: {}", e);
+            return Err(Box::new(e));
+        }
+    };
+    parse_results(results)
+}
+
+fn parse_results(mut results: Response) -> Result<Vec<KnowledgeResult>, Box<dyn Error>> {
+    // Collect results as serde_json::Value (which implements SurrealValue) then deserialize
+    let raw: Vec<serde_json::Value> = results.take(0)?;
+    raw.into_iter()
+        .map(|v| serde_json::from_value(v).map_err(|e| Box::new(e) as Box<dyn Error>))
+        .collect()
+}
+
+// The records as values to insert, each with the id it is stored under
+fn record_values(records: Vec<EmbeddingData>) -> Result<serde_json::Value, serde_json::Error> {
+    records
+        .into_iter()
+        .map(|record| {
+            let id = record.record_id();
+            let mut value = serde_json::to_value(record)?;
+            value["id"] = id.into();
+            Ok(value)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(serde_json::Value::Array)
+}
+
+async fn read_embedding(db_handle: &Surreal<Any>) -> Result<Option<EmbeddingInfo>, Box<dyn Error>> {
+    let mut results = db_handle
+        .query("SELECT model, dimension FROM knowledge_meta:embedding;")
+        .await?;
+    let raw: Vec<serde_json::Value> = results.take(0)?;
+    match raw.into_iter().next() {
+        Some(value) => Ok(Some(serde_json::from_value(value)?)),
+        None => Ok(None),
+    }
+}
+
 #[async_trait]
 impl KnowledgeStore for KnowledgeStoreImpl {
     /// Implements the core logic for querying the knowledge database. It takes the user input, generates
     /// an embedding from it, and queries the database for relevant documents based on similarity.
+    /// Keyword searches use the full-text index instead and need no embedding.
     async fn query_knowledge(
         &self,
         user_input: Cow<'_, str>,
         limit: usize,
+        mode: SearchMode,
+        filter: &KnowledgeFilter,
     ) -> Result<Vec<KnowledgeResult>, Box<dyn Error>> {
+        let db_handle = self.connect().await?;
+        let collections = &filter.collections;
+        let candidates = filter.candidates(limit);
+        if mode == SearchMode::Keyword {
+            let found =
+                keyword_search(&db_handle, &user_input, None, collections, candidates).await?;
+            return Ok(filter.apply(found, limit));
+        }
+
         // Generate the embedding for the user input
         let embedding = self
             .embedding_service
             .inner
-            .get_embedding(user_input)
+            .get_embedding(user_input.clone())
             .await?;
-        info!("Searching for knowledge for embedding");
-        let db_handle = match &self.db {
-            Some(db) => db,
-            _ => {
-                &self
-                    .connect(self.embedding_service.inner.embedding_len())
-                    .await?
-            }
-        };
-        let query = format!("SELECT \
-        data_source, content, metadata, vector::similarity::cosine(embedding, $embedding) AS distance \
-        FROM context_embeddings \
-        WHERE embedding <|{}|> $embedding \
-        ORDER BY distance DESC;", limit);
-        // Query the knowledge base for the closest embeddings (most relevant documents)
-        let mut results = match db_handle.query(query).bind(("embedding", embedding)).await {
-            Ok(results) => {
-                info!("Successfully searched knowledge for embedding");
-                results
-            }
-            Err(e) => {
-                warn!("Failed to search for knowledge for embedding: {}", e);
-                return Err(Box::new(e));
-            }
-        };
-        // Collect results as serde_json::Value (which implements SurrealValue) then deserialize
-        let raw: Vec<serde_json::Value> = results.take(0)?;
-        let files: Vec<KnowledgeResult> = raw
-            .into_iter()
-            .map(|v| serde_json::from_value(v).map_err(|e| Box::new(e) as Box<dyn Error>))
-            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(recorded) = read_embedding(&db_handle).await? {
+            recorded.check(&self.embedding_model, embedding.len())?;
+        }
+        if mode == SearchMode::Vector {
+            let found = vector_search(&db_handle, &embedding, collections, candidates).await?;
+            return Ok(filter.apply(found, limit));
+        }
 
-        Ok(files)
+        let candidates = hybrid_candidates(filter, limit);
+        let vector = vector_search(&db_handle, &embedding, collections, candidates).await?;
+        let keyword = keyword_search(
+            &db_handle,
+            &user_input,
+            Some(&embedding),
+            collections,
+            candidates,
+        )
+        .await?;
+        Ok(fuse_hybrid(vector, keyword, filter, limit))
     }
 
     async fn store_knowledge(&self, knowledge: EmbeddingData) -> Result<(), Box<dyn Error>> {
         let data_source = knowledge.data_source.to_string();
         info!("Storing knowledge for: {}", data_source);
-        let db_handle = match &self.db {
-            Some(db) => db,
-            _ => {
-                &self
-                    .connect(self.embedding_service.inner.embedding_len())
+        let db_handle = self.connect().await?;
+        match read_embedding(&db_handle).await? {
+            Some(recorded) => recorded.check(&self.embedding_model, knowledge.embedding.len())?,
+            None => {
+                self.record_embedding(&db_handle, knowledge.embedding.len())
                     .await?
             }
-        };
+        }
 
         match db_handle
-            .upsert::<Option<serde_json::Value>>((
-                "context_embeddings",
-                knowledge.data_source.to_string(),
-            ))
+            .upsert::<Option<serde_json::Value>>(("context_embeddings", knowledge.record_id()))
             .content(serde_json::to_value(knowledge)?)
             .await
         {
@@ -139,7 +397,205 @@ impl KnowledgeStore for KnowledgeStoreImpl {
         Ok(())
     }
 
+    async fn remove_knowledge(
+        &self,
+        collection: &str,
+        data_source: &DataSource,
+    ) -> Result<(), Box<dyn Error>> {
+        info!("Removing knowledge for: {} in {}", data_source, collection);
+        let db_handle = self.connect().await?;
+
+        db_handle
+            .query(format!(
+                "DELETE context_embeddings WHERE data_source = $data_source AND {} = $collection;",
+                collection_field()
+            ))
+            .bind(("data_source", serde_json::to_value(data_source)?))
+            .bind(("collection", collection.to_string()))
+            .await?;
+        Ok(())
+    }
+
+    async fn replace_source(
+        &self,
+        collection: &str,
+        data_source: &DataSource,
+        records: Vec<EmbeddingData>,
+    ) -> Result<(), Box<dyn Error>> {
+        info!(
+            "Replacing knowledge for: {} in {} with {} records",
+            data_source,
+            collection,
+            records.len()
+        );
+        let db_handle = self.connect().await?;
+        if let Some(first) = records.first() {
+            match read_embedding(&db_handle).await? {
+                Some(recorded) => recorded.check(&self.embedding_model, first.embedding.len())?,
+                None => {
+                    self.record_embedding(&db_handle, first.embedding.len())
+                        .await?
+                }
+            }
+        }
+
+        // A single transaction, so a failure keeps the old records of the source
+        let mut statements = vec![
+            "BEGIN TRANSACTION;".to_string(),
+            format!(
+                "DELETE context_embeddings WHERE data_source = $data_source AND {} = $collection;",
+                collection_field()
+            ),
+        ];
+        if !records.is_empty() {
+            statements.push("INSERT INTO context_embeddings $records;".to_string());
+        }
+        statements.push("COMMIT TRANSACTION;".to_string());
+
+        db_handle
+            .query(statements.join("\n"))
+            .bind(("data_source", serde_json::to_value(data_source)?))
+            .bind(("collection", collection.to_string()))
+            .bind(("records", record_values(records)?))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    async fn list_sources(&self) -> Result<Vec<IndexedSource>, Box<dyn Error>> {
+        let db_handle = self.connect().await?;
+
+        let mut results = db_handle
+            .query(format!(
+                "SELECT data_source, {} AS collection, metadata FROM context_embeddings;",
+                collection_field()
+            ))
+            .await?;
+        let raw: Vec<serde_json::Value> = results.take(0)?;
+
+        // Every chunk is a record of its own, the sources are listed once
+        let mut sources: Vec<IndexedSource> = Vec::new();
+        let mut positions: HashMap<(String, DataSource), usize> = HashMap::new();
+        for value in raw {
+            let record: SourceRecord = serde_json::from_value(value)?;
+            let key = (record.collection, record.data_source);
+            match positions.get(&key) {
+                Some(&position) => sources[position].chunks += 1,
+                None => {
+                    let metadata = record.metadata.unwrap_or_default();
+                    positions.insert(key.clone(), sources.len());
+                    let (collection, data_source) = key;
+                    sources.push(IndexedSource {
+                        data_source,
+                        collection,
+                        content_hash: metadata.content_hash,
+                        modified: metadata.modified,
+                        chunks: 1,
+                    });
+                }
+            }
+        }
+        Ok(sources)
+    }
+
+    async fn stats(&self) -> Result<KnowledgeStats, Box<dyn Error>> {
+        let sources = self.list_sources().await?;
+        let embedding = self.recorded_embedding().await?;
+        let knowledge_dir = get_knowledge_dir()?;
+        // The size of a server's database is not known
+        let size_on_disk = if self.store_config.url.is_none() && knowledge_dir.exists() {
+            dir_size(&knowledge_dir)?
+        } else {
+            0
+        };
+        let mut collections: Vec<String> = sources
+            .iter()
+            .map(|source| source.collection.clone())
+            .collect();
+        collections.sort();
+        collections.dedup();
+        Ok(KnowledgeStats {
+            records: sources.iter().map(|source| source.chunks).sum(),
+            sources: sources.len(),
+            collections,
+            embedding,
+            configured_model: self.embedding_model.clone(),
+            size_on_disk,
+        })
+    }
+
+    async fn purge_knowledge(&self) -> Result<(), Box<dyn Error>> {
+        info!("Removing all knowledge");
+        let db_handle = self.connect().await?;
+
+        db_handle
+            .query("DELETE context_embeddings;")
+            .query("DELETE knowledge_meta;")
+            .query("REMOVE INDEX IF EXISTS idx_mtree_cosine ON context_embeddings;")
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    async fn replace_knowledge(&self, records: Vec<EmbeddingData>) -> Result<(), Box<dyn Error>> {
+        info!("Replacing all knowledge with {} records", records.len());
+        let embedding = EmbeddingInfo::of_records(&self.embedding_model, &records)?;
+
+        // A single transaction, so a failure keeps the old records and their index
+        let mut statements = vec![
+            "BEGIN TRANSACTION;".to_string(),
+            "DELETE context_embeddings;".to_string(),
+            "DELETE knowledge_meta;".to_string(),
+            "REMOVE INDEX IF EXISTS idx_mtree_cosine ON context_embeddings;".to_string(),
+        ];
+        if let Some(embedding) = &embedding {
+            statements.push(format!(
+                "DEFINE INDEX idx_mtree_cosine ON context_embeddings FIELDS embedding MTREE DIMENSION {} DIST COSINE TYPE F32;",
+                embedding.dimension
+            ));
+            statements.push("UPSERT knowledge_meta:embedding CONTENT $embedding;".to_string());
+            statements.push("INSERT INTO context_embeddings $records;".to_string());
+        }
+        statements.push("COMMIT TRANSACTION;".to_string());
+
+        let db_handle = self.connect().await?;
+        db_handle
+            .query(statements.join("\n"))
+            .bind(("embedding", serde_json::to_value(embedding)?))
+            .bind(("records", record_values(records)?))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    async fn list_records(&self) -> Result<Vec<EmbeddingData>, Box<dyn Error>> {
+        let db_handle = self.connect().await?;
+        let mut results = db_handle
+            .query(format!(
+                "SELECT data_source, {} AS collection, embedding, content, metadata FROM context_embeddings;",
+                collection_field()
+            ))
+            .await?;
+        let raw: Vec<serde_json::Value> = results.take(0)?;
+        raw.into_iter()
+            .map(|v| serde_json::from_value(v).map_err(|e| Box::new(e) as Box<dyn Error>))
+            .collect()
+    }
+
+    fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    async fn recorded_embedding(&self) -> Result<Option<EmbeddingInfo>, Box<dyn Error>> {
+        let db_handle = self.connect().await?;
+        read_embedding(&db_handle).await
+    }
+
     async fn get_embedding(&self, content: Cow<'_, str>) -> Result<Box<Vec<f32>>, Box<dyn Error>> {
         Ok(self.embedding_service.inner.get_embedding(content).await?)
     }
+
+    async fn get_embeddings(&self, contents: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        self.embedding_service.inner.get_embeddings(contents).await
+    }
 }
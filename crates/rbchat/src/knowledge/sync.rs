@@ -0,0 +1,262 @@
+//! This module keeps the knowledge store in step with the files it was built from.
+//!
+//! Instead of embedding every file again, `sync_knowledge` compares each file with the state
+//! stored when it was indexed:
+//!
+//! - Files with the stored modification time are skipped without being read.
+//! - Files whose content hash matches are skipped as well, only their time changed.
+//! - New and changed files are indexed, replacing the chunks of the old content.
+//! - Sources of local files that no longer exist are removed. Pages from the internet are
+//!   left alone.
+//!
+//! The numbers of added, updated, unchanged and removed sources are returned in a `SyncReport`.
+
+use crate::context::matching_files;
+use crate::extract::extract_file;
+use crate::knowledge::indexing::content_hash;
+use crate::knowledge::{index_document, Chunker, DataSource, IndexedSource, KnowledgeStore};
+use chrono::{DateTime, Utc};
+use log::warn;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// What syncing a single document did.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncOutcome {
+    Added,
+    Updated,
+    Unchanged,
+}
+
+/// Counts the sources changed by `sync_knowledge`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncReport {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    /// Files that could not be read or embedded.
+    pub failed: usize,
+}
+
+impl SyncReport {
+    pub fn count(&mut self, outcome: SyncOutcome) {
+        match outcome {
+            SyncOutcome::Added => self.added += 1,
+            SyncOutcome::Updated => self.updated += 1,
+            SyncOutcome::Unchanged => self.unchanged += 1,
+        }
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Added {}, updated {}, removed {}, unchanged {}",
+            self.added, self.updated, self.removed, self.unchanged
+        )?;
+        if self.failed > 0 {
+            write!(f, ", failed {}", self.failed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Indexes `content` as `data_source` in `collection` unless its hash equals the one stored in
+/// `indexed`.
+pub async fn sync_document(
+    store: &dyn KnowledgeStore,
+    chunker: &Chunker,
+    collection: &str,
+    indexed: Option<&IndexedSource>,
+    data_source: DataSource,
+    content: &str,
+    modified: Option<DateTime<Utc>>,
+) -> Result<SyncOutcome, Box<dyn Error>> {
+    match indexed {
+        Some(indexed)
+            if indexed.content_hash.as_deref() == Some(content_hash(content).as_str()) =>
+        {
+            return Ok(SyncOutcome::Unchanged)
+        }
+        // Empty documents have no chunks, so they are never stored
+        None if content.trim().is_empty() => return Ok(SyncOutcome::Unchanged),
+        _ => {}
+    }
+    index_document(store, chunker, collection, data_source, content, modified).await?;
+    Ok(match indexed {
+        Some(_) => SyncOutcome::Updated,
+        None => SyncOutcome::Added,
+    })
+}
+
+/// Like `sync_document`, but reads the document from `path` only if its modification time
+/// differs from the stored one.
+pub async fn sync_file(
+    store: &dyn KnowledgeStore,
+    chunker: &Chunker,
+    collection: &str,
+    indexed: Option<&IndexedSource>,
+    data_source: DataSource,
+    path: &Path,
+) -> Result<SyncOutcome, Box<dyn Error>> {
+    let modified = file_modified(path);
+    if let Some(indexed) = indexed {
+        if modified.is_some() && indexed.modified == modified {
+            return Ok(SyncOutcome::Unchanged);
+        }
+    }
+    let content = extract_file(path)?;
+    sync_document(
+        store,
+        chunker,
+        collection,
+        indexed,
+        data_source,
+        &content,
+        modified,
+    )
+    .await
+}
+
+/// Returns the modification time of the file at `path`, if it can be determined.
+pub fn file_modified(path: &Path) -> Option<DateTime<Utc>> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(DateTime::<Utc>::from)
+}
+
+/// Syncs the files below `directory` matching `file_types` as context sources of `collection`,
+/// re-checks the files added as local files to any collection and removes the sources of files
+/// that no longer exist.
+pub async fn sync_knowledge(
+    store: &dyn KnowledgeStore,
+    chunker: &Chunker,
+    collection: &str,
+    directory: &Path,
+    file_types: &[String],
+) -> Result<SyncReport, Box<dyn Error>> {
+    let sources = store.list_sources().await?;
+    let indexed: HashMap<(&str, &DataSource), &IndexedSource> = sources
+        .iter()
+        .map(|source| ((source.collection.as_str(), &source.data_source), source))
+        .collect();
+    let current_dir = std::env::current_dir()?;
+    let mut report = SyncReport::default();
+
+    let mut documents = Vec::new();
+    for path in matching_files(directory, file_types)? {
+        // Context sources are named relative to the current directory, like `knowledge init` does
+        let name = path.strip_prefix(&current_dir).unwrap_or(&path);
+        documents.push((
+            collection,
+            DataSource::Context(name.to_string_lossy().into_owned()),
+            path,
+        ));
+    }
+    for source in &sources {
+        if let DataSource::LocalFiles(name) = &source.data_source {
+            let path = Path::new(name);
+            if path.is_file() {
+                documents.push((
+                    source.collection.as_str(),
+                    source.data_source.clone(),
+                    path.to_path_buf(),
+                ));
+            }
+        }
+    }
+
+    for (collection, data_source, path) in documents {
+        let previous = indexed.get(&(collection, &data_source)).copied();
+        match sync_file(store, chunker, collection, previous, data_source, &path).await {
+            Ok(outcome) => report.count(outcome),
+            Err(e) => {
+                warn!("Failed to sync '{}': {}", path.display(), e);
+                report.failed += 1;
+            }
+        }
+    }
+
+    report.removed = remove_missing_files(store, &sources).await?;
+    Ok(report)
+}
+
+/// Removes the sources of context and local files that no longer exist and returns their number.
+pub async fn remove_missing_files(
+    store: &dyn KnowledgeStore,
+    sources: &[IndexedSource],
+) -> Result<usize, Box<dyn Error>> {
+    let mut removed = 0;
+    for source in sources {
+        let is_missing = match &source.data_source {
+            DataSource::Context(name) | DataSource::LocalFiles(name) => !Path::new(name).exists(),
+            DataSource::Internet(_) => false,
+        };
+        if is_missing {
+            store
+                .remove_knowledge(&source.collection, &source.data_source)
+                .await?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::memory_store::MemoryStore;
+    use std::time::{Duration, SystemTime};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_sync_only_embeds_changed_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(dir.path().join("b.rs"), "fn b() {}\n").unwrap();
+        let store = MemoryStore::default();
+        let chunker = Chunker::default();
+        let file_types = vec!["rs".to_string()];
+
+        let report = sync_knowledge(&store, &chunker, "code", dir.path(), &file_types)
+            .await
+            .unwrap();
+        assert_eq!((report.added, report.updated, report.removed), (2, 0, 0));
+        assert_eq!(store.embedded(), 2);
+
+        let report = sync_knowledge(&store, &chunker, "code", dir.path(), &file_types)
+            .await
+            .unwrap();
+        assert_eq!(report.unchanged, 2);
+        assert_eq!(store.embedded(), 2);
+
+        fs::write(dir.path().join("a.rs"), "fn a() { changed(); }\n").unwrap();
+        // File times may be too coarse to tell the writes apart
+        fs::File::options()
+            .write(true)
+            .open(dir.path().join("a.rs"))
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        fs::remove_file(dir.path().join("b.rs")).unwrap();
+        let report = sync_knowledge(&store, &chunker, "code", dir.path(), &file_types)
+            .await
+            .unwrap();
+
+        assert_eq!((report.added, report.updated, report.removed), (0, 1, 1));
+        assert_eq!(store.embedded(), 3);
+        let sources = store.list_sources().await.unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].collection, "code");
+        assert_eq!(
+            sources[0].content_hash,
+            Some(content_hash("fn a() { changed(); }\n"))
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! This module keeps the embedding requests within the limits of the provider.
+//!
+//! `ThrottledEmbeddingService` sends at most `batch_size` texts with one request, spaces the
+//! requests so no more than `requests_per_minute` are sent, and repeats a failed request up to
+//! `max_retries` times, pausing twice as long before every attempt, up to a minute. The settings are read from
+//! the `embedding` section of the configuration.
+
+use crate::config::EmbeddingConfig;
+use crate::knowledge::EmbeddingService;
+use async_trait::async_trait;
+use log::warn;
+use std::borrow::Cow;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, sleep_until, Instant};
+
+/// Pause before the first retry of a failed request.
+const RETRY_PAUSE: Duration = Duration::from_secs(1);
+
+/// Longest pause between two attempts, however many retries are configured.
+const MAX_RETRY_PAUSE: Duration = Duration::from_secs(60);
+
+/// An `EmbeddingService` sending the requests of the service it wraps in batches, within the
+/// rate limit and with retries.
+pub(crate) struct ThrottledEmbeddingService {
+    inner: Arc<dyn EmbeddingService>,
+    batch_size: usize,
+    /// The time between two requests, if their rate is limited.
+    interval: Option<Duration>,
+    max_retries: u32,
+    retry_pause: Duration,
+    next_request: Mutex<Instant>,
+}
+
+impl ThrottledEmbeddingService {
+    pub fn new(inner: Arc<dyn EmbeddingService>, config: &EmbeddingConfig) -> Self {
+        ThrottledEmbeddingService {
+            inner,
+            batch_size: config.batch_size.max(1),
+            interval: config
+                .requests_per_minute
+                .filter(|requests| *requests > 0)
+                .map(|requests| Duration::from_secs(60) / requests),
+            max_retries: config.max_retries,
+            retry_pause: RETRY_PAUSE,
+            next_request: Mutex::new(Instant::now()),
+        }
+    }
+
+    // Waits until the rate limit allows the next request. Waiting requests take turns.
+    async fn wait_for_turn(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let mut next_request = self.next_request.lock().await;
+        sleep_until(*next_request).await;
+        *next_request = Instant::now() + interval;
+    }
+
+    // The pause before retrying after `attempt` failed, doubling with every attempt
+    fn retry_pause(&self, attempt: u32) -> Duration {
+        self.retry_pause
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_RETRY_PAUSE)
+    }
+
+    // Sends one request for `batch`, repeating it if it fails
+    async fn request(&self, batch: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        let mut attempt = 0;
+        loop {
+            self.wait_for_turn().await;
+            // Errors are not `Send`, only their message is kept across the pause
+            let error = match self.inner.get_embeddings(batch).await {
+                Ok(embeddings) if embeddings.len() == batch.len() => return Ok(embeddings),
+                Ok(embeddings) => format!(
+                    "The embedding service returned {} embeddings for {} texts",
+                    embeddings.len(),
+                    batch.len()
+                ),
+                Err(e) => e.to_string(),
+            };
+            if attempt >= self.max_retries {
+                return Err(error.into());
+            }
+            let pause = self.retry_pause(attempt);
+            warn!(
+                "Embedding request failed, retrying in {:?}: {}",
+                pause, error
+            );
+            sleep(pause).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingService for ThrottledEmbeddingService {
+    async fn get_embedding(&self, content: Cow<'_, str>) -> Result<Box<Vec<f32>>, Box<dyn Error>> {
+        let mut embeddings = self.request(&[content.into_owned()]).await?;
+        Ok(Box::new(embeddings.remove(0)))
+    }
+
+    async fn get_embeddings(&self, contents: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        let mut embeddings = Vec::with_capacity(contents.len());
+        for batch in contents.chunks(self.batch_size) {
+            embeddings.extend(self.request(batch).await?);
+        }
+        Ok(embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+
+    // Records the size of every request and fails the first `failures` of them
+    struct FlakyService {
+        requests: StdMutex<Vec<usize>>,
+        failures: StdMutex<usize>,
+    }
+
+    #[async_trait]
+    impl EmbeddingService for FlakyService {
+        async fn get_embedding(
+            &self,
+            content: Cow<'_, str>,
+        ) -> Result<Box<Vec<f32>>, Box<dyn Error>> {
+            let mut embeddings = self.get_embeddings(&[content.into_owned()]).await?;
+            Ok(Box::new(embeddings.remove(0)))
+        }
+
+        async fn get_embeddings(
+            &self,
+            contents: &[String],
+        ) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+            self.requests.lock().unwrap().push(contents.len());
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err("429 Too Many Requests".into());
+            }
+            Ok(contents
+                .iter()
+                .map(|content| vec![content.len() as f32])
+                .collect())
+        }
+    }
+
+    fn throttled(
+        failures: usize,
+        max_retries: u32,
+    ) -> (Arc<FlakyService>, ThrottledEmbeddingService) {
+        let flaky = Arc::new(FlakyService {
+            requests: StdMutex::new(vec![]),
+            failures: StdMutex::new(failures),
+        });
+        let config = EmbeddingConfig {
+            batch_size: 2,
+            max_retries,
+            ..EmbeddingConfig::default()
+        };
+        let mut service = ThrottledEmbeddingService::new(flaky.clone(), &config);
+        service.retry_pause = Duration::from_millis(1);
+        (flaky, service)
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_batched_and_retried() {
+        let (flaky, service) = throttled(1, 3);
+        let texts: Vec<String> = ["a", "bb", "ccc", "dddd", "eeeee"]
+            .iter()
+            .map(|text| text.to_string())
+            .collect();
+
+        let embeddings = service.get_embeddings(&texts).await.unwrap();
+
+        assert_eq!(
+            embeddings,
+            vec![vec![1.0], vec![2.0], vec![3.0], vec![4.0], vec![5.0]]
+        );
+        // The first request failed once
+        assert_eq!(*flaky.requests.lock().unwrap(), vec![2, 2, 2, 1]);
+
+        let (_, service) = throttled(2, 1);
+        let error = service.get_embeddings(&texts).await.unwrap_err();
+        assert_eq!(error.to_string(), "429 Too Many Requests");
+    }
+
+    #[test]
+    fn test_retry_pause_is_capped() {
+        let (_, mut service) = throttled(0, 100);
+        service.retry_pause = RETRY_PAUSE;
+
+        assert_eq!(service.retry_pause(0), RETRY_PAUSE);
+        assert_eq!(service.retry_pause(3), RETRY_PAUSE * 8);
+        assert_eq!(service.retry_pause(40), MAX_RETRY_PAUSE);
+        assert_eq!(service.retry_pause(u32::MAX), MAX_RETRY_PAUSE);
+    }
+}
@@ -1,7 +1,9 @@
 pub mod chat;
 pub mod config;
 pub mod context;
+pub mod extract;
 pub mod image;
 pub mod knowledge;
 pub mod persona;
 pub mod provider;
+pub mod tools;
@@ -0,0 +1,910 @@
+//! This module provides an interface for communicating with the Anthropic Messages API.
+//! It encapsulates the functionality required to send messages to Claude models,
+//! receive responses, and keep track of the token usage reported by the API.
+//!
+//! The `AnthropicInterface` struct acts as a bridge between the chat service and the
+//! Messages API. The API expects a single top-level system prompt and alternating
+//! user/assistant turns, so the conversion combines all system, context and knowledge
+//! messages into the system prompt and merges consecutive turns of the same role.
+//!
+//! ## Fields
+//!
+//! - `model`: The Claude model used for generating chat messages.
+//! - `base_url`: The endpoint of the API, `https://api.anthropic.com` unless the model configures a `url`.
+//! - `timeout_duration`: A duration that represents the timeout for API requests.
+//! - `last_call_completion_token`, `last_call_prompt_token`: Track token usage for
+//!   the last API call.
+//! - `overall_completion_token`, `overall_prompt_token`: Cumulative token usage metrics.
+//!
+//! ## Error Handling
+//!
+//! All methods return a `Result`, which will contain an error of type `Box<dyn Error>` on failure.
+//! Error responses of the API are turned into errors carrying the HTTP status and the response body.
+
+use crate::chat::interface::{ChatBackend, Message, MessageInfo, MessageRole, ToolCall};
+use crate::config::Model;
+use crate::tools::ToolDefinition;
+use async_trait::async_trait;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use dotenvy::dotenv;
+use futures::StreamExt;
+use log::{debug, error, info, trace, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::timeout;
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const DEFAULT_API_KEY_ENV: &str = "ANTHROPIC_API_KEY";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+/// AnthropicInterface provides a wrapper around the Anthropic Messages API for sending chat requests.
+/// It implements the ChatBackend trait, allowing it to be integrated into a chat application.
+#[derive(Clone)]
+pub struct AnthropicInterface {
+    model: String,
+    base_url: String,
+    api_key: String,
+    max_tokens: u32,
+    headers: HashMap<String, String>,
+    timeout_duration: Duration,
+    last_call_completion_token: u32,
+    last_call_prompt_token: u32,
+    overall_completion_token: u32,
+    overall_prompt_token: u32,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize, Debug)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool<'a>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct AnthropicTool<'a> {
+    name: &'a str,
+    description: &'a str,
+    input_schema: &'a Value,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct AnthropicMessage {
+    role: &'static str,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: ImageSource,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ImageSource {
+    #[serde(rename = "type")]
+    source_type: &'static str,
+    media_type: &'static str,
+    data: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct MessagesResponse {
+    content: Vec<ResponseBlock>,
+    usage: Usage,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ResponseBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Usage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart {
+        message: StreamMessage,
+    },
+    ContentBlockStart {
+        content_block: ResponseBlock,
+    },
+    ContentBlockDelta {
+        delta: StreamDelta,
+    },
+    MessageDelta {
+        usage: Usage,
+    },
+    Error {
+        error: ApiError,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamMessage {
+    usage: Usage,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamDelta {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    partial_json: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiError {
+    message: String,
+}
+
+#[async_trait]
+impl ChatBackend for AnthropicInterface {
+    async fn send_request(
+        &mut self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+    ) -> Result<Message, Box<dyn Error>> {
+        let request = self.create_request(messages, tools, false)?;
+        info!(
+            "Sending request to Anthropic with model '{}' and timeout {:?}.",
+            self.model, self.timeout_duration
+        );
+        let response = self.post_messages(&request).await?;
+
+        let response: MessagesResponse = match timeout(self.timeout_duration, response.json()).await
+        {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => {
+                error!("Error while reading Anthropic response: {:#?}", e);
+                return Err(Box::new(e));
+            }
+            Err(e) => {
+                error!("Timeout error while reading Anthropic response: {:#?}", e);
+                return Err(Box::new(e));
+            }
+        };
+        info!("Received a successful response from Anthropic.");
+
+        self.update_statistics(response.usage.input_tokens, response.usage.output_tokens);
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in response.content {
+            match block {
+                ResponseBlock::Text { text } => content.push_str(text.as_str()),
+                ResponseBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                    id,
+                    name,
+                    arguments: input.to_string(),
+                }),
+                ResponseBlock::Other => {}
+            }
+        }
+        Ok(self.create_response_message(content, tool_calls))
+    }
+
+    async fn send_request_stream(
+        &mut self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        on_delta: &mut (dyn for<'a> FnMut(&'a str) + Send),
+    ) -> Result<Message, Box<dyn Error>> {
+        let request = self.create_request(messages, tools, true)?;
+        info!(
+            "Sending streaming request to Anthropic with model '{}' and timeout {:?}.",
+            self.model, self.timeout_duration
+        );
+        let response = self.post_messages(&request).await?;
+
+        let mut stream = response.bytes_stream();
+        // Kept as bytes, a character may be split between two chunks
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut content = String::new();
+        let mut tool_calls: Vec<ToolCall> = Vec::new();
+        let mut usage = Usage::default();
+        // The timeout applies to the gap between two chunks, so long answers are not cut off.
+        loop {
+            let chunk = match timeout(self.timeout_duration, stream.next()).await {
+                Ok(Some(Ok(chunk))) => chunk,
+                Ok(Some(Err(e))) => {
+                    error!("Error while receiving stream from Anthropic: {:#?}", e);
+                    return Err(Box::new(e));
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Timeout error while waiting for Anthropic chunk: {:#?}", e);
+                    return Err(Box::new(e));
+                }
+            };
+            buffer.extend_from_slice(&chunk);
+
+            for data in take_sse_events(&mut buffer) {
+                trace!("Received Anthropic event: {}", data);
+                match serde_json::from_str::<StreamEvent>(&data)? {
+                    StreamEvent::MessageStart { message } => {
+                        usage.input_tokens = message.usage.input_tokens;
+                    }
+                    StreamEvent::ContentBlockStart {
+                        content_block: ResponseBlock::ToolUse { id, name, .. },
+                    } => {
+                        // The input follows as partial JSON in the deltas of this block
+                        tool_calls.push(ToolCall {
+                            id,
+                            name,
+                            arguments: String::new(),
+                        });
+                    }
+                    StreamEvent::ContentBlockStart { .. } => {}
+                    StreamEvent::ContentBlockDelta { delta } => {
+                        if let Some(text) = delta.text {
+                            on_delta(text.as_str());
+                            content.push_str(text.as_str());
+                        }
+                        if let (Some(partial_json), Some(tool_call)) =
+                            (delta.partial_json, tool_calls.last_mut())
+                        {
+                            tool_call.arguments.push_str(partial_json.as_str());
+                        }
+                    }
+                    StreamEvent::MessageDelta { usage: delta_usage } => {
+                        usage.output_tokens = delta_usage.output_tokens;
+                    }
+                    StreamEvent::Error { error } => {
+                        error!("Anthropic reported an error: {}", error.message);
+                        return Err(error.message.into());
+                    }
+                    StreamEvent::Other => {}
+                }
+            }
+        }
+
+        info!("Streaming request completed successfully.");
+        self.update_statistics(usage.input_tokens, usage.output_tokens);
+        Ok(self.create_response_message(content, tool_calls))
+    }
+
+    fn print_statistics(&self) {
+        println!(
+            "Last Call Completion Tokens: {}, Last Call Prompt Tokens: {}, Overall Completion Tokens: {}, Overall Prompt Tokens: {}",
+            self.last_call_completion_token,
+            self.last_call_prompt_token,
+            self.overall_completion_token,
+            self.overall_prompt_token
+        );
+    }
+}
+
+impl AnthropicInterface {
+    /// Creates the interface for a configured model. The key is read from the variable named
+    /// by `api_key_env` (default `ANTHROPIC_API_KEY`), `url` replaces the default endpoint
+    /// `https://api.anthropic.com` and `headers` are sent with every request.
+    pub fn from_model(model: &Model, timeout_secs: u64) -> Result<Self, Box<dyn Error>> {
+        dotenv().ok();
+        let key_variable = model.api_key_env.as_deref().unwrap_or(DEFAULT_API_KEY_ENV);
+        let api_key = env::var(key_variable).map_err(|_| {
+            format!(
+                "The environment variable '{}' must be set for model '{}'.",
+                key_variable, model.name
+            )
+        })?;
+        let mut interface = Self::with_api_key(
+            model.api_name.clone(),
+            model.url.clone(),
+            api_key,
+            timeout_secs,
+        );
+        interface.headers = model.headers.clone();
+        Ok(interface)
+    }
+
+    pub fn with_api_key(
+        model: String,
+        url: Option<String>,
+        api_key: String,
+        timeout_secs: u64,
+    ) -> Self {
+        let base_url = url.unwrap_or(DEFAULT_BASE_URL.into());
+        info!(
+            "Creating Anthropic interface with model: {} and URL: {}",
+            model, base_url
+        );
+        AnthropicInterface {
+            model,
+            base_url,
+            api_key,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            headers: HashMap::new(),
+            timeout_duration: Duration::from_secs(timeout_secs),
+            last_call_completion_token: 0,
+            last_call_prompt_token: 0,
+            overall_completion_token: 0,
+            overall_prompt_token: 0,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn create_request<'a>(
+        &'a self,
+        messages: &[Message],
+        tools: &'a [ToolDefinition],
+        stream: bool,
+    ) -> Result<MessagesRequest<'a>, Box<dyn Error>> {
+        let (system, messages) = Self::convert_messages(messages)?;
+        debug!("Converted messages for Anthropic: {:?}", messages);
+        let tools = tools
+            .iter()
+            .map(|tool| AnthropicTool {
+                name: tool.name.as_str(),
+                description: tool.description.as_str(),
+                input_schema: &tool.parameters,
+            })
+            .collect();
+        Ok(MessagesRequest {
+            model: self.model.as_str(),
+            max_tokens: self.max_tokens,
+            system,
+            messages,
+            tools,
+            stream,
+        })
+    }
+
+    async fn post_messages(
+        &self,
+        request: &MessagesRequest<'_>,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let url = format!("{}/v1/messages", self.base_url.trim_end_matches('/'));
+        let mut builder = self
+            .client
+            .post(url)
+            .header("x-api-key", self.api_key.as_str())
+            .header("anthropic-version", ANTHROPIC_VERSION);
+        for (name, value) in &self.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let send = builder.json(request).send();
+
+        let response = match timeout(self.timeout_duration, send).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => {
+                error!("Error while sending request to Anthropic: {:#?}", e);
+                return Err(Box::new(e));
+            }
+            Err(e) => {
+                error!(
+                    "Timeout error while waiting for Anthropic response: {:#?}",
+                    e
+                );
+                return Err(Box::new(e));
+            }
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            error!("Anthropic returned {}: {}", status, body);
+            return Err(format!("Anthropic API error ({}): {}", status, body).into());
+        }
+        Ok(response)
+    }
+
+    /// Splits the messages into the system prompt and the conversation turns.
+    fn convert_messages(
+        messages: &[Message],
+    ) -> Result<(Option<String>, Vec<AnthropicMessage>), Box<dyn Error>> {
+        let mut system_parts: Vec<&str> = Vec::new();
+        let mut converted: Vec<AnthropicMessage> = Vec::new();
+
+        for msg in messages {
+            let (role, content) = match msg.role {
+                MessageRole::System | MessageRole::Context | MessageRole::Knowledge => {
+                    system_parts.push(msg.content.as_str());
+                    continue;
+                }
+                MessageRole::User => ("user", Self::create_user_content(msg)?),
+                MessageRole::Assistant => ("assistant", Self::create_text_content(msg)),
+                MessageRole::ToolCall => ("assistant", Self::create_tool_use_content(msg)),
+                // Tool results are sent back as part of a user turn
+                MessageRole::ToolResult => ("user", Self::create_tool_result_content(msg)),
+            };
+            if content.is_empty() {
+                continue;
+            }
+            // The API expects alternating roles, so consecutive turns of one role are merged
+            match converted.last_mut() {
+                Some(last) if last.role == role => last.content.extend(content),
+                _ => converted.push(AnthropicMessage { role, content }),
+            }
+        }
+
+        let system = if system_parts.is_empty() {
+            None
+        } else {
+            Some(system_parts.join("\n\n"))
+        };
+        Ok((system, converted))
+    }
+
+    fn create_text_content(msg: &Message) -> Vec<ContentBlock> {
+        if msg.content.is_empty() {
+            vec![]
+        } else {
+            vec![ContentBlock::Text {
+                text: msg.content.clone(),
+            }]
+        }
+    }
+
+    fn create_tool_use_content(msg: &Message) -> Vec<ContentBlock> {
+        let mut content = Self::create_text_content(msg);
+        if let Some(MessageInfo::ToolCallInfo { tool_calls }) = &msg.info {
+            content.extend(tool_calls.iter().map(|tool_call| ContentBlock::ToolUse {
+                id: tool_call.id.clone(),
+                name: tool_call.name.clone(),
+                input: Self::tool_use_input(tool_call),
+            }));
+        }
+        content
+    }
+
+    // The Messages API requires the input of a tool use to be an object, so missing or invalid
+    // arguments are sent as `{}`
+    fn tool_use_input(tool_call: &ToolCall) -> Value {
+        if tool_call.arguments.trim().is_empty() {
+            return json!({});
+        }
+        match serde_json::from_str::<Value>(&tool_call.arguments) {
+            Ok(input) if input.is_object() => input,
+            _ => {
+                warn!(
+                    "Invalid arguments for tool '{}', sending none: {}",
+                    tool_call.name, tool_call.arguments
+                );
+                json!({})
+            }
+        }
+    }
+
+    fn create_tool_result_content(msg: &Message) -> Vec<ContentBlock> {
+        match &msg.info {
+            Some(MessageInfo::ToolResultInfo { tool_call_id, .. }) => {
+                vec![ContentBlock::ToolResult {
+                    tool_use_id: tool_call_id.clone(),
+                    content: msg.content.clone(),
+                }]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn create_user_content(msg: &Message) -> Result<Vec<ContentBlock>, Box<dyn Error>> {
+        let mut content = Vec::new();
+        if let Some(MessageInfo::UserInfo {
+            image_path: Some(image_path),
+            ..
+        }) = &msg.info
+        {
+            info!("Adding image to Anthropic user message from {}", image_path);
+            let image_data = std::fs::read(image_path)?;
+            content.push(ContentBlock::Image {
+                source: ImageSource {
+                    source_type: "base64",
+                    media_type: image_media_type(image_path),
+                    data: BASE64_STANDARD.encode(image_data),
+                },
+            });
+        }
+        content.extend(Self::create_text_content(msg));
+        Ok(content)
+    }
+
+    fn create_response_message(&self, content: String, tool_calls: Vec<ToolCall>) -> Message {
+        if !tool_calls.is_empty() {
+            return Message {
+                role: MessageRole::ToolCall,
+                content,
+                info: Some(MessageInfo::ToolCallInfo { tool_calls }),
+            };
+        }
+        Message {
+            role: MessageRole::Assistant,
+            content,
+            info: Some(MessageInfo::AssistantInfo {
+                model: self.model.clone(),
+                persona_name: String::new(),
+                prompt_token: self.last_call_prompt_token,
+                completion_token: self.last_call_completion_token,
+                timestamp: Utc::now(),
+            }),
+        }
+    }
+
+    fn update_statistics(&mut self, prompt_token: u32, completion_token: u32) {
+        self.last_call_completion_token = completion_token;
+        self.last_call_prompt_token = prompt_token;
+        self.overall_completion_token += self.last_call_completion_token;
+        self.overall_prompt_token += self.last_call_prompt_token;
+        info!("Updated token statistics: Last call completion tokens: {}, Last call prompt tokens: {}", self.last_call_completion_token, self.last_call_prompt_token);
+    }
+}
+
+fn image_media_type(image_path: &str) -> &'static str {
+    let extension = Path::new(image_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "image/png",
+    }
+}
+
+/// Removes all complete server-sent events from `buffer` and returns their data payloads.
+/// Only complete events are decoded, so characters split between chunks stay intact.
+fn take_sse_events(buffer: &mut Vec<u8>) -> Vec<String> {
+    // A carriage return byte never occurs inside a multi-byte character
+    buffer.retain(|byte| *byte != b'\r');
+    let mut events = Vec::new();
+    while let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+        let event: Vec<u8> = buffer.drain(..end + 2).collect();
+        let data = String::from_utf8_lossy(&event)
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(str::trim_start)
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !data.is_empty() {
+            events.push(data);
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::task::JoinHandle;
+
+    // Starts a stand-in for the Messages API that answers a single request with `body`
+    // and hands the received request back through the join handle.
+    async fn serve_once(
+        body: &'static str,
+        content_type: &'static str,
+    ) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut socket).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                content_type,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+        (url, handle)
+    }
+
+    async fn read_request(socket: &mut TcpStream) -> String {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            data.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&data).to_string();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .find_map(|l| {
+                        l.to_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                if data.len() >= header_end + 4 + content_length {
+                    return text;
+                }
+            }
+        }
+    }
+
+    fn request_body(request: &str) -> Value {
+        let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+        serde_json::from_str(body).unwrap()
+    }
+
+    fn messages() -> Vec<Message> {
+        vec![
+            Message {
+                role: MessageRole::System,
+                content: "You are a helpful assistant.".to_string(),
+                info: None,
+            },
+            Message {
+                role: MessageRole::Context,
+                content: "Filename: main.rs".to_string(),
+                info: None,
+            },
+            Message {
+                role: MessageRole::Knowledge,
+                content: "Some knowledge".to_string(),
+                info: None,
+            },
+            Message {
+                role: MessageRole::User,
+                content: "Hello".to_string(),
+                info: None,
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_send_request() {
+        let (url, server) = serve_once(
+            r#"{"id":"msg_1","type":"message","role":"assistant","content":[{"type":"text","text":"Hi there"}],"usage":{"input_tokens":12,"output_tokens":3}}"#,
+            "application/json",
+        )
+        .await;
+        let mut backend = AnthropicInterface::with_api_key(
+            "claude-test".to_string(),
+            Some(url),
+            "test-key".to_string(),
+            10,
+        );
+
+        let response = backend.send_request(&messages(), &[]).await.unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(response.content, "Hi there");
+        assert!(matches!(
+            response.info,
+            Some(MessageInfo::AssistantInfo {
+                prompt_token: 12,
+                completion_token: 3,
+                ..
+            })
+        ));
+        assert!(request.starts_with("POST /v1/messages"));
+        assert!(request.contains("x-api-key: test-key"));
+        let body = request_body(&request);
+        assert_eq!(
+            body["system"],
+            "You are a helpful assistant.\n\nFilename: main.rs\n\nSome knowledge"
+        );
+        assert_eq!(body["model"], "claude-test");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert_eq!(body["messages"][0]["content"][0]["text"], "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_send_request_stream() {
+        let (url, server) = serve_once(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":20,\"output_tokens\":1}}}\n\n\
+             event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n\
+             event: ping\ndata: {\"type\":\"ping\"}\n\n\
+             event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" World\"}}\n\n\
+             event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":5}}\n\n\
+             event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+            "text/event-stream",
+        )
+        .await;
+        let mut backend = AnthropicInterface::with_api_key(
+            "claude-test".to_string(),
+            Some(url),
+            "test-key".to_string(),
+            10,
+        );
+
+        let mut deltas = Vec::new();
+        let response = backend
+            .send_request_stream(&messages(), &[], &mut |delta| {
+                deltas.push(delta.to_string())
+            })
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(deltas, vec!["Hello", " World"]);
+        assert_eq!(response.content, "Hello World");
+        assert!(matches!(
+            response.info,
+            Some(MessageInfo::AssistantInfo {
+                prompt_token: 20,
+                completion_token: 5,
+                ..
+            })
+        ));
+        assert_eq!(request_body(&request)["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn test_send_request_with_tool_use() {
+        let (url, server) = serve_once(
+            r#"{"content":[{"type":"text","text":"Let me check."},{"type":"tool_use","id":"toolu_1","name":"create_file","input":{"file_path":"a.txt","content":"A"}}],"usage":{"input_tokens":30,"output_tokens":10}}"#,
+            "application/json",
+        )
+        .await;
+        let mut backend = AnthropicInterface::with_api_key(
+            "claude-test".to_string(),
+            Some(url),
+            "test-key".to_string(),
+            10,
+        );
+        let tools = vec![ToolDefinition {
+            name: "create_file".to_string(),
+            description: "Creates a file.".to_string(),
+            parameters: serde_json::json!({ "type": "object" }),
+        }];
+
+        let response = backend.send_request(&messages(), &tools).await.unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(response.role, MessageRole::ToolCall);
+        assert_eq!(response.content, "Let me check.");
+        match response.info {
+            Some(MessageInfo::ToolCallInfo { tool_calls }) => {
+                assert_eq!(tool_calls.len(), 1);
+                assert_eq!(tool_calls[0].id, "toolu_1");
+                assert_eq!(tool_calls[0].name, "create_file");
+                let arguments: Value = serde_json::from_str(&tool_calls[0].arguments).unwrap();
+                assert_eq!(arguments["file_path"], "a.txt");
+            }
+            other => panic!("Expected tool call info, got {:?}", other),
+        }
+        let body = request_body(&request);
+        assert_eq!(body["tools"][0]["name"], "create_file");
+        assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+    }
+
+    #[test]
+    fn test_take_sse_events_keeps_split_characters() {
+        let event = "data: {\"text\":\"Grüße\"}\r\n\r\n".as_bytes();
+        // Split inside the two bytes of 'ü'
+        let split = event.iter().position(|byte| *byte == 0xC3).unwrap() + 1;
+        let mut buffer = event[..split].to_vec();
+
+        assert!(take_sse_events(&mut buffer).is_empty());
+        buffer.extend_from_slice(&event[split..]);
+        assert_eq!(take_sse_events(&mut buffer), vec!["{\"text\":\"Grüße\"}"]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_convert_messages_with_image() {
+        let mut image = tempfile::Builder::new().suffix(".jpg").tempfile().unwrap();
+        image.write_all(b"image").unwrap();
+        let messages = vec![
+            Message {
+                role: MessageRole::User,
+                content: "What is this?".to_string(),
+                info: Some(MessageInfo::UserInfo {
+                    timestamp: Utc::now(),
+                    image_path: Some(image.path().to_string_lossy().to_string()),
+                }),
+            },
+            Message {
+                role: MessageRole::User,
+                content: "Please be brief.".to_string(),
+                info: None,
+            },
+        ];
+
+        let (system, converted) = AnthropicInterface::convert_messages(&messages).unwrap();
+
+        assert_eq!(system, None);
+        assert_eq!(converted.len(), 1);
+        assert_eq!(
+            converted[0].content,
+            vec![
+                ContentBlock::Image {
+                    source: ImageSource {
+                        source_type: "base64",
+                        media_type: "image/jpeg",
+                        data: BASE64_STANDARD.encode("image"),
+                    },
+                },
+                ContentBlock::Text {
+                    text: "What is this?".to_string()
+                },
+                ContentBlock::Text {
+                    text: "Please be brief.".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_tool_arguments_are_sent_as_empty_input() {
+        let tool_call = |arguments: &str| ToolCall {
+            id: "toolu_1".to_string(),
+            name: "create_file".to_string(),
+            arguments: arguments.to_string(),
+        };
+        let message = Message {
+            role: MessageRole::Assistant,
+            content: String::new(),
+            info: Some(MessageInfo::ToolCallInfo {
+                tool_calls: vec![
+                    tool_call(r#"{"file_path":"a.txt"}"#),
+                    tool_call(r#"{"file_path":"#),
+                    tool_call(""),
+                    tool_call("null"),
+                ],
+            }),
+        };
+
+        let inputs: Vec<Value> = AnthropicInterface::create_tool_use_content(&message)
+            .into_iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse { input, .. } => Some(input),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            inputs,
+            vec![
+                json!({"file_path": "a.txt"}),
+                json!({}),
+                json!({}),
+                json!({})
+            ]
+        );
+    }
+}
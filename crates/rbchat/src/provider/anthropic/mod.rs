@@ -0,0 +1,23 @@
+//! This module provides an interface for interacting with Anthropic's Messages API,
+//! enabling chat interactions with Claude models within the Rusty Buddy application.
+//!
+//! The `AnthropicInterface` struct encapsulates the necessary methods to send
+//! messages to the Anthropic API, receive responses, and track token usage.
+//!
+//! ## Key Responsibilities
+//!
+//! - **Message Handling:** Converts custom message formats into the Messages API format.
+//!   System, context and knowledge messages are combined into the top-level system prompt,
+//!   user messages may carry image attachments.
+//! - **Backend Integration:** Implements the `ChatBackend` trait, including streaming.
+//! - **Customizable Settings:** The base URL can be configured per model, which allows
+//!   gateways or local stand-ins to be used instead of api.anthropic.com.
+//!
+//! ## Methods
+//!
+//! - **from_model:** Constructs a new instance of `AnthropicInterface` for a configured model,
+//!   reading the key from `ANTHROPIC_API_KEY` or the variable named by `api_key_env`.
+//! - **send_request:** Sends a request with messages to the Anthropic backend and retrieves a response.
+//! - **print_statistics:** Outputs token usage statistics related to the last request and overall usage.
+
+pub mod anthropic_interface;
@@ -23,21 +23,26 @@
 //!
 //! ## Trait Implementations
 //!
-//! - `ChatBackend`: Implements the necessary methods to send requests to the chat model and print statistics about the model in use.
+//! - `ChatBackend`: Implements the necessary methods to send requests to the chat model, stream its answers and print statistics about the model in use.
 
-use crate::chat::interface::{ChatBackend, Message, MessageInfo, MessageRole};
+use crate::chat::interface::{ChatBackend, Message, MessageInfo, MessageRole, ToolCall};
+use crate::context::estimate_tokens;
 use crate::knowledge::EmbeddingService;
+use crate::tools::ToolDefinition;
 use async_trait::async_trait;
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine;
 use chrono::Utc;
+use futures::StreamExt;
 use log::{debug, error, info, warn}; // Ensure to import appropriate logging macros
 use ollama_rs::generation::embeddings::request::{EmbeddingsInput, GenerateEmbeddingsRequest};
 use ollama_rs::generation::images::Image;
+use ollama_rs::generation::tools::{ToolCall as OllamaToolCall, ToolCallFunction, ToolInfo};
 use ollama_rs::{
     generation::chat::{request::ChatMessageRequest, ChatMessage},
     IntoUrlSealed, Ollama,
 };
+use serde_json::json;
 use std::borrow::Cow;
 use std::error::Error;
 
@@ -88,12 +93,100 @@ impl OllamaInterface {
                     chat_message
                 }
                 MessageRole::Assistant => ChatMessage::assistant(msg.content.clone()),
+                MessageRole::ToolCall => {
+                    let mut chat_message = ChatMessage::assistant(msg.content.clone());
+                    if let Some(MessageInfo::ToolCallInfo { tool_calls }) = &msg.info {
+                        chat_message.tool_calls = tool_calls
+                            .iter()
+                            .map(|tool_call| OllamaToolCall {
+                                function: ToolCallFunction {
+                                    name: tool_call.name.clone(),
+                                    arguments: serde_json::from_str(&tool_call.arguments)
+                                        .unwrap_or_default(),
+                                },
+                            })
+                            .collect();
+                    }
+                    chat_message
+                }
+                MessageRole::ToolResult => ChatMessage::tool(msg.content.clone()),
                 MessageRole::Context | MessageRole::System | MessageRole::Knowledge => {
                     ChatMessage::system(msg.content.clone())
                 }
             })
             .collect()
     }
+
+    fn convert_tools(tools: &[ToolDefinition]) -> Result<Vec<ToolInfo>, Box<dyn Error>> {
+        tools
+            .iter()
+            .map(|tool| {
+                let info = serde_json::from_value(json!({
+                    "type": "Function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.parameters,
+                    }
+                }))?;
+                Ok(info)
+            })
+            .collect()
+    }
+
+    fn create_request(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+    ) -> Result<ChatMessageRequest, Box<dyn Error>> {
+        let chat_messages = Self::convert_messages(messages);
+        debug!("Converted messages for Ollama: {:?}", chat_messages);
+        let mut request = ChatMessageRequest::new(self.model.clone(), chat_messages);
+        if !tools.is_empty() {
+            request = request.tools(Self::convert_tools(tools)?);
+        }
+        Ok(request)
+    }
+
+    /// Ollama does not assign ids to tool calls, so they are numbered in order of appearance.
+    fn convert_tool_calls(tool_calls: &[OllamaToolCall], offset: usize) -> Vec<ToolCall> {
+        tool_calls
+            .iter()
+            .enumerate()
+            .map(|(i, tool_call)| ToolCall {
+                id: format!("call_{}", offset + i),
+                name: tool_call.function.name.clone(),
+                arguments: tool_call.function.arguments.to_string(),
+            })
+            .collect()
+    }
+
+    fn create_response_message(
+        &self,
+        content: String,
+        tool_calls: Vec<ToolCall>,
+        prompt_token: u32,
+        completion_token: u32,
+    ) -> Message {
+        if !tool_calls.is_empty() {
+            return Message {
+                role: MessageRole::ToolCall,
+                content,
+                info: Some(MessageInfo::ToolCallInfo { tool_calls }),
+            };
+        }
+        Message {
+            role: MessageRole::Assistant,
+            content,
+            info: Some(MessageInfo::AssistantInfo {
+                model: self.model.clone(),
+                persona_name: String::new(),
+                prompt_token,
+                completion_token,
+                timestamp: Utc::now(),
+            }),
+        }
+    }
 }
 
 #[async_trait]
@@ -101,13 +194,10 @@ impl ChatBackend for OllamaInterface {
     async fn send_request(
         &mut self,
         messages: &[Message],
-        _use_tools: bool,
+        tools: &[ToolDefinition],
     ) -> Result<Message, Box<dyn Error>> {
         info!("Sending request to Ollama with {} messages", messages.len());
-        let chat_messages = Self::convert_messages(messages);
-        debug!("Converted messages for Ollama: {:?}", chat_messages);
-
-        let request = ChatMessageRequest::new(self.model.clone(), chat_messages);
+        let request = self.create_request(messages, tools)?;
         info!(
             "Sending chat message request to Ollama for model: {}",
             self.model
@@ -118,18 +208,14 @@ impl ChatBackend for OllamaInterface {
                 info!("Received response from Ollama");
                 let assistant_message = response.message;
                 debug!("Got assistant message: {}", assistant_message.content);
-                let content_len = assistant_message.content.len();
-                Ok(Message {
-                    role: MessageRole::Assistant,
-                    content: assistant_message.content,
-                    info: Some(MessageInfo::AssistantInfo {
-                        model: self.model.clone(),
-                        persona_name: String::new(),
-                        prompt_token: 0,
-                        completion_token: content_len as u32,
-                        timestamp: Utc::now(),
-                    }),
-                })
+                let content_len = assistant_message.content.len() as u32;
+                let tool_calls = Self::convert_tool_calls(&assistant_message.tool_calls, 0);
+                Ok(self.create_response_message(
+                    assistant_message.content,
+                    tool_calls,
+                    0,
+                    content_len,
+                ))
             }
             Err(e) => {
                 error!("Failed to get response from Ollama: {}", e);
@@ -138,6 +224,50 @@ impl ChatBackend for OllamaInterface {
         }
     }
 
+    async fn send_request_stream(
+        &mut self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        on_delta: &mut (dyn for<'a> FnMut(&'a str) + Send),
+    ) -> Result<Message, Box<dyn Error>> {
+        info!(
+            "Sending streaming request to Ollama with {} messages",
+            messages.len()
+        );
+        let request = self.create_request(messages, tools)?;
+        let mut stream = match self.ollama.send_chat_messages_stream(request).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("Failed to open stream to Ollama: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let mut content = String::new();
+        let mut prompt_token = 0;
+        let mut completion_token = None;
+        let mut tool_calls = Vec::new();
+        while let Some(response) = stream.next().await {
+            let response = response.map_err(|_| "Failed to read response stream from Ollama")?;
+            on_delta(response.message.content.as_str());
+            content.push_str(response.message.content.as_str());
+            let offset = tool_calls.len();
+            tool_calls.extend(Self::convert_tool_calls(
+                &response.message.tool_calls,
+                offset,
+            ));
+            if let Some(final_data) = response.final_data {
+                prompt_token = final_data.prompt_eval_count as u32;
+                completion_token = Some(final_data.eval_count as u32);
+            }
+        }
+        info!("Received streamed response from Ollama");
+
+        // A stream cut short has no final data, so the tokens of the answer are estimated
+        let completion_token = completion_token.unwrap_or_else(|| estimate_tokens(&content) as u32);
+        Ok(self.create_response_message(content, tool_calls, prompt_token, completion_token))
+    }
+
     fn print_statistics(&self) {
         debug!("Using Ollama model: {}", self.model);
     }
@@ -167,7 +297,19 @@ impl EmbeddingService for OllamaInterface {
         }
     }
 
-    fn embedding_len(&self) -> usize {
-        1024
+    async fn get_embeddings(&self, contents: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        info!("Generating embeddings for {} contents", contents.len());
+        let request = GenerateEmbeddingsRequest::new(
+            self.model.clone(),
+            EmbeddingsInput::Multiple(contents.to_vec()),
+        );
+
+        match self.ollama.generate_embeddings(request).await {
+            Ok(result) => Ok(result.embeddings),
+            Err(e) => {
+                error!("Error generating embeddings: {}", e);
+                Err(e.into())
+            }
+        }
     }
 }
@@ -1,5 +1,5 @@
 //! This module provides an interface for interacting with OpenAI's AI backend,
-//! facilitating chat interactions and file comparison functionalities.
+//! facilitating chat interactions and embeddings.
 //!
 //! The `OpenAIInterface` struct encapsulates the necessary methods to send
 //! messages to the OpenAI API, receive responses, and manage the flow of
@@ -28,5 +28,4 @@
 //! - **send_request:** Sends a request with messages to the OpenAI backend and retrieves a response.
 //! - **print_statistics:** Outputs token usage statistics related to the last request and overall usage.
 
-pub mod file_diff;
 pub mod openai_interface;
@@ -22,7 +22,9 @@
 //! ## Methods
 //!
 //! - `new`: Creates a new instance of `OpenAIInterface`, initializing it with the provided model and optional timeout.
+//! - `from_model`: Creates an instance for a configured model, honouring its endpoint, key variable and headers.
 //! - `send_request`: Sends a request with messages to the OpenAI backend and retrieves a response.
+//! - `send_request_stream`: Sends a request as a chunked completion and forwards the content deltas while they arrive.
 //! - `print_statistics`: Outputs token usage statistics related to the last request and overall usage.
 //!
 //! ## Using the OpenAIInterface
@@ -34,21 +36,22 @@
 //! All methods return a `Result`, which will contain an error of type `Box<dyn Error>` on failure.
 //! Therefore, ensure to handle potential errors gracefully when invoking these methods during use.
 
-use crate::chat::interface::{ChatBackend, Message, MessageInfo, MessageRole};
+use crate::chat::interface::{ChatBackend, Message, MessageInfo, MessageRole, ToolCall};
+use crate::config::Model;
 use crate::knowledge::EmbeddingService;
-use crate::provider::openai::file_diff;
-use crate::provider::openai::file_diff::{create_directory, create_file, update_file_section};
+use crate::tools::ToolDefinition;
 use async_openai::config::OpenAIConfig;
 use async_openai::types::chat::{
-    ChatCompletionMessageToolCall, ChatCompletionMessageToolCalls,
-    ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
-    ChatCompletionRequestMessageContentPartImageArgs,
+    ChatCompletionMessageToolCall, ChatCompletionMessageToolCallChunk,
+    ChatCompletionMessageToolCalls, ChatCompletionRequestAssistantMessageArgs,
+    ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartImageArgs,
     ChatCompletionRequestMessageContentPartTextArgs, ChatCompletionRequestSystemMessageArgs,
-    ChatCompletionRequestUserMessageArgs, ChatCompletionRequestUserMessageContent,
-    ChatCompletionRequestUserMessageContentPart, ChatCompletionResponseMessage, ChatCompletionTool,
+    ChatCompletionRequestToolMessageArgs, ChatCompletionRequestUserMessageArgs,
+    ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
+    ChatCompletionResponseMessage, ChatCompletionStreamOptions, ChatCompletionTool,
     ChatCompletionToolChoiceOption, ChatCompletionTools, CompletionUsage,
     CreateChatCompletionRequest, CreateChatCompletionRequestArgs, CreateChatCompletionResponse,
-    FunctionObjectArgs, ImageDetail, ImageUrlArgs, ToolChoiceOptions,
+    FunctionCall, FunctionObjectArgs, ImageDetail, ImageUrlArgs, ToolChoiceOptions,
 };
 use async_openai::types::embeddings::CreateEmbeddingRequestArgs;
 use async_openai::Client;
@@ -57,14 +60,17 @@ use base64::prelude::BASE64_STANDARD;
 use base64::Engine;
 use chrono::Utc;
 use dotenvy::dotenv;
+use futures::StreamExt;
 use log::{debug, error, info, trace, warn};
-use serde_json::Value;
+use reqwest::header::HeaderName;
 use std::borrow::Cow;
 use std::env;
 use std::error::Error;
 use std::time::Duration;
 use tokio::time::timeout;
 
+const DEFAULT_API_KEY_ENV: &str = "OPENAI_KEY";
+
 /// OpenAIInterface provides a wrapper around the OpenAI API for sending chat requests and handling tools.
 /// It implements the ChatBackend trait, allowing it to be integrated into a chat application.
 ///
@@ -86,7 +92,7 @@ impl ChatBackend for OpenAIInterface {
     async fn send_request(
         &mut self,
         messages: &[Message],
-        use_tools: bool,
+        tools: &[ToolDefinition],
     ) -> Result<Message, Box<dyn Error>> {
         trace!(
             "Preparing to send a request to OpenAI with messages: {:?}",
@@ -96,7 +102,7 @@ impl ChatBackend for OpenAIInterface {
         let oai_messages = self.convert_to_chat_completion_messages(messages)?;
         trace!("Converted messages: {:?}", oai_messages);
 
-        let request = self.create_openai_request(&oai_messages, use_tools)?;
+        let request = self.create_openai_request(&oai_messages, tools)?;
         info!(
             "Sending request to OpenAI with model '{}' and timeout {:?}.",
             self.model, self.timeout_duration
@@ -119,36 +125,103 @@ impl ChatBackend for OpenAIInterface {
             }
         };
 
-        let usage = chat_completion.usage.clone().unwrap();
-        self.update_statistics(usage);
+        self.update_statistics(chat_completion.usage.as_ref());
 
         debug!("Extracting returned message from chat completion.");
         let returned_message = self.extract_returned_message(&chat_completion)?;
 
-        if use_tools {
-            if let Some(tool_calls) = returned_message.tool_calls {
-                for tool_call in tool_calls {
-                    if let ChatCompletionMessageToolCalls::Function(tc) = tool_call {
-                        debug!("Handling tool call: {:?}", tc.function.name);
-                        self.handle_tool_call(tc).await?;
-                    }
+        let content = returned_message.content.unwrap_or_default();
+        let tool_calls: Vec<ToolCall> = returned_message
+            .tool_calls
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|tool_call| match tool_call {
+                ChatCompletionMessageToolCalls::Function(tc) => Some(ToolCall {
+                    id: tc.id,
+                    name: tc.function.name,
+                    arguments: tc.function.arguments,
+                }),
+                ChatCompletionMessageToolCalls::Custom(_) => None,
+            })
+            .collect();
+        info!("Request processing completed successfully.");
+        Ok(self.create_response_message(content, tool_calls))
+    }
+
+    async fn send_request_stream(
+        &mut self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        on_delta: &mut (dyn for<'a> FnMut(&'a str) + Send),
+    ) -> Result<Message, Box<dyn Error>> {
+        trace!(
+            "Preparing to send a streaming request to OpenAI with messages: {:?}",
+            messages
+        );
+
+        let oai_messages = self.convert_to_chat_completion_messages(messages)?;
+        let mut request = self.create_openai_request(&oai_messages, tools)?;
+        request.stream_options = Some(ChatCompletionStreamOptions {
+            include_usage: Some(true),
+            include_obfuscation: None,
+        });
+        info!(
+            "Sending streaming request to OpenAI with model '{}' and timeout {:?}.",
+            self.model, self.timeout_duration
+        );
+
+        let result = timeout(
+            self.timeout_duration,
+            self.client.chat().create_stream(request),
+        )
+        .await;
+        let mut stream = match result {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                error!("Error while opening stream to OpenAI: {:#?}", e);
+                return Err(Box::new(e));
+            }
+            Err(e) => {
+                error!("Timeout error while waiting for OpenAI stream: {:#?}", e);
+                return Err(Box::new(e));
+            }
+        };
+
+        let mut content = String::new();
+        let mut tool_calls: Vec<ToolCall> = Vec::new();
+        let mut usage = None;
+        // The timeout applies to the gap between two chunks, so long answers are not cut off.
+        loop {
+            let chunk = match timeout(self.timeout_duration, stream.next()).await {
+                Ok(Some(Ok(chunk))) => chunk,
+                Ok(Some(Err(e))) => {
+                    error!("Error while receiving stream from OpenAI: {:#?}", e);
+                    return Err(Box::new(e));
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Timeout error while waiting for OpenAI chunk: {:#?}", e);
+                    return Err(Box::new(e));
+                }
+            };
+
+            if chunk.usage.is_some() {
+                usage = chunk.usage;
+            }
+            for choice in chunk.choices {
+                if let Some(delta) = choice.delta.content {
+                    on_delta(delta.as_str());
+                    content.push_str(delta.as_str());
+                }
+                if let Some(chunks) = choice.delta.tool_calls {
+                    merge_tool_call_chunks(&mut tool_calls, chunks);
                 }
             }
         }
 
-        let content = returned_message.content.unwrap_or_default();
-        info!("Request processing completed successfully.");
-        Ok(Message {
-            role: MessageRole::Assistant,
-            content,
-            info: Some(MessageInfo::AssistantInfo {
-                model: self.model.clone(),
-                persona_name: String::new(),
-                prompt_token: self.last_call_prompt_token,
-                completion_token: self.last_call_completion_token,
-                timestamp: Utc::now(),
-            }),
-        })
+        info!("Streaming request completed successfully.");
+        self.update_statistics(usage.as_ref());
+        Ok(self.create_response_message(content, tool_calls))
     }
 
     fn print_statistics(&self) {
@@ -191,8 +264,65 @@ impl EmbeddingService for OpenAIInterface {
         Ok(Box::new(embedding_response.data[0].embedding.clone()))
     }
 
-    fn embedding_len(&self) -> usize {
-        3072
+    async fn get_embeddings(&self, contents: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+        info!("Generating embeddings for {} contents.", contents.len());
+        let truncated_contents: Vec<&str> = contents
+            .iter()
+            .map(|content| truncate_to_max_bytes(content, 32_000))
+            .collect();
+        let embedding_request = CreateEmbeddingRequestArgs::default()
+            .model(self.model.clone())
+            .input(truncated_contents)
+            .build()?;
+
+        let mut embedding_response = match self.client.embeddings().create(embedding_request).await
+        {
+            Ok(embedding_response) => embedding_response,
+            Err(e) => {
+                error!("Error creating embeddings: {}", e);
+                return Err(Box::new(e));
+            }
+        };
+
+        // The embeddings name the position of their input
+        embedding_response
+            .data
+            .sort_by_key(|embedding| embedding.index);
+        Ok(embedding_response
+            .data
+            .into_iter()
+            .map(|embedding| embedding.embedding)
+            .collect())
+    }
+}
+
+/// Streamed tool calls arrive in fragments: the first chunk of a call carries its id and
+/// name, the following ones append to the arguments. Fragments are matched by their index.
+fn merge_tool_call_chunks(
+    tool_calls: &mut Vec<ToolCall>,
+    chunks: Vec<ChatCompletionMessageToolCallChunk>,
+) {
+    for chunk in chunks {
+        let index = chunk.index as usize;
+        while tool_calls.len() <= index {
+            tool_calls.push(ToolCall {
+                id: String::new(),
+                name: String::new(),
+                arguments: String::new(),
+            });
+        }
+        let tool_call = &mut tool_calls[index];
+        if let Some(id) = chunk.id {
+            tool_call.id = id;
+        }
+        if let Some(function) = chunk.function {
+            if let Some(name) = function.name {
+                tool_call.name.push_str(name.as_str());
+            }
+            if let Some(arguments) = function.arguments {
+                tool_call.arguments.push_str(arguments.as_str());
+            }
+        }
     }
 }
 
@@ -218,6 +348,27 @@ impl OpenAIInterface {
             std::process::exit(1);
         }
 
+        Self::with_client(
+            model,
+            timeout_secs,
+            OpenAIInterface::create_openai_client().unwrap(),
+        )
+    }
+
+    /// Creates the interface for a configured model. Its `url`, `api_key_env`, `headers`,
+    /// `organization` and `project` select the endpoint, so any OpenAI compatible server
+    /// (vLLM, LM Studio, llama.cpp, LiteLLM, gateways) can be used.
+    pub fn from_model(model: &Model, timeout_secs: u64) -> Result<Self, Box<dyn Error>> {
+        dotenv().ok();
+        let config = Self::create_openai_config(model)?;
+        Ok(Self::with_client(
+            model.api_name.clone(),
+            timeout_secs,
+            Client::with_config(config),
+        ))
+    }
+
+    fn with_client(model: String, timeout_secs: u64, client: Client<OpenAIConfig>) -> Self {
         OpenAIInterface {
             model,
             timeout_duration: Duration::from_secs(timeout_secs),
@@ -225,7 +376,7 @@ impl OpenAIInterface {
             last_call_prompt_token: 0,
             overall_completion_token: 0,
             overall_prompt_token: 0,
-            client: OpenAIInterface::create_openai_client().unwrap(),
+            client,
         }
     }
 
@@ -282,10 +433,49 @@ impl OpenAIInterface {
                     .build()
                     .unwrap()
                     .into(),
+                MessageRole::ToolCall => Self::create_tool_call_message(msg),
+                MessageRole::ToolResult => {
+                    let tool_call_id = match &msg.info {
+                        Some(MessageInfo::ToolResultInfo { tool_call_id, .. }) => {
+                            tool_call_id.as_str()
+                        }
+                        _ => "",
+                    };
+                    ChatCompletionRequestToolMessageArgs::default()
+                        .content(msg.content.as_str())
+                        .tool_call_id(tool_call_id)
+                        .build()
+                        .unwrap()
+                        .into()
+                }
             })
             .collect())
     }
 
+    fn create_tool_call_message(msg: &Message) -> ChatCompletionRequestMessage {
+        let tool_calls = match &msg.info {
+            Some(MessageInfo::ToolCallInfo { tool_calls }) => tool_calls
+                .iter()
+                .map(|tool_call| {
+                    ChatCompletionMessageToolCalls::Function(ChatCompletionMessageToolCall {
+                        id: tool_call.id.clone(),
+                        function: FunctionCall {
+                            name: tool_call.name.clone(),
+                            arguments: tool_call.arguments.clone(),
+                        },
+                    })
+                })
+                .collect(),
+            _ => vec![],
+        };
+        let mut builder = ChatCompletionRequestAssistantMessageArgs::default();
+        builder.tool_calls(tool_calls);
+        if !msg.content.is_empty() {
+            builder.content(msg.content.as_str());
+        }
+        builder.build().unwrap().into()
+    }
+
     fn create_user_message(msg: &Message) -> Result<ChatCompletionRequestMessage, Box<dyn Error>> {
         let user_msg = ChatCompletionRequestMessageContentPartTextArgs::default()
             .text(msg.content.as_str())
@@ -322,143 +512,122 @@ impl OpenAIInterface {
             .into())
     }
 
-    fn update_statistics(&mut self, usage: CompletionUsage) {
-        self.last_call_completion_token = usage.completion_tokens;
-        self.last_call_prompt_token = usage.prompt_tokens;
+    // Turns the answer into a tool call message if the model requested tools
+    fn create_response_message(&self, content: String, tool_calls: Vec<ToolCall>) -> Message {
+        if tool_calls.is_empty() {
+            return self.create_assistant_message(content);
+        }
+        debug!("Model requested {} tool calls.", tool_calls.len());
+        Message {
+            role: MessageRole::ToolCall,
+            content,
+            info: Some(MessageInfo::ToolCallInfo { tool_calls }),
+        }
+    }
+
+    fn create_assistant_message(&self, content: String) -> Message {
+        Message {
+            role: MessageRole::Assistant,
+            content,
+            info: Some(MessageInfo::AssistantInfo {
+                model: self.model.clone(),
+                persona_name: String::new(),
+                prompt_token: self.last_call_prompt_token,
+                completion_token: self.last_call_completion_token,
+                timestamp: Utc::now(),
+            }),
+        }
+    }
+
+    // OpenAI-compatible servers such as the llama.cpp server may not report the usage, the
+    // call then counts no tokens
+    fn update_statistics(&mut self, usage: Option<&CompletionUsage>) {
+        self.last_call_completion_token = usage.map_or(0, |usage| usage.completion_tokens);
+        self.last_call_prompt_token = usage.map_or(0, |usage| usage.prompt_tokens);
         self.overall_completion_token += self.last_call_completion_token;
         self.overall_prompt_token += self.last_call_prompt_token;
         info!("Updated token statistics: Last call completion tokens: {}, Last call prompt tokens: {}", self.last_call_completion_token, self.last_call_prompt_token);
     }
 
     fn create_openai_client() -> Result<Client<OpenAIConfig>, Box<dyn Error>> {
-        let openai_key = env::var("OPENAI_KEY")?;
+        let openai_key = env::var(DEFAULT_API_KEY_ENV)?;
         Ok(Client::with_config(
             OpenAIConfig::default().with_api_key(openai_key),
         ))
     }
 
+    fn create_openai_config(model: &Model) -> Result<OpenAIConfig, Box<dyn Error>> {
+        let mut config = OpenAIConfig::default();
+        match (&model.url, &model.api_key_env) {
+            // The OpenAI key must not reach other hosts, so a custom url only gets the key of
+            // an explicitly configured variable. Local servers usually run without one.
+            (Some(_), None) => {
+                info!(
+                    "No api_key_env set, using model '{}' without an API key.",
+                    model.name
+                );
+                // The default config picks up the OpenAI variables of the environment
+                config = config.with_api_key("").with_org_id("").with_project_id("");
+            }
+            (_, api_key_env) => {
+                let key_variable = api_key_env.as_deref().unwrap_or(DEFAULT_API_KEY_ENV);
+                let api_key = env::var(key_variable).map_err(|_| {
+                    format!(
+                        "The environment variable '{}' must be set for model '{}'.",
+                        key_variable, model.name
+                    )
+                })?;
+                config = config.with_api_key(api_key);
+            }
+        }
+        if let Some(url) = &model.url {
+            config = config.with_api_base(url.as_str());
+        }
+        if let Some(organization) = &model.organization {
+            config = config.with_org_id(organization.as_str());
+        }
+        if let Some(project) = &model.project {
+            config = config.with_project_id(project.as_str());
+        }
+        for (name, value) in &model.headers {
+            config =
+                config.with_header(HeaderName::from_bytes(name.as_bytes())?, value.as_str())?;
+        }
+        Ok(config)
+    }
+
     fn create_openai_request(
         &self,
         messages: &[ChatCompletionRequestMessage],
-        use_tools: bool,
+        tools: &[ToolDefinition],
     ) -> Result<CreateChatCompletionRequest, Box<dyn Error>> {
         let mut builder = &mut CreateChatCompletionRequestArgs::default();
         builder = builder.model(self.model.as_str()).messages(messages);
-        if use_tools {
+        if !tools.is_empty() {
             builder = builder
-                .tools(vec![
-                    Self::create_diff_tool()?,
-                    Self::create_new_dir_tool()?,
-                    Self::create_new_file_tool()?,
-                    Self::create_update_file_section_tool()?,
-                ])
+                .tools(Self::convert_tools(tools)?)
                 .tool_choice(ChatCompletionToolChoiceOption::Mode(
-                    ToolChoiceOptions::Required,
+                    ToolChoiceOptions::Auto,
                 ))
                 .parallel_tool_calls(true);
         }
-        debug!("Created request for OpenAI with tools: {}", use_tools);
+        debug!("Created request for OpenAI with {} tools", tools.len());
         Ok(builder.build()?)
     }
 
-    fn create_new_file_tool() -> Result<ChatCompletionTools, Box<dyn Error>> {
-        Ok(ChatCompletionTools::Function(ChatCompletionTool {
-            function: FunctionObjectArgs::default()
-                .name("create_file")
-                .description("Creates a new file with given content at the specified path.")
-                .parameters(serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "file_path": {
-                            "type": "string",
-                            "description": "The path for the new file."
-                        },
-                        "file_content": {
-                            "type": "string",
-                            "description": "The content to write to the new file."
-                        }
-                    },
-                    "required": ["file_path", "file_content"]
-                }))
-                .build()?,
-        }))
-    }
-
-    fn create_new_dir_tool() -> Result<ChatCompletionTools, Box<dyn Error>> {
-        Ok(ChatCompletionTools::Function(ChatCompletionTool {
-            function: FunctionObjectArgs::default()
-                .name("create_directory")
-                .description("Creates a new directory at the specified path.")
-                .parameters(serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "directory_path": {
-                            "type": "string",
-                            "description": "The path where the new directory should be created."
-                        }
-                    },
-                    "required": ["directory_path"]
-                }))
-                .build()?,
-        }))
-    }
-
-    fn create_diff_tool() -> Result<ChatCompletionTools, Box<dyn Error>> {
-        Ok(ChatCompletionTools::Function(ChatCompletionTool {
-            function: FunctionObjectArgs::default()
-                .name("show_diff")
-                .description(
-                    "Shows the diff between an existing file and the newly generated content of that file and asks the user to apply the changes.",
-                )
-                .parameters(serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "diff_file": {
-                            "type": "string",
-                            "description": "The path to the original file."
-                        },
-                        "diff_content": {
-                            "type": "string",
-                            "description": "The new content of the file to display."
-                        }
-                    },
-                    "required": ["diff_file", "diff_content"]
-                }))
-                .build()?,
-        }))
-    }
-
-    fn create_update_file_section_tool() -> Result<ChatCompletionTools, Box<dyn Error>> {
-        Ok(ChatCompletionTools::Function(ChatCompletionTool {
-            function: FunctionObjectArgs::default()
-                .name("update_file_section")
-                .description(
-                    "Updates a section of a file specified by starting and ending lines with new content.",
-                )
-                .parameters(serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "file_path": {
-                            "type": "string",
-                            "description": "The path to the file to update."
-                        },
-                        "start_line": {
-                            "type": "integer",
-                            "description": "The starting line number of the section to be updated."
-                        },
-                        "end_line": {
-                            "type": "integer",
-                            "description": "The ending line number of the section to be updated."
-                        },
-                        "new_content": {
-                            "type": "string",
-                            "description": "The new content that will replace the specified section."
-                        }
-                    },
-                    "required": ["file_path", "start_line", "end_line", "new_content"]
+    fn convert_tools(tools: &[ToolDefinition]) -> Result<Vec<ChatCompletionTools>, Box<dyn Error>> {
+        tools
+            .iter()
+            .map(|tool| {
+                Ok(ChatCompletionTools::Function(ChatCompletionTool {
+                    function: FunctionObjectArgs::default()
+                        .name(tool.name.as_str())
+                        .description(tool.description.as_str())
+                        .parameters(tool.parameters.clone())
+                        .build()?,
                 }))
-                .build()?,
-        }))
+            })
+            .collect()
     }
 
     fn extract_returned_message(
@@ -472,70 +641,63 @@ impl OpenAIInterface {
             .message
             .clone())
     }
+}
 
-    async fn handle_tool_call(
-        &self,
-        tool_call: ChatCompletionMessageToolCall,
-    ) -> Result<(), Box<dyn Error>> {
-        let args_json: Value = serde_json::from_str(&tool_call.function.arguments)?;
-        match tool_call.function.name.as_str() {
-            "create_file" => {
-                let file_path = args_json
-                    .get("file_path")
-                    .and_then(Value::as_str)
-                    .ok_or("Missing 'file_path' argument")?;
-                let file_content = args_json
-                    .get("file_content")
-                    .and_then(Value::as_str)
-                    .ok_or("Missing 'file_content' argument")?;
-                create_file(file_path, file_content).await?;
-            }
-            "create_directory" => {
-                let directory_path = args_json
-                    .get("directory_path")
-                    .and_then(Value::as_str)
-                    .ok_or("Missing 'directory_path' argument")?;
-                create_directory(directory_path).await?;
-            }
-            "show_diff" => {
-                let diff_file = args_json
-                    .get("diff_file")
-                    .and_then(Value::as_str)
-                    .ok_or("Missing 'diff_file' argument")?;
-                let diff_content = args_json
-                    .get("diff_content")
-                    .and_then(Value::as_str)
-                    .ok_or("Missing 'diff_content' argument")?;
-                file_diff::show_diff_in_beyond_compare(diff_file, diff_content).await?;
-            }
-            "update_file_section" => {
-                // Handling 'update_file_section' by retrieving required arguments
-                let file_path = args_json
-                    .get("file_path")
-                    .and_then(Value::as_str)
-                    .ok_or("Missing 'file_path' argument")?;
-                let start_line = args_json
-                    .get("start_line")
-                    .and_then(Value::as_u64) // Line indices are typically usize
-                    .ok_or("Missing or invalid 'start_line' argument")?
-                    as usize;
-                let end_line = args_json
-                    .get("end_line")
-                    .and_then(Value::as_u64) // Line indices are typically usize
-                    .ok_or("Missing or invalid 'end_line' argument")?
-                    as usize;
-                let new_content = args_json
-                    .get("new_content")
-                    .and_then(Value::as_str)
-                    .ok_or("Missing 'new_content' argument")?;
-
-                // Call the method to replace content within specified lines
-                update_file_section(file_path, start_line, end_line, new_content).await?;
-            }
-            _ => {
-                return Err(Box::from("Unknown tool call name."));
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AIBackend;
+    use async_openai::config::Config;
+    use std::collections::HashMap;
+
+    fn model(url: Option<&str>, api_key_env: Option<&str>) -> Model {
+        Model {
+            name: "gateway".to_string(),
+            api_name: "served-model".to_string(),
+            url: url.map(str::to_string),
+            backend: AIBackend::OpenAI,
+            api_key_env: api_key_env.map(str::to_string),
+            headers: HashMap::from([("X-Team".to_string(), "tools".to_string())]),
+            organization: Some("org-1".to_string()),
+            project: Some("proj-1".to_string()),
+            context_window: None,
         }
-        Ok(())
+    }
+
+    #[test]
+    fn test_create_openai_config_for_compatible_endpoint() {
+        let config =
+            OpenAIInterface::create_openai_config(&model(Some("http://localhost:8000/v1"), None))
+                .unwrap();
+
+        assert_eq!(config.api_base(), "http://localhost:8000/v1");
+        let headers = config.headers();
+        assert_eq!(headers.get("X-Team").unwrap(), "tools");
+        assert_eq!(headers.get("OpenAI-Organization").unwrap(), "org-1");
+        assert_eq!(headers.get("OpenAI-Project").unwrap(), "proj-1");
+    }
+
+    #[test]
+    fn test_create_openai_config_sends_no_key_to_custom_url() {
+        unsafe { env::set_var(DEFAULT_API_KEY_ENV, "sk-openai-secret") };
+        let config =
+            OpenAIInterface::create_openai_config(&model(Some("http://localhost:8000/v1"), None))
+                .unwrap();
+
+        let headers = config.headers();
+        assert_eq!(headers.get("Authorization").unwrap(), "Bearer ");
+    }
+
+    #[test]
+    fn test_create_openai_config_requires_configured_key() {
+        let result = OpenAIInterface::create_openai_config(&model(
+            Some("http://localhost:8000/v1"),
+            Some("RUSTY_BUDDY_TEST_MISSING_KEY"),
+        ));
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("RUSTY_BUDDY_TEST_MISSING_KEY"));
     }
 }
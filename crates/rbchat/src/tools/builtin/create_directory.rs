@@ -0,0 +1,51 @@
+//! This tool lets the model create directories.
+
+use crate::tools::builtin::string_argument;
+use crate::tools::file_sandbox::FileSandbox;
+use crate::tools::tool::{RegisterableTool, Tool};
+use crate::tools::tool_registry::ToolRegistry;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// Creates a directory, including missing parent directories.
+pub struct CreateDirectoryTool;
+
+#[async_trait]
+impl Tool for CreateDirectoryTool {
+    fn name(&self) -> &str {
+        "create_directory"
+    }
+
+    fn description(&self) -> &str {
+        "Creates a new directory at the specified path."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "directory_path": {
+                    "type": "string",
+                    "description": "The path where the new directory should be created."
+                }
+            },
+            "required": ["directory_path"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: Value,
+        sandbox: &FileSandbox,
+    ) -> Result<String, Box<dyn Error>> {
+        let directory_path = string_argument(&arguments, "directory_path")?;
+        sandbox.create_directory(directory_path).await
+    }
+}
+
+impl RegisterableTool for CreateDirectoryTool {
+    fn register_with_registry(registry: &mut ToolRegistry) {
+        registry.register_tool(Box::new(CreateDirectoryTool));
+    }
+}
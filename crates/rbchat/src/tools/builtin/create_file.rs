@@ -0,0 +1,56 @@
+//! This tool lets the model create a new file or overwrite an existing one.
+
+use crate::tools::builtin::string_argument;
+use crate::tools::file_sandbox::FileSandbox;
+use crate::tools::tool::{RegisterableTool, Tool};
+use crate::tools::tool_registry::ToolRegistry;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// Creates a file with the given content at the specified path.
+pub struct CreateFileTool;
+
+#[async_trait]
+impl Tool for CreateFileTool {
+    fn name(&self) -> &str {
+        "create_file"
+    }
+
+    fn description(&self) -> &str {
+        "Creates a new file with given content at the specified path."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "file_path": {
+                    "type": "string",
+                    "description": "The path for the new file."
+                },
+                "file_content": {
+                    "type": "string",
+                    "description": "The content to write to the new file."
+                }
+            },
+            "required": ["file_path", "file_content"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: Value,
+        sandbox: &FileSandbox,
+    ) -> Result<String, Box<dyn Error>> {
+        let file_path = string_argument(&arguments, "file_path")?;
+        let file_content = string_argument(&arguments, "file_content")?;
+        sandbox.write_file(file_path, file_content).await
+    }
+}
+
+impl RegisterableTool for CreateFileTool {
+    fn register_with_registry(registry: &mut ToolRegistry) {
+        registry.register_tool(Box::new(CreateFileTool));
+    }
+}
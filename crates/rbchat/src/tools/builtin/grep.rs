@@ -0,0 +1,57 @@
+//! This tool lets the model search the files of the project with a regular expression.
+
+use crate::tools::builtin::string_argument;
+use crate::tools::explore::grep;
+use crate::tools::file_sandbox::FileSandbox;
+use crate::tools::tool::{RegisterableTool, Tool};
+use crate::tools::tool_registry::ToolRegistry;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// Returns the lines matching a regular expression together with their file and line number.
+pub struct GrepTool;
+
+#[async_trait]
+impl Tool for GrepTool {
+    fn name(&self) -> &str {
+        "grep"
+    }
+
+    fn description(&self) -> &str {
+        "Searches the files of the project for lines matching a regular expression and returns them as 'path:line: text'."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "pattern": {
+                    "type": "string",
+                    "description": "The regular expression to search for, in Rust regex syntax."
+                },
+                "path": {
+                    "type": "string",
+                    "description": "The file or directory to search, relative to the project root. Defaults to the root."
+                }
+            },
+            "required": ["pattern"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: Value,
+        sandbox: &FileSandbox,
+    ) -> Result<String, Box<dyn Error>> {
+        let pattern = string_argument(&arguments, "pattern")?;
+        let path = arguments.get("path").and_then(Value::as_str).unwrap_or(".");
+        grep(sandbox, pattern, path)
+    }
+}
+
+impl RegisterableTool for GrepTool {
+    fn register_with_registry(registry: &mut ToolRegistry) {
+        registry.register_tool(Box::new(GrepTool));
+    }
+}
@@ -0,0 +1,61 @@
+//! This tool lets the model list the files and directories of the project.
+
+use crate::tools::explore::list_directory;
+use crate::tools::file_sandbox::FileSandbox;
+use crate::tools::tool::{RegisterableTool, Tool};
+use crate::tools::tool_registry::ToolRegistry;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// Lists the entries of a directory, optionally including all subdirectories.
+pub struct ListDirectoryTool;
+
+#[async_trait]
+impl Tool for ListDirectoryTool {
+    fn name(&self) -> &str {
+        "list_directory"
+    }
+
+    fn description(&self) -> &str {
+        "Lists the files and directories in a directory of the project. Directories end with '/'."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "directory_path": {
+                    "type": "string",
+                    "description": "The directory to list, relative to the project root. Defaults to the root."
+                },
+                "recursive": {
+                    "type": "boolean",
+                    "description": "Also list the content of all subdirectories."
+                }
+            }
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: Value,
+        sandbox: &FileSandbox,
+    ) -> Result<String, Box<dyn Error>> {
+        let directory_path = arguments
+            .get("directory_path")
+            .and_then(Value::as_str)
+            .unwrap_or(".");
+        let recursive = arguments
+            .get("recursive")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        list_directory(sandbox, directory_path, recursive)
+    }
+}
+
+impl RegisterableTool for ListDirectoryTool {
+    fn register_with_registry(registry: &mut ToolRegistry) {
+        registry.register_tool(Box::new(ListDirectoryTool));
+    }
+}
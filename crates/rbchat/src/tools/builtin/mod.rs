@@ -0,0 +1,70 @@
+//! This module is responsible for the registration of the builtin tools in the
+//! `ToolRegistry`. The function `initialize_tools` takes a mutable reference to a
+//! `ToolRegistry` and registers all tools by calling their registration methods.
+//! `initialize_read_only_tools` registers only the tools that explore the project.
+//!
+//! The registered tools include:
+//! - `ReadFileTool`: Reads a file or a range of its lines.
+//! - `ListDirectoryTool`: Lists the entries of a directory.
+//! - `GrepTool`: Searches the project with a regular expression.
+//! - `CreateFileTool`: Creates a file with the given content.
+//! - `CreateDirectoryTool`: Creates a directory.
+//! - `ShowDiffTool`: Shows the difference between a file and proposed content and applies it on approval.
+//! - `UpdateFileSectionTool`: Replaces a range of lines in a file.
+
+use crate::tools::builtin::create_directory::CreateDirectoryTool;
+use crate::tools::builtin::create_file::CreateFileTool;
+use crate::tools::builtin::grep::GrepTool;
+use crate::tools::builtin::list_directory::ListDirectoryTool;
+use crate::tools::builtin::read_file::ReadFileTool;
+use crate::tools::builtin::show_diff::ShowDiffTool;
+use crate::tools::builtin::update_file_section::UpdateFileSectionTool;
+use crate::tools::tool::RegisterableTool;
+use crate::tools::tool_registry::ToolRegistry;
+use serde_json::Value;
+use std::error::Error;
+
+mod create_directory;
+mod create_file;
+mod grep;
+mod list_directory;
+mod read_file;
+mod show_diff;
+mod update_file_section;
+
+pub fn initialize_tools(registry: &mut ToolRegistry) {
+    initialize_read_only_tools(registry);
+    // Each tool registers itself
+    CreateFileTool::register_with_registry(registry);
+    CreateDirectoryTool::register_with_registry(registry);
+    ShowDiffTool::register_with_registry(registry);
+    UpdateFileSectionTool::register_with_registry(registry);
+}
+
+pub fn initialize_read_only_tools(registry: &mut ToolRegistry) {
+    ReadFileTool::register_with_registry(registry);
+    ListDirectoryTool::register_with_registry(registry);
+    GrepTool::register_with_registry(registry);
+}
+
+fn string_argument<'a>(arguments: &'a Value, name: &str) -> Result<&'a str, Box<dyn Error>> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Missing '{}' argument", name).into())
+}
+
+fn line_argument(arguments: &Value, name: &str) -> Result<usize, Box<dyn Error>> {
+    arguments
+        .get(name)
+        .and_then(Value::as_u64) // Line indices are typically usize
+        .map(|line| line as usize)
+        .ok_or_else(|| format!("Missing or invalid '{}' argument", name).into())
+}
+
+fn optional_line_argument(arguments: &Value, name: &str) -> Option<usize> {
+    arguments
+        .get(name)
+        .and_then(Value::as_u64)
+        .map(|line| line as usize)
+}
@@ -0,0 +1,62 @@
+//! This tool lets the model read a file of the project on demand.
+
+use crate::tools::builtin::{optional_line_argument, string_argument};
+use crate::tools::explore::read_file;
+use crate::tools::file_sandbox::FileSandbox;
+use crate::tools::tool::{RegisterableTool, Tool};
+use crate::tools::tool_registry::ToolRegistry;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// Returns the content of a file, or of a range of its lines.
+pub struct ReadFileTool;
+
+#[async_trait]
+impl Tool for ReadFileTool {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "Reads a file of the project. Large files can be read in parts by giving a line range."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "file_path": {
+                    "type": "string",
+                    "description": "The path to the file, relative to the project root."
+                },
+                "start_line": {
+                    "type": "integer",
+                    "description": "The first line to read, starting at 1."
+                },
+                "end_line": {
+                    "type": "integer",
+                    "description": "The last line to read, inclusive."
+                }
+            },
+            "required": ["file_path"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: Value,
+        sandbox: &FileSandbox,
+    ) -> Result<String, Box<dyn Error>> {
+        let file_path = string_argument(&arguments, "file_path")?;
+        let start_line = optional_line_argument(&arguments, "start_line");
+        let end_line = optional_line_argument(&arguments, "end_line");
+        read_file(sandbox, file_path, start_line, end_line)
+    }
+}
+
+impl RegisterableTool for ReadFileTool {
+    fn register_with_registry(registry: &mut ToolRegistry) {
+        registry.register_tool(Box::new(ReadFileTool));
+    }
+}
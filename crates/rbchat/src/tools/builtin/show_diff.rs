@@ -0,0 +1,65 @@
+//! This tool shows the user the difference between a file and the content proposed by the model.
+//! The diff is presented by the `ChangeApprover` of the sandbox, which writes the file once the
+//! user accepts the change.
+
+use crate::tools::builtin::string_argument;
+use crate::tools::file_sandbox::FileSandbox;
+use crate::tools::tool::{RegisterableTool, Tool};
+use crate::tools::tool_registry::ToolRegistry;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// Shows the diff between an existing file and its new content, letting the user apply the changes.
+pub struct ShowDiffTool;
+
+#[async_trait]
+impl Tool for ShowDiffTool {
+    fn name(&self) -> &str {
+        "show_diff"
+    }
+
+    fn description(&self) -> &str {
+        "Shows the diff between an existing file and the newly generated content of that file and asks the user to apply the changes."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "diff_file": {
+                    "type": "string",
+                    "description": "The path to the original file."
+                },
+                "diff_content": {
+                    "type": "string",
+                    "description": "The new content of the file to display."
+                }
+            },
+            "required": ["diff_file", "diff_content"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: Value,
+        sandbox: &FileSandbox,
+    ) -> Result<String, Box<dyn Error>> {
+        let diff_file = string_argument(&arguments, "diff_file")?;
+        let diff_content = string_argument(&arguments, "diff_content")?;
+        if !sandbox.resolve(diff_file)?.is_file() {
+            return Err(format!(
+                "File '{}' does not exist, use create_file for new files.",
+                diff_file
+            )
+            .into());
+        }
+        sandbox.write_file(diff_file, diff_content).await
+    }
+}
+
+impl RegisterableTool for ShowDiffTool {
+    fn register_with_registry(registry: &mut ToolRegistry) {
+        registry.register_tool(Box::new(ShowDiffTool));
+    }
+}
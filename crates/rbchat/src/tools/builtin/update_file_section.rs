@@ -0,0 +1,70 @@
+//! This tool lets the model replace a range of lines in an existing file.
+
+use crate::tools::builtin::{line_argument, string_argument};
+use crate::tools::file_sandbox::FileSandbox;
+use crate::tools::tool::{RegisterableTool, Tool};
+use crate::tools::tool_registry::ToolRegistry;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// Replaces the lines from `start_line` to `end_line` of a file with new content.
+pub struct UpdateFileSectionTool;
+
+#[async_trait]
+impl Tool for UpdateFileSectionTool {
+    fn name(&self) -> &str {
+        "update_file_section"
+    }
+
+    fn description(&self) -> &str {
+        "Updates a section of a file specified by starting and ending lines with new content."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "file_path": {
+                    "type": "string",
+                    "description": "The path to the file to update."
+                },
+                "start_line": {
+                    "type": "integer",
+                    "description": "The starting line number of the section to be updated."
+                },
+                "end_line": {
+                    "type": "integer",
+                    "description": "The ending line number of the section to be updated."
+                },
+                "new_content": {
+                    "type": "string",
+                    "description": "The new content that will replace the specified section."
+                }
+            },
+            "required": ["file_path", "start_line", "end_line", "new_content"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        arguments: Value,
+        sandbox: &FileSandbox,
+    ) -> Result<String, Box<dyn Error>> {
+        let file_path = string_argument(&arguments, "file_path")?;
+        let start_line = line_argument(&arguments, "start_line")?;
+        let end_line = line_argument(&arguments, "end_line")?;
+        let new_content = string_argument(&arguments, "new_content")?;
+
+        // Call the method to replace content within specified lines
+        sandbox
+            .update_file_section(file_path, start_line, end_line, new_content)
+            .await
+    }
+}
+
+impl RegisterableTool for UpdateFileSectionTool {
+    fn register_with_registry(registry: &mut ToolRegistry) {
+        registry.register_tool(Box::new(UpdateFileSectionTool));
+    }
+}
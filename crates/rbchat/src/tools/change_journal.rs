@@ -0,0 +1,240 @@
+//! This module records the files changed by tools, so the changes of a run can be undone.
+//!
+//! Every `wish` invocation or chat session that lets tools write files starts a `ChangeJournal`.
+//! Before the `FileSandbox` applies a change, the journal stores the prior state of the file,
+//! its content or the fact that it did not exist. Each run is saved as a JSON file in
+//! `.rusty/journal`, named after the time the run started.
+//!
+//! `undo_last_run` restores the files of the most recent run and removes its journal, so
+//! calling it again undoes the run before. `undo_last_run_summary` does the same and describes
+//! the outcome for the user.
+
+use crate::tools::file_sandbox::FileChange;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The prior state of a path touched during a run. Paths are relative to the project root.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub path: PathBuf,
+    /// True if the run created this directory.
+    #[serde(default)]
+    pub directory: bool,
+    /// Content of the file before the run, `None` if the run created it.
+    pub original: Option<String>,
+}
+
+/// All changes recorded for one `wish` invocation or chat session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalRun {
+    pub label: String,
+    pub started: DateTime<Utc>,
+    pub entries: Vec<JournalEntry>,
+}
+
+/// Records the prior state of every file a run changes.
+pub struct ChangeJournal {
+    file: PathBuf,
+    run: Mutex<JournalRun>,
+}
+
+impl ChangeJournal {
+    /// Starts the journal of a new run in `journal_dir`. Nothing is written until the first change.
+    pub fn start(journal_dir: PathBuf, label: &str) -> Self {
+        let started = Utc::now();
+        let file = journal_dir.join(format!("{}.json", started.format("%Y%m%d-%H%M%S%.3f")));
+        ChangeJournal {
+            file,
+            run: Mutex::new(JournalRun {
+                label: label.to_string(),
+                started,
+                entries: Vec::new(),
+            }),
+        }
+    }
+
+    /// Records the state of the path of `change` before it is applied below `root`.
+    pub fn record(&self, change: &FileChange, root: &Path) -> Result<(), Box<dyn Error>> {
+        let mut run = self.run.lock().unwrap();
+        // Only the state before the first change of a run has to be restored
+        if run.entries.iter().any(|entry| entry.path == change.path()) {
+            return Ok(());
+        }
+        let entry = match change {
+            FileChange::CreateDirectory { path } => {
+                if root.join(path).exists() {
+                    return Ok(());
+                }
+                JournalEntry {
+                    path: path.clone(),
+                    directory: true,
+                    original: None,
+                }
+            }
+            FileChange::WriteFile { path, original, .. } => JournalEntry {
+                path: path.clone(),
+                directory: false,
+                original: original.clone(),
+            },
+        };
+        run.entries.push(entry);
+
+        if let Some(parent) = self.file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.file, serde_json::to_string_pretty(&*run)?)?;
+        Ok(())
+    }
+}
+
+/// Lists the recorded runs, oldest first.
+pub fn list_runs(journal_dir: &Path) -> Result<Vec<JournalRun>, Box<dyn Error>> {
+    journal_files(journal_dir)?
+        .iter()
+        .map(|file| Ok(serde_json::from_str(&fs::read_to_string(file)?)?))
+        .collect()
+}
+
+/// Restores the files changed by the most recent run below `root` and removes its journal.
+/// Returns the undone run, or `None` if no run is recorded.
+pub fn undo_last_run(
+    journal_dir: &Path,
+    root: &Path,
+) -> Result<Option<JournalRun>, Box<dyn Error>> {
+    let Some(file) = journal_files(journal_dir)?.pop() else {
+        return Ok(None);
+    };
+    let run: JournalRun = serde_json::from_str(&fs::read_to_string(&file)?)?;
+
+    // Restore in reverse order, so files are removed before the directories containing them
+    for entry in run.entries.iter().rev() {
+        let target = root.join(&entry.path);
+        match (&entry.original, entry.directory) {
+            (_, true) => {
+                if let Err(e) = fs::remove_dir(&target) {
+                    log::warn!("Could not remove directory {}: {}", target.display(), e);
+                }
+            }
+            (Some(original), false) => fs::write(&target, original)?,
+            (None, false) => {
+                if target.exists() {
+                    fs::remove_file(&target)?;
+                }
+            }
+        }
+    }
+    fs::remove_file(&file)?;
+    Ok(Some(run))
+}
+
+/// Undoes the most recent run like `undo_last_run` and returns the lines describing what was
+/// restored, as printed by the `undo` command and the `/undo-changes` chat command.
+pub fn undo_last_run_summary(
+    journal_dir: &Path,
+    root: &Path,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let Some(run) = undo_last_run(journal_dir, root)? else {
+        return Ok(vec!["No changes to undo.".to_string()]);
+    };
+    let mut lines: Vec<String> = run
+        .entries
+        .iter()
+        .map(|entry| format!("Restored {}", entry.path.display()))
+        .collect();
+    lines.push(format!(
+        "Undid the changes of {} started at {}.",
+        run.label,
+        run.started.format("%Y-%m-%d %H:%M:%S")
+    ));
+    Ok(lines)
+}
+
+fn journal_files(journal_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !journal_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(journal_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    // The names start with the time of the run, so sorting them orders the runs
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::file_sandbox::{AutoApprover, FileSandbox};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_undo_restores_last_run() {
+        let root = TempDir::new().unwrap();
+        let journal_dir = root.path().join(".rusty").join("journal");
+        fs::write(root.path().join("a.txt"), "before\n").unwrap();
+
+        let sandbox = FileSandbox::new(root.path().to_path_buf())
+            .approver(Box::new(AutoApprover))
+            .journal(ChangeJournal::start(journal_dir.clone(), "wish"));
+        sandbox.write_file("a.txt", "first\n").await.unwrap();
+        sandbox.write_file("a.txt", "second\n").await.unwrap();
+        sandbox.create_directory("dir").await.unwrap();
+        sandbox.write_file("dir/new.txt", "new\n").await.unwrap();
+
+        let runs = list_runs(&journal_dir).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].entries.len(), 3);
+
+        let run = undo_last_run(&journal_dir, sandbox.root())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(run.label, "wish");
+        assert_eq!(
+            fs::read_to_string(root.path().join("a.txt")).unwrap(),
+            "before\n"
+        );
+        assert!(!root.path().join("dir").exists());
+        assert!(undo_last_run(&journal_dir, sandbox.root())
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_undo_summary_lists_restored_files() {
+        let root = TempDir::new().unwrap();
+        let journal_dir = root.path().join("journal");
+        let sandbox = FileSandbox::new(root.path().to_path_buf())
+            .approver(Box::new(AutoApprover))
+            .journal(ChangeJournal::start(journal_dir.clone(), "wish"));
+        sandbox.write_file("a.txt", "new\n").await.unwrap();
+
+        let lines = undo_last_run_summary(&journal_dir, sandbox.root()).unwrap();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "Restored a.txt");
+        assert!(lines[1].starts_with("Undid the changes of wish started at "));
+        assert_eq!(
+            undo_last_run_summary(&journal_dir, sandbox.root()).unwrap(),
+            vec!["No changes to undo."]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_records_nothing() {
+        let root = TempDir::new().unwrap();
+        let journal_dir = root.path().join("journal");
+        let sandbox = FileSandbox::new(root.path().to_path_buf())
+            .dry_run(true)
+            .journal(ChangeJournal::start(journal_dir.clone(), "wish"));
+
+        sandbox.write_file("a.txt", "content").await.unwrap();
+
+        assert!(list_runs(&journal_dir).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+//! This module renders file changes as a unified diff for the terminal, so a proposed
+//! change can be reviewed without an external diff tool.
+//!
+//! Changes are grouped into hunks with three lines of context. Every line carries the line
+//! numbers of the old and the new file in a gutter. When colors are enabled, removed lines
+//! are printed red, added lines green and hunk headers cyan using ANSI escape sequences.
+
+use crate::tools::file_sandbox::FileChange;
+use similar::{ChangeTag, TextDiff};
+use std::fmt::Write;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Lines of unchanged context shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// Renders a file change as a unified diff with line numbers, optionally colored.
+pub fn render_diff(change: &FileChange, colored: bool) -> String {
+    let paint = |color: &str, text: &str| {
+        if colored {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    };
+
+    match change {
+        FileChange::CreateDirectory { path } => {
+            paint(BOLD, &format!("new directory {}", path.display())) + "\n"
+        }
+        FileChange::WriteFile {
+            path,
+            original,
+            content,
+        } => {
+            let mut out = String::new();
+            let header = match original {
+                Some(_) => format!("--- a/{0}\n+++ b/{0}", path.display()),
+                None => format!("--- /dev/null\n+++ b/{}", path.display()),
+            };
+            out.push_str(&paint(BOLD, &header));
+            out.push('\n');
+
+            let old = original.as_deref().unwrap_or_default();
+            let diff = TextDiff::from_lines(old, content.as_str());
+            let groups = diff.grouped_ops(CONTEXT_LINES);
+            if groups.is_empty() {
+                out.push_str("no changes\n");
+                return out;
+            }
+
+            // Size the gutter for the largest line number of either side
+            let width = old
+                .lines()
+                .count()
+                .max(content.lines().count())
+                .to_string()
+                .len();
+            for group in groups {
+                let (first, last) = (&group[0], &group[group.len() - 1]);
+                let old_range = first.old_range().start..last.old_range().end;
+                let new_range = first.new_range().start..last.new_range().end;
+                let hunk = format!(
+                    "@@ -{},{} +{},{} @@",
+                    old_range.start + 1,
+                    old_range.len(),
+                    new_range.start + 1,
+                    new_range.len()
+                );
+                out.push_str(&paint(CYAN, &hunk));
+                out.push('\n');
+
+                for op in &group {
+                    for change in diff.iter_changes(op) {
+                        let number = |index: Option<usize>| match index {
+                            Some(index) => format!("{:>width$}", index + 1, width = width),
+                            None => " ".repeat(width),
+                        };
+                        let (sign, color) = match change.tag() {
+                            ChangeTag::Delete => ('-', Some(RED)),
+                            ChangeTag::Insert => ('+', Some(GREEN)),
+                            ChangeTag::Equal => (' ', None),
+                        };
+                        let line =
+                            format!("{}{}", sign, change.value().trim_end_matches(['\n', '\r']));
+                        let line = match color {
+                            Some(color) => paint(color, &line),
+                            None => line,
+                        };
+                        let _ = writeln!(
+                            out,
+                            "{} {} │{}",
+                            number(change.old_index()),
+                            number(change.new_index()),
+                            line
+                        );
+                    }
+                }
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn write_change(original: Option<&str>, content: &str) -> FileChange {
+        FileChange::WriteFile {
+            path: PathBuf::from("src/lib.rs"),
+            original: original.map(str::to_string),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_hunk_with_line_numbers() {
+        let original = (1..=10)
+            .map(|i| format!("line {}\n", i))
+            .collect::<String>();
+        let content = original.replace("line 5\n", "line five\n");
+
+        let diff = render_diff(&write_change(Some(&original), &content), false);
+
+        assert!(diff.starts_with("--- a/src/lib.rs\n+++ b/src/lib.rs\n"));
+        assert!(diff.contains("@@ -2,7 +2,7 @@\n"));
+        assert!(diff.contains(" 4  4 │ line 4\n"));
+        assert!(diff.contains(" 5    │-line 5\n"));
+        assert!(diff.contains("    5 │+line five\n"));
+        assert!(!diff.contains("line 1\n"));
+        assert!(!diff.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_render_new_file_colored() {
+        let diff = render_diff(&write_change(None, "fn main() {}\n"), true);
+
+        assert!(diff.contains("/dev/null"));
+        assert!(diff.contains(&format!("{}+fn main() {{}}{}", GREEN, RESET)));
+    }
+}
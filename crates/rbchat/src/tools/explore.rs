@@ -0,0 +1,247 @@
+//! This module provides the read-only file operations behind the exploration tools, which let
+//! the model look at the project on demand instead of receiving every file up front.
+//!
+//! All paths are resolved through the `FileSandbox`, so nothing outside of the project root
+//! can be read. Files and directories are filtered with `WalkBuilder` and its standard filters,
+//! exactly like `load_files_into_context`: entries excluded by `.gitignore` or hidden files are
+//! neither listed, searched nor read.
+//!
+//! The results are limited in size, so a single call cannot flood the context window.
+
+use crate::tools::file_sandbox::FileSandbox;
+use ignore::WalkBuilder;
+use regex::Regex;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of bytes `read_file` returns.
+const MAX_READ_BYTES: usize = 100_000;
+/// Maximum number of entries `list_directory` returns.
+const MAX_LIST_ENTRIES: usize = 1_000;
+/// Maximum number of matching lines `grep` returns.
+const MAX_GREP_MATCHES: usize = 200;
+
+/// Returns the content of a file, optionally limited to the lines `start_line` to `end_line`
+/// (1-based, inclusive).
+pub fn read_file(
+    sandbox: &FileSandbox,
+    path: &str,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+) -> Result<String, Box<dyn Error>> {
+    let resolved = visible_path(sandbox, path)?;
+    let content = fs::read_to_string(&resolved)
+        .map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+
+    let content = match (start_line, end_line) {
+        (None, None) => content,
+        (start, end) => {
+            let start = start.unwrap_or(1).max(1);
+            let end = end.unwrap_or(usize::MAX);
+            content
+                .lines()
+                .skip(start - 1)
+                .take(end.saturating_sub(start - 1))
+                .map(|line| format!("{}\n", line))
+                .collect()
+        }
+    };
+
+    if content.len() > MAX_READ_BYTES {
+        let mut cut = MAX_READ_BYTES;
+        while !content.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        return Ok(format!(
+            "{}\n[Truncated after {} bytes, request a line range to read the rest.]",
+            &content[..cut],
+            cut
+        ));
+    }
+    Ok(content)
+}
+
+/// Lists the entries of a directory relative to the project root. Directories end with `/`.
+pub fn list_directory(
+    sandbox: &FileSandbox,
+    path: &str,
+    recursive: bool,
+) -> Result<String, Box<dyn Error>> {
+    let resolved = visible_path(sandbox, path)?;
+    if !resolved.is_dir() {
+        return Err(format!("'{}' is not a directory.", path).into());
+    }
+
+    let mut walker = WalkBuilder::new(&resolved);
+    walker
+        .standard_filters(true)
+        .sort_by_file_name(|a, b| a.cmp(b));
+    if !recursive {
+        walker.max_depth(Some(1));
+    }
+
+    let mut out = String::new();
+    let mut count = 0;
+    for entry in walker.build() {
+        let entry = entry?;
+        if entry.path() == resolved {
+            continue;
+        }
+        if count == MAX_LIST_ENTRIES {
+            out.push_str("[More entries omitted, list a subdirectory to see them.]\n");
+            break;
+        }
+        let relative = relative(sandbox, entry.path());
+        if entry.path().is_dir() {
+            let _ = writeln!(out, "{}/", relative.display());
+        } else {
+            let _ = writeln!(out, "{}", relative.display());
+        }
+        count += 1;
+    }
+    if out.is_empty() {
+        out.push_str("The directory is empty.\n");
+    }
+    Ok(out)
+}
+
+/// Searches the files below `path` for lines matching the regular expression `pattern`.
+/// Each match is returned as `path:line: text` with a 1-based line number.
+pub fn grep(sandbox: &FileSandbox, pattern: &str, path: &str) -> Result<String, Box<dyn Error>> {
+    let regex = Regex::new(pattern).map_err(|e| format!("Invalid pattern: {}", e))?;
+    let resolved = visible_path(sandbox, path)?;
+
+    let mut out = String::new();
+    let mut count = 0;
+    let walker = WalkBuilder::new(&resolved)
+        .standard_filters(true)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    for entry in walker {
+        let entry = entry?;
+        if !entry.path().is_file() {
+            continue;
+        }
+        // Binary and otherwise unreadable files are skipped
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let relative = relative(sandbox, entry.path());
+        for (index, line) in content.lines().enumerate() {
+            if !regex.is_match(line) {
+                continue;
+            }
+            if count == MAX_GREP_MATCHES {
+                out.push_str("[More matches omitted, narrow the pattern or the path.]\n");
+                return Ok(out);
+            }
+            let _ = writeln!(out, "{}:{}: {}", relative.display(), index + 1, line);
+            count += 1;
+        }
+    }
+    if out.is_empty() {
+        out.push_str("No matches found.\n");
+    }
+    Ok(out)
+}
+
+/// Resolves `path` inside the sandbox and rejects it if `WalkBuilder` would skip it.
+fn visible_path(sandbox: &FileSandbox, path: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let resolved = sandbox.resolve(path)?;
+    if !resolved.exists() {
+        return Err(format!("'{}' does not exist.", path).into());
+    }
+    if !is_visible(sandbox.root(), &resolved) {
+        return Err(format!("'{}' is ignored and cannot be accessed.", path).into());
+    }
+    Ok(resolved)
+}
+
+/// Checks every component of `path` below `root` against the ignore rules of its parent.
+fn is_visible(root: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let mut current = root.to_path_buf();
+    for component in relative.components() {
+        let parent = current.clone();
+        current.push(component);
+        let listed = WalkBuilder::new(&parent)
+            .standard_filters(true)
+            .max_depth(Some(1))
+            .build()
+            .filter_map(Result::ok)
+            .any(|entry| entry.path() == current);
+        if !listed {
+            return false;
+        }
+    }
+    true
+}
+
+fn relative(sandbox: &FileSandbox, path: &Path) -> PathBuf {
+    path.strip_prefix(sandbox.root())
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project() -> (TempDir, FileSandbox) {
+        let root = TempDir::new().unwrap();
+        // .gitignore files are only honoured inside a git repository
+        fs::create_dir(root.path().join(".git")).unwrap();
+        fs::write(root.path().join(".gitignore"), "target/\n.env\n").unwrap();
+        fs::create_dir_all(root.path().join("src")).unwrap();
+        fs::create_dir_all(root.path().join("target")).unwrap();
+        fs::write(
+            root.path().join("src/main.rs"),
+            "fn main() {\n    run();\n}\n",
+        )
+        .unwrap();
+        fs::write(root.path().join("src/lib.rs"), "pub fn run() {}\n").unwrap();
+        fs::write(root.path().join("target/out.rs"), "fn run() {}\n").unwrap();
+        fs::write(root.path().join(".env"), "KEY=secret\n").unwrap();
+        let sandbox = FileSandbox::new(root.path().to_path_buf());
+        (root, sandbox)
+    }
+
+    #[test]
+    fn test_read_file_with_line_range() {
+        let (_root, sandbox) = project();
+
+        assert_eq!(
+            read_file(&sandbox, "src/main.rs", Some(2), Some(3)).unwrap(),
+            "    run();\n}\n"
+        );
+        assert!(read_file(&sandbox, "target/out.rs", None, None).is_err());
+        assert!(read_file(&sandbox, ".env", None, None).is_err());
+        assert!(read_file(&sandbox, "../outside.rs", None, None).is_err());
+    }
+
+    #[test]
+    fn test_list_directory_respects_gitignore() {
+        let (_root, sandbox) = project();
+
+        let listing = list_directory(&sandbox, ".", true).unwrap();
+
+        assert_eq!(listing, "src/\nsrc/lib.rs\nsrc/main.rs\n");
+    }
+
+    #[test]
+    fn test_grep_reports_path_and_line() {
+        let (_root, sandbox) = project();
+
+        let matches = grep(&sandbox, r"\brun\(", ".").unwrap();
+
+        assert_eq!(
+            matches,
+            "src/lib.rs:1: pub fn run() {}\nsrc/main.rs:2:     run();\n"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! This module provides utility functions to create directories and files,
+//! as well as to show differences between a specified file and new content
+//! in an external diff tool.
+//!
+//! The following functions are provided:
+//!
+//! - `show_diff_in_external_tool`: Opens the diff tool configured as `diff_tool`
+//!   for a specified file and the new content provided. The new content is written
+//!   to a temporary file, which is then opened alongside the original file.
+//!
+//! - `create_directory`: Creates a new directory at the specified path.
+//!
+//! - `create_file`: Creates or updates a file with the specified content.
+//!
+//! - `replace_lines`: Replaces a range of lines in a text.
+//!
+//! The writing functions are only used by the `FileSandbox`, which confines them to the
+//! project root and journals their changes.
+//!
+//! All functions return a Result type, encapsulating success or any errors that
+//! may occur during execution.
+//!
+//! This module is particularly useful for developers who need to manage files
+//! and directories, as well as compare file contents visually using tools like Beyond Compare.
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+use tempfile::NamedTempFile;
+
+/// Opens an external diff tool to compare an original file with newly provided content.
+/// The new content is stored in a temporary file, which the tool may modify, for example
+/// when the user merges only some of the changes.
+///
+/// # Arguments
+///
+/// * `command` - The diff tool to run, optionally followed by arguments, e.g. `bcomp` or `meld`.
+///   The original file and the temporary file are appended as the last two arguments.
+/// * `diff_file` - A string slice that holds the path to the original file.
+/// * `diff_content` - A string slice containing the new content to compare.
+///
+/// # Returns
+///
+/// Returns the content of the temporary file after the tool exited or an error if the
+/// tool could not be launched.
+pub fn show_diff_in_external_tool(
+    command: &str,
+    diff_file: &str,
+    diff_content: &str,
+) -> Result<String, Box<dyn Error>> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or("No diff tool configured")?;
+    println!("Opening diff for file {} in {}.", diff_file, program);
+    // Create a temporary file for the new content
+    let temp_file = NamedTempFile::new()?;
+
+    // Write the new content to the temporary file
+    fs::write(temp_file.path(), diff_content)?;
+
+    // Launch the diff tool with the original file and the temp file
+    let status = Command::new(program)
+        .args(parts)
+        .arg(diff_file)
+        .arg(temp_file.path())
+        .status() // This will block until the command is finished
+        .map_err(|e| format!("Failed to launch diff tool '{}': {}", program, e))?;
+
+    // Many diff tools report differences through their exit code, so this is no error
+    if !status.success() {
+        log::debug!("Diff tool '{}' exited with {}", program, status);
+    }
+
+    Ok(fs::read_to_string(temp_file.path())?)
+}
+
+/// Creates a new directory at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path to the directory to create.
+///
+/// # Returns
+///
+/// Returns a Result that is Ok(()) on success or an error if the operation fails.
+pub(crate) async fn create_directory(path: &str) -> Result<(), Box<dyn Error>> {
+    println!("Create directory {}", path);
+    fs::create_dir_all(path)?;
+    Ok(())
+}
+
+/// Replaces the lines from `start_line` (inclusive) to `end_line` (exclusive) of `original`
+/// with `new_content`. Every line of the result is terminated by a newline.
+pub(crate) fn replace_lines(
+    original: &str,
+    start_line: usize,
+    end_line: usize,
+    new_content: &str,
+) -> Result<String, Box<dyn Error>> {
+    let mut lines: Vec<&str> = original.lines().collect();
+
+    // Check for invalid range
+    if start_line > end_line || end_line > lines.len() {
+        return Err("Invalid line range specified.".into());
+    }
+
+    // Replace the lines in the specified range
+    lines.splice(start_line..end_line, new_content.lines());
+
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+/// Creates or updates a file with the specified content.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path to the file to create or update.
+/// * `content` - A string slice containing the content to write to the file.
+///
+/// # Returns
+///
+/// Returns a Result that is Ok(()) on success or an error if the operation fails.
+pub(crate) async fn create_file(path: &str, content: &str) -> Result<(), Box<dyn Error>> {
+    println!("Create or update file {}", path);
+    fs::write(path, content)?;
+    Ok(())
+}
@@ -0,0 +1,457 @@
+//! This module confines the file operations of tools to the project root.
+//!
+//! Every path supplied by the model is resolved against the root, which is the directory
+//! containing `.rusty/`. Absolute paths, `../` escapes and symbolic links leading outside
+//! of the root are rejected before anything is touched.
+//!
+//! Writes are described as a `FileChange` and handed to a `ChangeApprover` before they are
+//! applied, so the CLI can ask the user about each operation. Without an approver every write
+//! is denied; `AutoApprover` has to be set explicitly to apply changes unasked. The approver may also return
+//! an edited version of the content, which is written instead. In dry-run mode the changes
+//! are only passed to the approver to be shown. With a `ChangeJournal` attached, the prior
+//! state of every changed file is recorded, so the run can be undone.
+
+use crate::tools::change_journal::ChangeJournal;
+use crate::tools::file_diff::{create_directory, create_file, replace_lines};
+use similar::TextDiff;
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A file operation requested by a tool. Paths are relative to the project root.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileChange {
+    CreateDirectory {
+        path: PathBuf,
+    },
+    WriteFile {
+        path: PathBuf,
+        /// Content of the file before the change, `None` if the file does not exist yet.
+        original: Option<String>,
+        content: String,
+    },
+}
+
+impl FileChange {
+    pub fn path(&self) -> &Path {
+        match self {
+            FileChange::CreateDirectory { path } => path,
+            FileChange::WriteFile { path, .. } => path,
+        }
+    }
+
+    /// Renders the change as a unified diff in the format used by git.
+    pub fn unified_diff(&self) -> String {
+        match self {
+            FileChange::CreateDirectory { path } => {
+                format!("new directory {}\n", path.display())
+            }
+            FileChange::WriteFile {
+                path,
+                original,
+                content,
+            } => {
+                let new_name = format!("b/{}", path.display());
+                let old_name = if original.is_some() {
+                    format!("a/{}", path.display())
+                } else {
+                    "/dev/null".to_string()
+                };
+                TextDiff::from_lines(original.as_deref().unwrap_or_default(), content.as_str())
+                    .unified_diff()
+                    .header(&old_name, &new_name)
+                    .to_string()
+            }
+        }
+    }
+}
+
+/// The answer of a `ChangeApprover` to a proposed change.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Review {
+    Accept,
+    Reject,
+    /// Apply the change with the given content instead of the proposed one.
+    Edit(String),
+}
+
+/// Decides whether a file change may be applied.
+pub trait ChangeApprover: Send + Sync {
+    fn approve(&self, change: &FileChange) -> Result<bool, Box<dyn Error>>;
+
+    /// Reviews a change. Approvers that let the user edit the content override this.
+    fn review(&self, change: &FileChange) -> Result<Review, Box<dyn Error>> {
+        Ok(if self.approve(change)? {
+            Review::Accept
+        } else {
+            Review::Reject
+        })
+    }
+
+    /// Shows a change that is not applied because of a dry run. Nothing is shown by default.
+    fn preview(&self, _change: &FileChange) {}
+}
+
+/// Denies every change, the approver of a sandbox until another one is set.
+pub struct DenyApprover;
+
+impl ChangeApprover for DenyApprover {
+    fn approve(&self, change: &FileChange) -> Result<bool, Box<dyn Error>> {
+        Err(format!(
+            "Changing {} is not allowed, no approver is set.",
+            change.path().display()
+        )
+        .into())
+    }
+}
+
+/// Approves every change, used when no one can be asked.
+pub struct AutoApprover;
+
+impl ChangeApprover for AutoApprover {
+    fn approve(&self, _change: &FileChange) -> Result<bool, Box<dyn Error>> {
+        Ok(true)
+    }
+}
+
+/// Confines tool file operations to a root directory.
+pub struct FileSandbox {
+    root: PathBuf,
+    approver: Box<dyn ChangeApprover>,
+    dry_run: bool,
+    journal: Option<ChangeJournal>,
+}
+
+impl FileSandbox {
+    pub fn new(root: PathBuf) -> Self {
+        // Compare against the canonical root so symbolic links inside the path are detected
+        let root = root.canonicalize().unwrap_or(root);
+        FileSandbox {
+            root,
+            approver: Box::new(DenyApprover),
+            dry_run: false,
+            journal: None,
+        }
+    }
+
+    pub fn approver(mut self, approver: Box<dyn ChangeApprover>) -> Self {
+        self.approver = approver;
+        self
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn journal(mut self, journal: ChangeJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolves a path given by the model to an absolute path inside the root.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let outside = || format!("Path '{}' is outside of the project root.", path);
+        let requested = Path::new(path);
+        let joined = if requested.is_absolute() {
+            requested.to_path_buf()
+        } else {
+            self.root.join(requested)
+        };
+
+        let mut resolved = PathBuf::new();
+        for component in joined.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !resolved.pop() {
+                        return Err(outside().into());
+                    }
+                }
+                component => resolved.push(component),
+            }
+        }
+        if !resolved.starts_with(&self.root) {
+            return Err(outside().into());
+        }
+
+        // The existing part of the path may contain symbolic links pointing elsewhere. A link
+        // whose target does not exist is rejected too, writing to it would create the target.
+        let mut existing = self.root.clone();
+        for component in resolved.strip_prefix(&self.root)?.components() {
+            existing.push(component);
+            match fs::symlink_metadata(&existing) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    let target = existing.canonicalize().map_err(|_| outside())?;
+                    if !target.starts_with(&self.root) {
+                        return Err(outside().into());
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        Ok(resolved)
+    }
+
+    pub async fn create_directory(&self, path: &str) -> Result<String, Box<dyn Error>> {
+        let resolved = self.resolve(path)?;
+        let change = FileChange::CreateDirectory {
+            path: self.relative(&resolved),
+        };
+        self.apply(change).await
+    }
+
+    pub async fn write_file(&self, path: &str, content: &str) -> Result<String, Box<dyn Error>> {
+        let resolved = self.resolve(path)?;
+        let change = FileChange::WriteFile {
+            path: self.relative(&resolved),
+            original: fs::read_to_string(&resolved).ok(),
+            content: content.to_string(),
+        };
+        self.apply(change).await
+    }
+
+    pub async fn update_file_section(
+        &self,
+        path: &str,
+        start_line: usize,
+        end_line: usize,
+        new_content: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let resolved = self.resolve(path)?;
+        let original = fs::read_to_string(&resolved)?;
+        let content = replace_lines(&original, start_line, end_line, new_content)?;
+        let change = FileChange::WriteFile {
+            path: self.relative(&resolved),
+            original: Some(original),
+            content,
+        };
+        self.apply(change).await
+    }
+
+    async fn apply(&self, mut change: FileChange) -> Result<String, Box<dyn Error>> {
+        let path = change.path().display().to_string();
+        if self.dry_run {
+            self.approver.preview(&change);
+            return Ok(format!("Dry run, the change to {} was not applied.", path));
+        }
+        let mut edited = false;
+        match self.approver.review(&change)? {
+            Review::Accept => {}
+            Review::Reject => {
+                return Err(format!("The user rejected the change to {}.", path).into());
+            }
+            Review::Edit(new_content) => {
+                if let FileChange::WriteFile { content, .. } = &mut change {
+                    *content = new_content;
+                    edited = true;
+                }
+            }
+        }
+
+        if let Some(journal) = &self.journal {
+            journal.record(&change, &self.root)?;
+        }
+
+        let target = self.root.join(change.path());
+        let target = target.to_string_lossy();
+        match &change {
+            FileChange::CreateDirectory { .. } => {
+                create_directory(&target).await?;
+                Ok(format!("Created directory {}", path))
+            }
+            FileChange::WriteFile { content, .. } => {
+                create_file(&target, content).await?;
+                if edited {
+                    // Let the model know its proposal was not written verbatim
+                    Ok(format!(
+                        "Wrote file {} with changes made by the user:\n{}",
+                        path, content
+                    ))
+                } else {
+                    Ok(format!("Wrote file {}", path))
+                }
+            }
+        }
+    }
+
+    fn relative(&self, resolved: &Path) -> PathBuf {
+        resolved
+            .strip_prefix(&self.root)
+            .unwrap_or(resolved)
+            .to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    struct RecordingApprover {
+        answer: bool,
+        changes: Arc<Mutex<Vec<FileChange>>>,
+    }
+
+    impl ChangeApprover for RecordingApprover {
+        fn approve(&self, change: &FileChange) -> Result<bool, Box<dyn Error>> {
+            self.changes.lock().unwrap().push(change.clone());
+            Ok(self.answer)
+        }
+
+        fn preview(&self, change: &FileChange) {
+            self.changes.lock().unwrap().push(change.clone());
+        }
+    }
+
+    #[test]
+    fn test_resolve_rejects_paths_outside_of_root() {
+        let root = TempDir::new().unwrap();
+        let sandbox = FileSandbox::new(root.path().to_path_buf());
+
+        assert!(sandbox.resolve("src/main.rs").is_ok());
+        assert!(sandbox.resolve("src/../Cargo.toml").is_ok());
+        assert!(sandbox.resolve("../outside.txt").is_err());
+        assert!(sandbox.resolve("src/../../outside.txt").is_err());
+        assert!(sandbox.resolve("/etc/passwd").is_err());
+        let inside = sandbox.root().join("inside.txt");
+        assert!(sandbox.resolve(inside.to_str().unwrap()).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_rejects_symlink_escape() {
+        let root = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.path().join("link")).unwrap();
+        let sandbox = FileSandbox::new(root.path().to_path_buf());
+
+        assert!(sandbox.resolve("link/file.txt").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_rejects_dangling_symlink() {
+        let root = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let target = outside.path().join("new.txt");
+        std::os::unix::fs::symlink(&target, root.path().join("link")).unwrap();
+        let sandbox = FileSandbox::new(root.path().to_path_buf());
+
+        assert!(sandbox.resolve("link").is_err());
+        assert!(sandbox.resolve("./link").is_err());
+        assert!(!target.exists());
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_denied_without_approver() {
+        let root = TempDir::new().unwrap();
+        let sandbox = FileSandbox::new(root.path().to_path_buf());
+
+        assert!(sandbox.write_file("new.txt", "content").await.is_err());
+        assert!(sandbox.create_directory("dir").await.is_err());
+        assert!(!root.path().join("new.txt").exists());
+        assert!(!root.path().join("dir").exists());
+
+        let sandbox = sandbox.approver(Box::new(AutoApprover));
+        sandbox.write_file("new.txt", "content").await.unwrap();
+        assert!(root.path().join("new.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_write_file_asks_approver() {
+        let root = TempDir::new().unwrap();
+        fs::write(root.path().join("a.txt"), "one\ntwo\n").unwrap();
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let sandbox =
+            FileSandbox::new(root.path().to_path_buf()).approver(Box::new(RecordingApprover {
+                answer: true,
+                changes: changes.clone(),
+            }));
+
+        sandbox
+            .update_file_section("a.txt", 1, 2, "three")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(root.path().join("a.txt")).unwrap(),
+            "one\nthree\n"
+        );
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes.len(), 1);
+        let diff = changes[0].unified_diff();
+        assert!(diff.contains("--- a/a.txt"));
+        assert!(diff.contains("-two"));
+        assert!(diff.contains("+three"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_change_is_not_applied() {
+        let root = TempDir::new().unwrap();
+        let sandbox =
+            FileSandbox::new(root.path().to_path_buf()).approver(Box::new(RecordingApprover {
+                answer: false,
+                changes: Default::default(),
+            }));
+
+        let result = sandbox.write_file("new.txt", "content").await;
+
+        assert!(result.is_err());
+        assert!(!root.path().join("new.txt").exists());
+    }
+
+    struct EditingApprover;
+
+    impl ChangeApprover for EditingApprover {
+        fn approve(&self, _change: &FileChange) -> Result<bool, Box<dyn Error>> {
+            Ok(true)
+        }
+
+        fn review(&self, _change: &FileChange) -> Result<Review, Box<dyn Error>> {
+            Ok(Review::Edit("edited\n".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_edited_content_is_written() {
+        let root = TempDir::new().unwrap();
+        let sandbox =
+            FileSandbox::new(root.path().to_path_buf()).approver(Box::new(EditingApprover));
+
+        let result = sandbox.write_file("new.txt", "proposed\n").await.unwrap();
+
+        assert!(result.contains("changes made by the user"));
+        assert_eq!(
+            fs::read_to_string(root.path().join("new.txt")).unwrap(),
+            "edited\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_does_not_write() {
+        let root = TempDir::new().unwrap();
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let sandbox = FileSandbox::new(root.path().to_path_buf())
+            .approver(Box::new(RecordingApprover {
+                answer: false,
+                changes: changes.clone(),
+            }))
+            .dry_run(true);
+
+        let result = sandbox.write_file("new.txt", "content").await.unwrap();
+        sandbox.create_directory("dir").await.unwrap();
+
+        assert!(result.starts_with("Dry run"));
+        assert!(!root.path().join("new.txt").exists());
+        assert!(!root.path().join("dir").exists());
+        // The changes were shown, not reviewed
+        assert_eq!(changes.lock().unwrap().len(), 2);
+    }
+}
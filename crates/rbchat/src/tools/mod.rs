@@ -0,0 +1,35 @@
+//! This module provides the tools the AI model can call while answering, independent
+//! of the backend in use. Tools implement the `Tool` trait and are collected in a
+//! `ToolRegistry`; every backend translates the registered tools into its own wire format.
+//!
+//! ## Structure
+//! - **Tool:** The trait every tool implements, together with `ToolDefinition` and `RegisterableTool`.
+//! - **Tool Registry:** Holds the available tools and executes the calls requested by the model.
+//! - **Builtin:** The file tools shipped with Rusty Buddy (`read_file`, `list_directory`, `grep`,
+//!   `create_file`, `create_directory`, `show_diff`, `update_file_section`) and
+//!   `initialize_tools` to register them.
+//! - **Explore:** The read-only file operations behind `read_file`, `list_directory` and `grep`.
+//! - **File Diff:** The file operations used by the builtin tools.
+//! - **Diff View:** Renders a `FileChange` as a colored unified diff with line numbers.
+//! - **File Sandbox:** Confines the file operations to the project root and asks a
+//!   `ChangeApprover` before anything is written.
+//! - **Change Journal:** Records the prior state of changed files and undoes the last run.
+pub mod builtin;
+pub mod change_journal;
+pub mod diff_view;
+pub mod explore;
+pub mod file_diff;
+pub mod file_sandbox;
+pub mod tool;
+pub mod tool_registry;
+
+pub use builtin::{initialize_read_only_tools, initialize_tools};
+pub use change_journal::{
+    list_runs, undo_last_run, undo_last_run_summary, ChangeJournal, JournalRun,
+};
+pub use diff_view::render_diff;
+pub use file_sandbox::{
+    AutoApprover, ChangeApprover, DenyApprover, FileChange, FileSandbox, Review,
+};
+pub use tool::{RegisterableTool, Tool, ToolDefinition};
+pub use tool_registry::ToolRegistry;
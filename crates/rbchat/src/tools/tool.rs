@@ -0,0 +1,96 @@
+//! This module defines the traits for tools the AI model can call during a chat.
+//! The `Tool` trait describes a tool through its name, description and JSON schema
+//! and executes it with the arguments chosen by the model.
+//! The `RegisterableTool` trait defines a method for registering tools
+//! in a `ToolRegistry`, which manages the available tools.
+//!
+//! # Traits
+//!
+//! ## Tool
+//!
+//! To create a new tool, implement the `Tool` trait. The returned string is handed
+//! back to the model as the result of the call. Tools that touch files go through the
+//! `FileSandbox` passed to `execute`, which keeps them inside the project root.
+//!
+//! ```rust
+//! use async_trait::async_trait;
+//! use rbchat::tools::{FileSandbox, Tool};
+//! use serde_json::{json, Value};
+//! use std::error::Error;
+//!
+//! pub struct EchoTool;
+//!
+//! #[async_trait]
+//! impl Tool for EchoTool {
+//!     fn name(&self) -> &str {
+//!         "echo"
+//!     }
+//!
+//!     fn description(&self) -> &str {
+//!         "Returns the given text."
+//!     }
+//!
+//!     fn parameters(&self) -> Value {
+//!         json!({
+//!             "type": "object",
+//!             "properties": { "text": { "type": "string" } },
+//!             "required": ["text"]
+//!         })
+//!     }
+//!
+//!     async fn execute(
+//!         &self,
+//!         arguments: Value,
+//!         _sandbox: &FileSandbox,
+//!     ) -> Result<String, Box<dyn Error>> {
+//!         Ok(arguments["text"].as_str().unwrap_or_default().to_string())
+//!     }
+//! }
+//! ```
+//!
+//! ## RegisterableTool
+//!
+//! This trait should be implemented by tools that register themselves in the tool registry.
+
+use crate::tools::file_sandbox::FileSandbox;
+use crate::tools::tool_registry::ToolRegistry;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::error::Error;
+
+/// Provider independent description of a tool. Each backend translates it
+/// into the wire format of its API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// JSON schema of the arguments object the tool expects.
+    fn parameters(&self) -> Value;
+
+    async fn execute(
+        &self,
+        arguments: Value,
+        sandbox: &FileSandbox,
+    ) -> Result<String, Box<dyn Error>>;
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: self.parameters(),
+        }
+    }
+}
+
+pub trait RegisterableTool {
+    fn register_with_registry(registry: &mut ToolRegistry);
+}
@@ -0,0 +1,149 @@
+use crate::chat::interface::ToolCall;
+use crate::config;
+use crate::tools::file_sandbox::FileSandbox;
+use crate::tools::tool::{Tool, ToolDefinition};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// The `ToolRegistry` struct is responsible for managing the tools the AI model may call.
+/// It works like the `CommandRegistry`: tools are registered under their name and
+/// looked up when the model requests a call.
+///
+/// The `ToolRegistry` offers the following functionalities:
+/// - `new`: Creates a new empty `ToolRegistry` whose tools are confined to the project root.
+///   Its sandbox denies every write; use `with_sandbox` to set a `ChangeApprover`.
+/// - `with_sandbox`: Creates a new empty `ToolRegistry` using the given `FileSandbox`.
+/// - `register_tool`: Adds a tool to the registry, replacing a tool of the same name.
+/// - `definitions`: Returns the definitions of all tools, which backends send to the model.
+/// - `execute_tool`: Executes the tool requested by a `ToolCall` and returns its result.
+pub struct ToolRegistry {
+    tools: HashMap<String, Box<dyn Tool>>,
+    sandbox: FileSandbox,
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        // Outside of a configured project the current directory is the root
+        let root = config::get_project_root()
+            .ok()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        Self::with_sandbox(FileSandbox::new(root))
+    }
+
+    pub fn with_sandbox(sandbox: FileSandbox) -> Self {
+        ToolRegistry {
+            tools: HashMap::new(),
+            sandbox,
+        }
+    }
+
+    pub fn register_tool(&mut self, tool: Box<dyn Tool>) {
+        self.tools.insert(tool.name().to_string(), tool);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Returns the definitions sorted by name, so requests stay stable between calls.
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        let mut definitions: Vec<ToolDefinition> =
+            self.tools.values().map(|tool| tool.definition()).collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
+    pub async fn execute_tool(&self, tool_call: &ToolCall) -> Result<String, Box<dyn Error>> {
+        let tool = self
+            .tools
+            .get(tool_call.name.as_str())
+            .ok_or_else(|| format!("Tool '{}' not found", tool_call.name))?;
+        let arguments: Value = if tool_call.arguments.trim().is_empty() {
+            Value::Object(Default::default())
+        } else {
+            serde_json::from_str(&tool_call.arguments)?
+        };
+        tool.execute(arguments, &self.sandbox).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    struct UpperTool;
+
+    #[async_trait]
+    impl Tool for UpperTool {
+        fn name(&self) -> &str {
+            "upper"
+        }
+
+        fn description(&self) -> &str {
+            "Converts text to upper case."
+        }
+
+        fn parameters(&self) -> Value {
+            json!({
+                "type": "object",
+                "properties": { "text": { "type": "string" } },
+                "required": ["text"]
+            })
+        }
+
+        async fn execute(
+            &self,
+            arguments: Value,
+            _sandbox: &FileSandbox,
+        ) -> Result<String, Box<dyn Error>> {
+            Ok(arguments["text"]
+                .as_str()
+                .unwrap_or_default()
+                .to_uppercase())
+        }
+    }
+
+    fn tool_call(name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_registered_tool() {
+        let mut registry = ToolRegistry::new();
+        registry.register_tool(Box::new(UpperTool));
+
+        let definitions = registry.definitions();
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].name, "upper");
+        assert_eq!(definitions[0].parameters["required"][0], "text");
+
+        let result = registry
+            .execute_tool(&tool_call("upper", r#"{"text":"hello"}"#))
+            .await
+            .unwrap();
+        assert_eq!(result, "HELLO");
+    }
+
+    #[tokio::test]
+    async fn test_execute_unknown_tool() {
+        let registry = ToolRegistry::new();
+
+        let result = registry.execute_tool(&tool_call("missing", "{}")).await;
+
+        assert!(result.unwrap_err().to_string().contains("missing"));
+    }
+}